@@ -1,6 +1,9 @@
 use criterion::{Criterion, criterion_group, criterion_main};
-use std::path::Path;
-use venv_rs_lib::dir_size::{Chonk, IterativeReader, ParallelReader, RecursiveReader};
+use std::{fs, path::Path};
+use tempfile::TempDir;
+use venv_rs_lib::dir_size::{
+    Chonk, HardlinkReader, IterativeReader, ParallelReader, RecursiveReader, StealingReader,
+};
 
 fn bench_recursive(c: &mut Criterion) {
     let path = Path::new(".");
@@ -35,5 +38,95 @@ fn bench_parallel(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, bench_recursive, bench_iterative, bench_parallel);
+fn bench_stealing(c: &mut Criterion) {
+    let path = Path::new(".");
+    let method = StealingReader::default();
+
+    c.bench_function("stealing dir size", |b| {
+        b.iter(|| {
+            let _ = method.get_dir_size(path).unwrap();
+        })
+    });
+}
+
+/// Builds a tree shaped like a venv with a few large packages: nested subpackages with sources
+/// and bytecode, tests, native extensions, bundled libraries and dist-info directories.
+fn venv_tree() -> TempDir {
+    let root = tempfile::tempdir().unwrap();
+    let bin = root.path().join("bin");
+    fs::create_dir_all(&bin).unwrap();
+    for script in ["python", "pip", "activate"] {
+        fs::write(bin.join(script), "#!/bin/sh\n").unwrap();
+    }
+    fs::write(root.path().join("pyvenv.cfg"), "version = 3.13.2\n").unwrap();
+
+    let site_packages = root.path().join("lib/python3.13/site-packages");
+    for pkg in 0..40 {
+        let name = format!("pkg{pkg}");
+        for sub in 0..8 {
+            let dir = site_packages.join(&name).join(format!("sub{sub}"));
+            let pycache = dir.join("__pycache__");
+            let tests = dir.join("tests");
+            fs::create_dir_all(&pycache).unwrap();
+            fs::create_dir_all(&tests).unwrap();
+            for module in 0..12 {
+                fs::write(dir.join(format!("m{module}.py")), "x = 1\n".repeat(50)).unwrap();
+                fs::write(
+                    pycache.join(format!("m{module}.cpython-313.pyc")),
+                    [0u8; 600],
+                )
+                .unwrap();
+            }
+            fs::write(tests.join("test_sub.py"), "def test(): ...\n").unwrap();
+            fs::write(
+                dir.join("_ext.cpython-313-x86_64-linux-gnu.so"),
+                [0u8; 4096],
+            )
+            .unwrap();
+        }
+        let libs = site_packages.join(format!("{name}.libs"));
+        fs::create_dir_all(&libs).unwrap();
+        fs::write(libs.join("libbundled.so"), [0u8; 8192]).unwrap();
+
+        let dist_info = site_packages.join(format!("{name}-1.0.dist-info"));
+        fs::create_dir_all(&dist_info).unwrap();
+        for file in ["METADATA", "RECORD", "WHEEL", "INSTALLER"] {
+            fs::write(dist_info.join(file), "Name: pkg\n").unwrap();
+        }
+    }
+    root
+}
+
+fn bench_venv_tree(c: &mut Criterion) {
+    let tree = venv_tree();
+    let path = tree.path();
+    let mut group = c.benchmark_group("venv-shaped tree");
+
+    group.bench_function("recursive", |b| {
+        b.iter(|| RecursiveReader.get_dir_size(path).unwrap())
+    });
+    group.bench_function("iterative", |b| {
+        b.iter(|| IterativeReader.get_dir_size(path).unwrap())
+    });
+    group.bench_function("parallel", |b| {
+        b.iter(|| ParallelReader.get_dir_size(path).unwrap())
+    });
+    group.bench_function("hardlink", |b| {
+        b.iter(|| HardlinkReader::new().get_dir_size(path).unwrap())
+    });
+    group.bench_function("stealing", |b| {
+        let reader = StealingReader::default();
+        b.iter(|| reader.get_dir_size(path).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_recursive,
+    bench_iterative,
+    bench_parallel,
+    bench_stealing,
+    bench_venv_tree
+);
 criterion_main!(benches);
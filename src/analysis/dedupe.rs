@@ -0,0 +1,433 @@
+//! Replacing identical files of duplicated distributions with hardlinks.
+
+use std::{
+    collections::HashMap,
+    fs::{self, Metadata},
+    io,
+    path::{Component, Path, PathBuf},
+};
+
+use rayon::prelude::*;
+
+use super::DuplicateGroup;
+use crate::venv::record::{RecordEntry, file_digest, read_record};
+
+/// A file that can be replaced with a hardlink to the same file of another copy.
+#[derive(Debug, Clone)]
+pub struct Link {
+    /// File that is kept
+    pub source: PathBuf,
+    /// File that is replaced
+    pub target: PathBuf,
+    pub size: u64,
+    /// Space freed by the link. Zero when other links keep the data of the target around.
+    pub freed: u64,
+}
+
+/// A file that is left alone.
+#[derive(Debug, Clone)]
+pub struct Skipped {
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+/// Links for the copies of one distribution, every copy is linked to the first one.
+#[derive(Debug, Clone)]
+pub struct PackagePlan {
+    pub name: String,
+    pub version: String,
+    /// Venvs that have a copy, they need to be parsed again after linking
+    pub venvs: Vec<PathBuf>,
+    pub links: Vec<Link>,
+    pub skipped: Vec<Skipped>,
+}
+
+impl PackagePlan {
+    pub fn freed(&self) -> u64 {
+        self.links.iter().map(|l| l.freed).sum()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DedupePlan {
+    pub packages: Vec<PackagePlan>,
+}
+
+impl DedupePlan {
+    pub fn links(&self) -> impl Iterator<Item = &Link> {
+        self.packages.iter().flat_map(|p| &p.links)
+    }
+
+    pub fn freed(&self) -> u64 {
+        self.packages.iter().map(PackagePlan::freed).sum()
+    }
+}
+
+/// Result of applying a [`DedupePlan`].
+#[derive(Debug, Default)]
+pub struct DedupeOutcome {
+    pub linked: usize,
+    pub freed: u64,
+    pub failed: Vec<Skipped>,
+}
+
+/// Finds the files of duplicated distributions that can be hardlinked. `groups` should come from
+/// [`find_duplicates`](super::find_duplicates) with hashes compared. A file is only linked when
+/// both copies match the sha256 in their `RECORD`, so files modified after install are never
+/// shared. Files outside site-packages, like scripts, are left alone since their shebangs
+/// point into their own venv.
+pub fn plan_dedupe(groups: &[DuplicateGroup]) -> DedupePlan {
+    let packages = groups
+        .iter()
+        .map(plan_package)
+        .filter(|p| !p.links.is_empty() || !p.skipped.is_empty())
+        .collect();
+    DedupePlan { packages }
+}
+
+/// Replaces the targets of the plan with hardlinks. Each link is made under a temporary name
+/// and renamed over the target, so the target is never missing.
+pub fn apply_dedupe(plan: &DedupePlan) -> DedupeOutcome {
+    let mut outcome = DedupeOutcome::default();
+    for link in plan.links() {
+        match replace_with_link(&link.source, &link.target) {
+            Ok(()) => {
+                outcome.linked += 1;
+                outcome.freed += link.freed;
+            }
+            Err(e) => outcome.failed.push(Skipped {
+                path: link.target.clone(),
+                reason: e.to_string(),
+            }),
+        }
+    }
+    outcome
+}
+
+fn plan_package(group: &DuplicateGroup) -> PackagePlan {
+    let mut plan = PackagePlan {
+        name: group.name.clone(),
+        version: group.version.clone(),
+        venvs: group.copies.iter().map(|c| c.venv_path.clone()).collect(),
+        links: Vec::new(),
+        skipped: Vec::new(),
+    };
+
+    let mut copies = Vec::new();
+    for copy in &group.copies {
+        let Some(site_packages) = copy.dist_info.parent() else {
+            continue;
+        };
+        match read_record(&copy.dist_info) {
+            Ok(record) => copies.push((site_packages, record_hashes(record))),
+            Err(e) => plan.skipped.push(Skipped {
+                path: copy.dist_info.clone(),
+                reason: e.to_string(),
+            }),
+        }
+    }
+    let Some(((source_dir, source_record), targets)) = copies.split_first() else {
+        return plan;
+    };
+
+    let results: Vec<(Vec<Link>, Vec<Skipped>)> = source_record
+        .par_iter()
+        .map(|(path, digest)| plan_file(path, digest, source_dir, targets))
+        .collect();
+    for (links, skipped) in results {
+        plan.links.extend(links);
+        plan.skipped.extend(skipped);
+    }
+    count_freed(&mut plan.links);
+    plan
+}
+
+/// Paths inside site-packages and their sha256 digests.
+fn record_hashes(record: Vec<RecordEntry>) -> HashMap<String, String> {
+    record
+        .into_iter()
+        .filter(|e| {
+            Path::new(&e.path)
+                .components()
+                .all(|c| matches!(c, Component::Normal(_)))
+        })
+        .filter_map(|e| {
+            let hash = e.hash?;
+            (hash.algorithm == "sha256").then_some((e.path, hash.digest))
+        })
+        .collect()
+}
+
+fn plan_file(
+    path: &str,
+    digest: &str,
+    source_dir: &Path,
+    targets: &[(&Path, HashMap<String, String>)],
+) -> (Vec<Link>, Vec<Skipped>) {
+    let mut links = Vec::new();
+    let mut skipped = Vec::new();
+    let mut skip = |path: PathBuf, reason: &str| {
+        skipped.push(Skipped {
+            path,
+            reason: reason.to_string(),
+        })
+    };
+
+    let source = source_dir.join(path);
+    let source_meta = match verified_meta(&source, digest) {
+        Ok(meta) => meta,
+        Err(reason) => {
+            skip(source, reason);
+            return (links, skipped);
+        }
+    };
+
+    for (target_dir, target_record) in targets {
+        let target = target_dir.join(path);
+        if target_record.get(path).map(String::as_str) != Some(digest) {
+            skip(target, "not in RECORD with the same hash");
+            continue;
+        }
+        let target_meta = match fs::symlink_metadata(&target) {
+            Ok(meta) => meta,
+            Err(_) => {
+                skip(target, "missing");
+                continue;
+            }
+        };
+        if let (Some(s), Some(t)) = (file_id(&source_meta), file_id(&target_meta)) {
+            if s.ino == t.ino && s.dev == t.dev {
+                continue;
+            }
+            if s.dev != t.dev {
+                skip(target, "on another filesystem");
+                continue;
+            }
+            // the link would hand the target over to the owner of the source
+            if s.uid != t.uid || s.gid != t.gid {
+                skip(target, "different owner");
+                continue;
+            }
+        }
+        if source_meta.permissions() != target_meta.permissions() {
+            skip(target, "different permissions");
+            continue;
+        }
+        if let Err(reason) = verified_meta(&target, digest) {
+            skip(target, reason);
+            continue;
+        }
+        links.push(Link {
+            source: source.clone(),
+            target,
+            size: target_meta.len(),
+            freed: 0,
+        });
+    }
+    (links, skipped)
+}
+
+/// Metadata of a regular file whose contents still match the digest from `RECORD`.
+fn verified_meta(path: &Path, digest: &str) -> Result<Metadata, &'static str> {
+    let meta = fs::symlink_metadata(path).map_err(|_| "missing")?;
+    if !meta.is_file() {
+        return Err("not a regular file");
+    }
+    match file_digest(path) {
+        Ok(d) if d == digest => Ok(meta),
+        Ok(_) => Err("modified since install"),
+        Err(_) => Err("unreadable"),
+    }
+}
+
+/// The data of a target is only freed once every link to it is replaced.
+fn count_freed(links: &mut [Link]) {
+    let mut replaced: HashMap<(u64, u64), (u64, u64)> = HashMap::new();
+    for link in links.iter_mut() {
+        let Some(id) = fs::symlink_metadata(&link.target)
+            .ok()
+            .and_then(|m| file_id(&m))
+        else {
+            link.freed = link.size;
+            continue;
+        };
+        let (count, nlink) = replaced.entry((id.dev, id.ino)).or_insert((0, id.nlink));
+        *count += 1;
+        if *count == *nlink {
+            link.freed = link.size;
+        }
+    }
+}
+
+fn replace_with_link(source: &Path, target: &Path) -> io::Result<()> {
+    let file_name = target
+        .file_name()
+        .ok_or_else(|| io::Error::other("target has no file name"))?;
+    let mut tmp_name = std::ffi::OsString::from(".");
+    tmp_name.push(file_name);
+    tmp_name.push(".venv-rs-dedupe");
+    let tmp = target.with_file_name(tmp_name);
+
+    match fs::remove_file(&tmp) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+    fs::hard_link(source, &tmp)?;
+    fs::rename(&tmp, target).inspect_err(|_| {
+        let _ = fs::remove_file(&tmp);
+    })
+}
+
+struct FileId {
+    dev: u64,
+    ino: u64,
+    nlink: u64,
+    uid: u32,
+    gid: u32,
+}
+
+#[cfg(unix)]
+fn file_id(meta: &Metadata) -> Option<FileId> {
+    use std::os::unix::fs::MetadataExt;
+    Some(FileId {
+        dev: meta.dev(),
+        ino: meta.ino(),
+        nlink: meta.nlink(),
+        uid: meta.uid(),
+        gid: meta.gid(),
+    })
+}
+
+#[cfg(not(unix))]
+fn file_id(_meta: &Metadata) -> Option<FileId> {
+    None
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::{os::unix::fs::MetadataExt, sync::Arc};
+
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::{
+        analysis::find_duplicates,
+        venv::{
+            Venv,
+            test_utils::{mock_package, mock_venv},
+        },
+    };
+
+    fn venv_with_alpha(root: &Path, name: &str) -> Arc<Venv> {
+        let path = root.join(name);
+        fs::create_dir(&path).unwrap();
+        let site_packages = mock_venv(&path);
+        mock_package(
+            &site_packages,
+            "alpha",
+            "1.0",
+            &[
+                ("alpha/__init__.py", "a = 1\n".repeat(100).as_str()),
+                ("alpha/core.py", "b = 2\n"),
+            ],
+        );
+        Arc::new(Venv::from_path(&path).unwrap())
+    }
+
+    fn ino(path: &Path) -> u64 {
+        fs::metadata(path).unwrap().ino()
+    }
+
+    #[test]
+    fn test_dedupe() {
+        let root = tempdir().unwrap();
+        let venvs = vec![
+            venv_with_alpha(root.path(), "first"),
+            venv_with_alpha(root.path(), "second"),
+            venv_with_alpha(root.path(), "third"),
+        ];
+        let init = |venv: &str| {
+            venvs
+                .iter()
+                .find(|v| v.name == venv)
+                .unwrap()
+                .site_packages()
+                .unwrap()
+                .join("alpha/__init__.py")
+        };
+
+        let plan = plan_dedupe(&find_duplicates(&venvs, true));
+        assert_eq!(plan.links().count(), 4);
+        assert_eq!(plan.freed(), 2 * (600 + 6));
+        assert!(plan.packages[0].skipped.is_empty());
+        // planning alone changes nothing
+        assert_ne!(ino(&init("first")), ino(&init("second")));
+
+        let outcome = apply_dedupe(&plan);
+        assert!(outcome.failed.is_empty());
+        assert_eq!(outcome.linked, 4);
+        assert_eq!(outcome.freed, plan.freed());
+        assert_eq!(ino(&init("first")), ino(&init("second")));
+        assert_eq!(ino(&init("first")), ino(&init("third")));
+        assert_eq!(
+            fs::read_to_string(init("third")).unwrap(),
+            "a = 1\n".repeat(100)
+        );
+
+        // nothing left to link
+        let plan = plan_dedupe(&find_duplicates(&venvs, true));
+        assert_eq!(plan.links().count(), 0);
+    }
+
+    #[test]
+    fn test_modified_file_is_skipped() {
+        let root = tempdir().unwrap();
+        let first = venv_with_alpha(root.path(), "first");
+        let second = venv_with_alpha(root.path(), "second");
+        let modified = second.site_packages().unwrap().join("alpha/core.py");
+        fs::write(&modified, "b = 3\n").unwrap();
+
+        let plan = plan_dedupe(&find_duplicates(&[first, second], true));
+        let package = &plan.packages[0];
+        assert_eq!(package.links.len(), 1);
+        assert!(package.links[0].target.ends_with("alpha/__init__.py"));
+        assert_eq!(package.skipped.len(), 1);
+        assert_eq!(package.skipped[0].path, modified);
+        assert_eq!(package.skipped[0].reason, "modified since install");
+    }
+
+    #[test]
+    fn test_different_owner_is_skipped() {
+        let root = tempdir().unwrap();
+        let first = venv_with_alpha(root.path(), "first");
+        let second = venv_with_alpha(root.path(), "second");
+        let core = second.site_packages().unwrap().join("alpha/core.py");
+        let uid = fs::metadata(&core).unwrap().uid();
+        // only root can give files away
+        if std::os::unix::fs::chown(&core, Some(uid + 1), None).is_err() {
+            return;
+        }
+
+        let plan = plan_dedupe(&find_duplicates(&[first, second], true));
+        let package = &plan.packages[0];
+        assert_eq!(package.links.len(), 1);
+        assert!(package.links[0].target.ends_with("alpha/__init__.py"));
+        assert_eq!(package.skipped.len(), 1);
+        assert_eq!(package.skipped[0].path, core);
+        assert_eq!(package.skipped[0].reason, "different owner");
+    }
+
+    #[test]
+    fn test_freed_only_when_every_link_is_replaced() {
+        let root = tempdir().unwrap();
+        let first = venv_with_alpha(root.path(), "first");
+        let second = venv_with_alpha(root.path(), "second");
+        // another link keeps the data of the second copy around
+        let core = second.site_packages().unwrap().join("alpha/core.py");
+        fs::hard_link(&core, root.path().join("core.py")).unwrap();
+
+        let plan = plan_dedupe(&find_duplicates(&[first, second], true));
+        assert_eq!(plan.links().count(), 2);
+        assert_eq!(plan.freed(), 600);
+    }
+}
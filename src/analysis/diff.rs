@@ -0,0 +1,279 @@
+//! Differences between the packages of two venvs.
+
+use std::{cmp::Ordering, collections::BTreeMap, path::PathBuf};
+
+use crate::{
+    dir_size::SizeMode,
+    venv::{Venv, model::Package, utils::canonical_name, version::Version},
+};
+
+/// How a package differs between the two venvs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ChangeKind {
+    /// Only in the second venv
+    Added,
+    /// Only in the first venv
+    Removed,
+    Upgraded,
+    Downgraded,
+    /// Different versions that can't be ordered, like unparsable ones
+    Changed,
+}
+
+/// A package that differs between the two venvs, or one of them lacks.
+#[derive(Debug, Clone)]
+pub struct PackageChange {
+    pub name: String,
+    pub kind: ChangeKind,
+    /// Version in the first venv
+    pub old: Option<String>,
+    /// Version in the second venv
+    pub new: Option<String>,
+    pub old_size: u64,
+    pub new_size: u64,
+}
+
+impl PackageChange {
+    /// Growth from the first venv to the second.
+    pub fn size_delta(&self) -> i64 {
+        self.new_size as i64 - self.old_size as i64
+    }
+}
+
+/// What changes from venv `a` to venv `b`.
+#[derive(Debug, Clone)]
+pub struct VenvDiff {
+    pub a: PathBuf,
+    pub b: PathBuf,
+    /// Python versions of `a` and `b`
+    pub python: (String, String),
+    /// Sizes of `a` and `b`
+    pub size: (u64, u64),
+    /// Sorted by kind, then name
+    pub changes: Vec<PackageChange>,
+    /// Packages with the same version in both
+    pub unchanged: usize,
+}
+
+impl VenvDiff {
+    pub fn size_delta(&self) -> i64 {
+        self.size.1 as i64 - self.size.0 as i64
+    }
+
+    pub fn of_kind(&self, kind: ChangeKind) -> impl Iterator<Item = &PackageChange> {
+        self.changes.iter().filter(move |c| c.kind == kind)
+    }
+
+    /// Whether both venvs have the same Python and packages.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty() && self.python.0 == self.python.1
+    }
+}
+
+/// Compares the packages of `a` and `b` by canonical name. Versions are compared as PEP 440
+/// versions, so `1.0` and `1.0.0` are the same.
+pub fn diff_venvs(a: &Venv, b: &Venv, size_mode: SizeMode) -> VenvDiff {
+    let mut packages: BTreeMap<String, (Option<&Package>, Option<&Package>)> = BTreeMap::new();
+    for pkg in &a.packages {
+        packages.entry(canonical_name(&pkg.name)).or_default().0 = Some(pkg);
+    }
+    for pkg in &b.packages {
+        packages.entry(canonical_name(&pkg.name)).or_default().1 = Some(pkg);
+    }
+
+    let size = |pkg: Option<&Package>| pkg.map_or(0, |p| p.usage.size(size_mode));
+    let mut changes = Vec::new();
+    let mut unchanged = 0;
+    for (old, new) in packages.into_values() {
+        let kind = match (old, new) {
+            (Some(_), None) => ChangeKind::Removed,
+            (None, Some(_)) => ChangeKind::Added,
+            (Some(old), Some(new)) => {
+                match (
+                    old.version.parse::<Version>(),
+                    new.version.parse::<Version>(),
+                ) {
+                    (Ok(o), Ok(n)) => match o.cmp(&n) {
+                        Ordering::Less => ChangeKind::Upgraded,
+                        Ordering::Greater => ChangeKind::Downgraded,
+                        Ordering::Equal => {
+                            unchanged += 1;
+                            continue;
+                        }
+                    },
+                    _ if old.version == new.version => {
+                        unchanged += 1;
+                        continue;
+                    }
+                    _ => ChangeKind::Changed,
+                }
+            }
+            (None, None) => unreachable!("every entry has a package"),
+        };
+        changes.push(PackageChange {
+            name: new.or(old).map(|p| p.name.clone()).unwrap_or_default(),
+            kind,
+            old: old.map(|p| p.version.clone()),
+            new: new.map(|p| p.version.clone()),
+            old_size: size(old),
+            new_size: size(new),
+        });
+    }
+    changes.sort_by(|x, y| {
+        x.kind
+            .cmp(&y.kind)
+            .then_with(|| canonical_name(&x.name).cmp(&canonical_name(&y.name)))
+    });
+
+    VenvDiff {
+        a: a.path.clone(),
+        b: b.path.clone(),
+        python: (a.version.clone(), b.version.clone()),
+        size: (a.usage.size(size_mode), b.usage.size(size_mode)),
+        changes,
+        unchanged,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::venv::test_utils::{mock_package, mock_venv};
+
+    fn venv_with(root: &Path, name: &str, python: &str, packages: &[(&str, &str)]) -> Venv {
+        let path = root.join(name);
+        fs::create_dir(&path).unwrap();
+        let site_packages = mock_venv(&path);
+        let cfg = fs::read_to_string(path.join("pyvenv.cfg")).unwrap();
+        fs::write(path.join("pyvenv.cfg"), cfg.replace("3.13.2", python)).unwrap();
+        for (pkg, version) in packages {
+            let file = format!("{pkg}/__init__.py");
+            mock_package(&site_packages, pkg, version, &[(&file, version)]);
+        }
+        Venv::from_path(&path).unwrap()
+    }
+
+    #[test]
+    fn test_diff_venvs() {
+        let root = tempdir().unwrap();
+        let a = venv_with(
+            root.path(),
+            "ci",
+            "3.13.2",
+            &[
+                ("alpha", "1.0"),
+                ("beta", "2.0"),
+                ("gamma", "3.0"),
+                ("Delta_Pkg", "1.0"),
+                ("eps", "weird"),
+            ],
+        );
+        let b = venv_with(
+            root.path(),
+            "local",
+            "3.13.2",
+            &[
+                ("alpha", "1.0.0"),
+                ("beta", "2.1"),
+                ("gamma", "2.9"),
+                ("zeta", "0.1"),
+                ("eps", "other"),
+            ],
+        );
+
+        let diff = diff_venvs(&a, &b, SizeMode::Apparent);
+        let kinds: Vec<(&str, ChangeKind)> = diff
+            .changes
+            .iter()
+            .map(|c| (c.name.as_str(), c.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                ("zeta", ChangeKind::Added),
+                ("Delta_Pkg", ChangeKind::Removed),
+                ("beta", ChangeKind::Upgraded),
+                ("gamma", ChangeKind::Downgraded),
+                ("eps", ChangeKind::Changed),
+            ]
+        );
+        assert_eq!(diff.unchanged, 1);
+        assert!(!diff.is_empty());
+
+        let added = diff.of_kind(ChangeKind::Added).next().unwrap();
+        assert_eq!(added.old, None);
+        assert_eq!(added.new.as_deref(), Some("0.1"));
+        assert_eq!(added.old_size, 0);
+        assert_eq!(added.size_delta(), added.new_size as i64);
+
+        assert!(diff_venvs(&a, &a, SizeMode::Apparent).is_empty());
+    }
+
+    #[test]
+    fn test_downgrades() {
+        let root = tempdir().unwrap();
+        let a = venv_with(
+            root.path(),
+            "new",
+            "3.13.2",
+            &[
+                ("alpha", "1.10"),
+                ("beta", "2.0"),
+                ("gamma", "1.0.post1"),
+                ("delta", "1!1.0"),
+                ("eps", "3.0"),
+            ],
+        );
+        let b = venv_with(
+            root.path(),
+            "old",
+            "3.13.2",
+            &[
+                ("alpha", "1.9"),
+                ("beta", "2.0rc1"),
+                ("gamma", "1.0"),
+                ("delta", "2.0"),
+                ("eps", "3.0.dev1"),
+            ],
+        );
+
+        // compared as versions, not as strings
+        let diff = diff_venvs(&a, &b, SizeMode::Apparent);
+        assert_eq!(diff.changes.len(), 5);
+        assert_eq!(diff.of_kind(ChangeKind::Downgraded).count(), 5);
+        assert_eq!(diff.unchanged, 0);
+
+        let alpha = &diff.changes[0];
+        assert_eq!(alpha.name, "alpha");
+        assert_eq!(alpha.old.as_deref(), Some("1.10"));
+        assert_eq!(alpha.new.as_deref(), Some("1.9"));
+        // the files of the fake packages hold their version
+        assert!(alpha.size_delta() < 0);
+
+        let back = diff_venvs(&b, &a, SizeMode::Apparent);
+        assert_eq!(back.of_kind(ChangeKind::Upgraded).count(), 5);
+        assert_eq!(back.changes[0].size_delta(), -alpha.size_delta());
+    }
+
+    #[test]
+    fn test_python_version_change() {
+        let root = tempdir().unwrap();
+        let packages = [("alpha", "1.0"), ("beta", "2.0")];
+        let a = venv_with(root.path(), "py313", "3.13.2", &packages);
+        let b = venv_with(root.path(), "py312", "3.12.8", &packages);
+
+        let diff = diff_venvs(&a, &b, SizeMode::Apparent);
+        assert_eq!(diff.python, ("3.13.2".to_string(), "3.12.8".to_string()));
+        assert!(diff.changes.is_empty());
+        assert_eq!(diff.unchanged, 2);
+        // a different Python alone makes the venvs differ
+        assert!(!diff.is_empty());
+
+        let patch = venv_with(root.path(), "patch", "3.13.3", &packages);
+        assert!(!diff_venvs(&a, &patch, SizeMode::Apparent).is_empty());
+    }
+}
@@ -0,0 +1,789 @@
+//! Comparing the packages of a venv to a requirements or lock file.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::{Result, eyre::WrapErr};
+use serde::{Deserialize, Serialize};
+
+use crate::venv::{Venv, utils::canonical_name, version::Version};
+
+/// Lock files looked for next to a venv, in order of preference.
+pub const LOCK_FILES: [&str; 5] = [
+    "pylock.toml",
+    "uv.lock",
+    "poetry.lock",
+    "requirements.lock",
+    "requirements.txt",
+];
+
+/// Installed but not listed packages that aren't drift, like `pip freeze` leaves them out.
+const TOOLING: [&str; 4] = ["pip", "setuptools", "wheel", "distribute"];
+
+/// What a file expects of a package.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expected {
+    /// Listed without a version
+    Any,
+    /// One of the locked versions, lock files with forked resolutions list several
+    Exact(Vec<String>),
+    /// A `==` or `===` pin of a requirements file as written, `==1.0` also allows `1.0+cpu`
+    Pinned(String),
+    /// PEP 440 specifiers like `>=1.0,<2`
+    Range(String),
+}
+
+impl Expected {
+    fn allows(&self, version: &str) -> bool {
+        match self {
+            Expected::Any => true,
+            Expected::Exact(versions) => match version.parse::<Version>() {
+                Ok(installed) => versions
+                    .iter()
+                    .any(|v| v.parse::<Version>().is_ok_and(|v| v == installed)),
+                Err(_) => versions.iter().any(|v| v == version),
+            },
+            Expected::Pinned(specifier) => match version.parse::<Version>() {
+                Ok(installed) => installed.satisfies(specifier).unwrap_or(false),
+                Err(_) => pinned_version(specifier) == version,
+            },
+            Expected::Range(specifiers) => version
+                .parse::<Version>()
+                .and_then(|v| v.satisfies(specifiers))
+                // a version we can't compare isn't reported as drift
+                .unwrap_or(true),
+        }
+    }
+}
+
+impl std::fmt::Display for Expected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expected::Any => write!(f, "any version"),
+            Expected::Exact(versions) => write!(f, "{}", versions.join(" or ")),
+            Expected::Pinned(specifier) => write!(f, "{}", pinned_version(specifier)),
+            Expected::Range(specifiers) => write!(f, "{specifiers}"),
+        }
+    }
+}
+
+/// The version of a `==` or `===` specifier.
+fn pinned_version(specifier: &str) -> &str {
+    specifier.trim_start_matches('=')
+}
+
+/// A package listed in a requirements or lock file.
+#[derive(Debug, Clone)]
+pub struct Requirement {
+    pub name: String,
+    pub expected: Expected,
+    /// Only needed on some platforms or with some extras, so it's not missing if absent
+    pub conditional: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Missing {
+    pub name: String,
+    pub expected: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Extra {
+    pub name: String,
+    pub version: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Mismatch {
+    pub name: String,
+    pub installed: String,
+    pub expected: String,
+}
+
+/// Differences between a venv and a requirements or lock file.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Drift {
+    pub file: PathBuf,
+    pub missing: Vec<Missing>,
+    pub extra: Vec<Extra>,
+    pub mismatched: Vec<Mismatch>,
+}
+
+impl Drift {
+    pub fn is_clean(&self) -> bool {
+        self.missing.is_empty() && self.extra.is_empty() && self.mismatched.is_empty()
+    }
+
+    /// Whether the installed package `name` is extra or has the wrong version.
+    pub fn drifted(&self, name: &str) -> bool {
+        self.mismatch_of(name).is_some() || self.extra.iter().any(|e| e.name == name)
+    }
+
+    pub fn mismatch_of(&self, name: &str) -> Option<&Mismatch> {
+        self.mismatched.iter().find(|m| m.name == name)
+    }
+}
+
+/// Reads the requirements of `path`, picking the format from the file name: `pylock.toml` and
+/// `pylock.<name>.toml`, `uv.lock` and `poetry.lock`. Anything else is read as a pip
+/// requirements file.
+pub fn read_requirements(path: &Path) -> Result<Vec<Requirement>> {
+    let contents =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let file_name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or_default();
+    let requirements = if file_name.starts_with("pylock.") && file_name.ends_with(".toml") {
+        parse_pylock(&contents)
+    } else if file_name == "uv.lock" {
+        parse_uv_lock(&contents)
+    } else if file_name == "poetry.lock" {
+        parse_poetry_lock(&contents)
+    } else {
+        let mut seen = HashSet::new();
+        parse_requirements_file(path, &contents, &mut seen)
+    };
+    requirements.with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+/// Compares the packages of `venv` to the requirements in `path`.
+pub fn check_drift(venv: &Venv, path: &Path) -> Result<Drift> {
+    let requirements = read_requirements(path)?;
+    Ok(compare(venv, &requirements, path))
+}
+
+/// The first lock file in [`LOCK_FILES`] in the directory that holds the venv.
+pub fn find_lock_file(venv: &Venv) -> Option<PathBuf> {
+    let dir = venv.path.parent()?;
+    LOCK_FILES
+        .iter()
+        .map(|name| dir.join(name))
+        .find(|p| p.is_file())
+}
+
+fn compare(venv: &Venv, requirements: &[Requirement], file: &Path) -> Drift {
+    // the same package can be listed more than once, e.g. with different markers
+    let mut expected: BTreeMap<String, Vec<&Requirement>> = BTreeMap::new();
+    for req in requirements {
+        expected
+            .entry(canonical_name(&req.name))
+            .or_default()
+            .push(req);
+    }
+
+    let mut drift = Drift {
+        file: file.to_path_buf(),
+        ..Default::default()
+    };
+    let mut installed = HashSet::new();
+    for pkg in &venv.packages {
+        let name = canonical_name(&pkg.name);
+        installed.insert(name.clone());
+        match expected.get(&name) {
+            Some(reqs) if reqs.iter().any(|r| r.expected.allows(&pkg.version)) => {}
+            Some(reqs) => drift.mismatched.push(Mismatch {
+                name: pkg.name.clone(),
+                installed: pkg.version.clone(),
+                expected: reqs
+                    .iter()
+                    .map(|r| r.expected.to_string())
+                    .collect::<Vec<_>>()
+                    .join(" or "),
+            }),
+            None if TOOLING.contains(&name.as_str()) => {}
+            None => drift.extra.push(Extra {
+                name: pkg.name.clone(),
+                version: pkg.version.clone(),
+            }),
+        }
+    }
+    for (name, reqs) in &expected {
+        if !installed.contains(name) && reqs.iter().any(|r| !r.conditional) {
+            drift.missing.push(Missing {
+                name: reqs[0].name.clone(),
+                expected: reqs[0].expected.to_string(),
+            });
+        }
+    }
+
+    drift.extra.sort_by(|a, b| a.name.cmp(&b.name));
+    drift.mismatched.sort_by(|a, b| a.name.cmp(&b.name));
+    drift
+}
+
+/// Reads a pip requirements file, following `-r` includes. Options, editable installs and
+/// URLs without a name are skipped.
+fn parse_requirements_file(
+    path: &Path,
+    contents: &str,
+    seen: &mut HashSet<PathBuf>,
+) -> Result<Vec<Requirement>> {
+    seen.insert(path.to_path_buf());
+    let mut requirements = Vec::new();
+    for line in contents.replace("\\\r\n", " ").replace("\\\n", " ").lines() {
+        // comments start at `#` at the start of the line or after whitespace
+        let line = match line.find(" #") {
+            Some(i) => &line[..i],
+            None if line.trim_start().starts_with('#') => "",
+            None => line,
+        }
+        .trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(include) = line
+            .strip_prefix("-r ")
+            .or_else(|| line.strip_prefix("--requirement "))
+            .or_else(|| line.strip_prefix("--requirement="))
+        {
+            let include = path.parent().unwrap_or(Path::new("")).join(include.trim());
+            if seen.contains(&include) {
+                continue;
+            }
+            let contents = fs::read_to_string(&include)
+                .with_context(|| format!("Failed to read {}", include.display()))?;
+            requirements.extend(parse_requirements_file(&include, &contents, seen)?);
+            continue;
+        }
+        if line.starts_with('-') {
+            continue;
+        }
+        if let Some(req) = parse_requirement(line) {
+            requirements.push(req);
+        }
+    }
+    Ok(requirements)
+}
+
+/// Parses a PEP 508 requirement like `name[extra]>=1.0; python_version < "3.11"`. Options
+/// after the requirement, like `--hash`, are ignored.
+fn parse_requirement(line: &str) -> Option<Requirement> {
+    let (requirement, marker) = match line.split_once(';') {
+        Some((requirement, marker)) => (requirement, Some(marker.trim())),
+        None => (line, None),
+    };
+    let requirement = requirement
+        .split(" --")
+        .next()
+        .unwrap_or(requirement)
+        .trim();
+    let name_end = requirement
+        .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')))
+        .unwrap_or(requirement.len());
+    let name = &requirement[..name_end];
+    // paths and URLs to install from aren't named
+    if !name.starts_with(|c: char| c.is_ascii_alphanumeric()) {
+        return None;
+    }
+    let mut rest = requirement[name_end..].trim_start();
+    if rest.starts_with('[') {
+        rest = rest.split_once(']').map_or("", |(_, r)| r).trim_start();
+    }
+    let rest = rest.trim_start_matches('(').trim_end_matches(')').trim();
+
+    let expected = if rest.is_empty() || rest.starts_with('@') {
+        Expected::Any
+    } else if rest.starts_with("==") && !rest.contains([',', '*']) {
+        Expected::Pinned(rest.replace(' ', ""))
+    } else {
+        Expected::Range(rest.replace(' ', ""))
+    };
+    Some(Requirement {
+        name: name.to_string(),
+        expected,
+        conditional: marker.is_some_and(|m| !m.is_empty()),
+    })
+}
+
+#[derive(Deserialize)]
+struct Pylock {
+    #[serde(default)]
+    packages: Vec<LockedPackage>,
+}
+
+/// `poetry.lock` lists its packages as `[[package]]`.
+#[derive(Deserialize)]
+struct PoetryLock {
+    #[serde(default)]
+    package: Vec<LockedPackage>,
+}
+
+/// A package in `pylock.toml` or `poetry.lock`.
+#[derive(Deserialize)]
+struct LockedPackage {
+    name: String,
+    version: Option<String>,
+    /// PEP 751 environment marker
+    marker: Option<String>,
+    /// Poetry's markers, a string or one per group
+    markers: Option<toml::Value>,
+    /// Only installed with an extra, in `poetry.lock`
+    #[serde(default)]
+    optional: bool,
+    /// Poetry dependency groups the package is in, only `main` is installed by default
+    groups: Option<Vec<String>>,
+}
+
+fn locked(packages: Vec<LockedPackage>) -> Vec<Requirement> {
+    packages
+        .into_iter()
+        .map(|p| {
+            let has_marker = p.marker.is_some_and(|m| !m.is_empty())
+                || p.markers.is_some_and(|m| match m {
+                    toml::Value::String(m) => !m.is_empty(),
+                    toml::Value::Table(groups) => !groups.is_empty(),
+                    _ => false,
+                });
+            let not_main = p.groups.is_some_and(|g| !g.iter().any(|g| g == "main"));
+            Requirement {
+                name: p.name,
+                expected: p
+                    .version
+                    .map_or(Expected::Any, |v| Expected::Exact(vec![v])),
+                conditional: p.optional || has_marker || not_main,
+            }
+        })
+        .collect()
+}
+
+/// Packages of a PEP 751 lock file.
+fn parse_pylock(contents: &str) -> Result<Vec<Requirement>> {
+    let lock: Pylock = toml::from_str(contents)?;
+    Ok(locked(lock.packages))
+}
+
+fn parse_poetry_lock(contents: &str) -> Result<Vec<Requirement>> {
+    let lock: PoetryLock = toml::from_str(contents)?;
+    Ok(locked(lock.package))
+}
+
+#[derive(Deserialize)]
+struct UvLock {
+    #[serde(default)]
+    package: Vec<UvPackage>,
+}
+
+/// A package in `uv.lock`. Markers aren't on the package but on the dependencies pointing
+/// to it, and on packages locked only for some of the forks of the resolution.
+#[derive(Deserialize)]
+struct UvPackage {
+    name: String,
+    version: Option<String>,
+    source: Option<toml::Value>,
+    #[serde(default, rename = "resolution-markers")]
+    resolution_markers: Vec<String>,
+    #[serde(default)]
+    dependencies: Vec<UvDependency>,
+    #[serde(default, rename = "optional-dependencies")]
+    optional_dependencies: BTreeMap<String, Vec<UvDependency>>,
+    #[serde(default, rename = "dev-dependencies")]
+    dev_dependencies: BTreeMap<String, Vec<UvDependency>>,
+}
+
+#[derive(Deserialize)]
+struct UvDependency {
+    name: String,
+    marker: Option<String>,
+}
+
+/// Packages of a `uv.lock`. Virtual packages, like a workspace root that isn't a package,
+/// aren't installed and are left out. A package is required if the packages nothing depends
+/// on, the workspace members, reach it through dependencies without a marker. Everything
+/// else is only installed on some platforms, with an extra or with a dev group.
+fn parse_uv_lock(contents: &str) -> Result<Vec<Requirement>> {
+    let lock: UvLock = toml::from_str(contents)?;
+
+    let mut depended_on = HashSet::new();
+    for p in &lock.package {
+        let optional = p.optional_dependencies.values().flatten();
+        let dev = p.dev_dependencies.values().flatten();
+        for dep in p.dependencies.iter().chain(optional).chain(dev) {
+            depended_on.insert(canonical_name(&dep.name));
+        }
+    }
+    // a package locked in several versions is visited once per version
+    let mut visited = vec![false; lock.package.len()];
+    let mut required: HashSet<String> = HashSet::new();
+    let mut queue: Vec<usize> = (0..lock.package.len())
+        .filter(|&i| !depended_on.contains(&canonical_name(&lock.package[i].name)))
+        .collect();
+    while let Some(i) = queue.pop() {
+        if std::mem::replace(&mut visited[i], true) {
+            continue;
+        }
+        let p = &lock.package[i];
+        required.insert(canonical_name(&p.name));
+        for dep in p.dependencies.iter().filter(|d| d.marker.is_none()) {
+            let name = canonical_name(&dep.name);
+            queue.extend(
+                (0..lock.package.len()).filter(|&j| canonical_name(&lock.package[j].name) == name),
+            );
+        }
+    }
+
+    let requirements = lock
+        .package
+        .into_iter()
+        .filter(|p| p.source.as_ref().is_none_or(|s| s.get("virtual").is_none()))
+        .map(|p| Requirement {
+            conditional: !p.resolution_markers.is_empty()
+                || !required.contains(&canonical_name(&p.name)),
+            name: p.name,
+            expected: p
+                .version
+                .map_or(Expected::Any, |v| Expected::Exact(vec![v])),
+        })
+        .collect();
+    Ok(merge_versions(requirements))
+}
+
+/// Joins the versions of a package locked more than once for different platforms.
+fn merge_versions(requirements: Vec<Requirement>) -> Vec<Requirement> {
+    let mut merged: BTreeMap<String, Requirement> = BTreeMap::new();
+    for req in requirements {
+        let name = canonical_name(&req.name);
+        match merged.get_mut(&name) {
+            Some(existing) => {
+                if let (Expected::Exact(versions), Expected::Exact(more)) =
+                    (&mut existing.expected, req.expected)
+                {
+                    versions.extend(more);
+                }
+                // either version is enough, so the package is no longer required as locked
+                existing.conditional = true;
+            }
+            None => {
+                merged.insert(name, req);
+            }
+        }
+    }
+    merged.into_values().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::venv::test_utils::{mock_package, mock_venv};
+
+    #[test]
+    fn test_parse_requirement() {
+        let req = parse_requirement("Alpha[extra] == 1.0 --hash=sha256:abc").unwrap();
+        assert_eq!(req.name, "Alpha");
+        assert_eq!(req.expected, Expected::Pinned("==1.0".to_string()));
+        assert_eq!(req.expected.to_string(), "1.0");
+        assert!(!req.conditional);
+
+        let req = parse_requirement("beta>=1.0, <2; python_version < \"3.11\"").unwrap();
+        assert_eq!(req.expected, Expected::Range(">=1.0,<2".to_string()));
+        assert!(req.conditional);
+
+        let req = parse_requirement("gamma @ https://example.com/gamma.whl").unwrap();
+        assert_eq!(req.expected, Expected::Any);
+        assert_eq!(
+            parse_requirement("delta==1.*").unwrap().expected,
+            Expected::Range("==1.*".to_string())
+        );
+        assert!(parse_requirement("./local/path").is_none());
+    }
+
+    #[test]
+    fn test_allows_local_version() {
+        let pin = parse_requirement("torch==2.1.0").unwrap().expected;
+        assert!(pin.allows("2.1.0+cpu") && pin.allows("2.1") && !pin.allows("2.1.1"));
+        let pin = parse_requirement("torch===2.1.0").unwrap().expected;
+        assert!(pin.allows("2.1.0") && !pin.allows("2.1.0+cpu"));
+        // a lock file names the exact version that was resolved
+        let locked = Expected::Exact(vec!["2.1.0".to_string()]);
+        assert!(locked.allows("2.1.0") && !locked.allows("2.1.0+cpu"));
+    }
+
+    #[test]
+    fn test_uv_lock_markers() {
+        // as `uv lock` writes it for a project depending on click
+        let contents = r#"
+version = 1
+revision = 2
+requires-python = ">=3.9"
+resolution-markers = [
+    "python_full_version >= '3.10'",
+    "python_full_version < '3.10'",
+]
+
+[[package]]
+name = "click"
+version = "8.1.8"
+source = { registry = "https://pypi.org/simple" }
+resolution-markers = [
+    "python_full_version < '3.10'",
+]
+dependencies = [
+    { name = "colorama", marker = "sys_platform == 'win32'" },
+]
+sdist = { url = "https://files.pythonhosted.org/click-8.1.8.tar.gz", hash = "sha256:ed53", size = 226593 }
+
+[[package]]
+name = "click"
+version = "8.2.1"
+source = { registry = "https://pypi.org/simple" }
+resolution-markers = [
+    "python_full_version >= '3.10'",
+]
+dependencies = [
+    { name = "colorama", marker = "sys_platform == 'win32'" },
+]
+
+[[package]]
+name = "colorama"
+version = "0.4.6"
+source = { registry = "https://pypi.org/simple" }
+
+[[package]]
+name = "demo"
+version = "0.1.0"
+source = { editable = "." }
+dependencies = [
+    { name = "click", version = "8.1.8", source = { registry = "https://pypi.org/simple" }, marker = "python_full_version < '3.10'" },
+    { name = "click", version = "8.2.1", source = { registry = "https://pypi.org/simple" }, marker = "python_full_version >= '3.10'" },
+    { name = "rich" },
+]
+
+[package.optional-dependencies]
+yaml = [
+    { name = "pyyaml" },
+]
+
+[package.dev-dependencies]
+dev = [
+    { name = "pytest" },
+]
+
+[[package]]
+name = "rich"
+version = "13.9.4"
+source = { registry = "https://pypi.org/simple" }
+dependencies = [
+    { name = "pygments" },
+]
+
+[[package]]
+name = "pygments"
+version = "2.19.1"
+source = { registry = "https://pypi.org/simple" }
+
+[[package]]
+name = "pyyaml"
+version = "6.0.2"
+source = { registry = "https://pypi.org/simple" }
+
+[[package]]
+name = "pytest"
+version = "8.3.5"
+source = { registry = "https://pypi.org/simple" }
+"#;
+        let requirements = parse_uv_lock(contents).unwrap();
+        let conditional: Vec<(&str, bool)> = requirements
+            .iter()
+            .map(|r| (r.name.as_str(), r.conditional))
+            .collect();
+        assert_eq!(
+            conditional,
+            [
+                ("click", true),
+                ("colorama", true),
+                ("demo", false),
+                ("pygments", false),
+                ("pytest", true),
+                ("pyyaml", true),
+                ("rich", false),
+            ]
+        );
+
+        let root = tempdir().unwrap();
+        let venv_dir = root.path().join(".venv");
+        fs::create_dir(&venv_dir).unwrap();
+        let site_packages = mock_venv(&venv_dir);
+        for (name, version) in [("click", "8.2.1"), ("rich", "13.9.4"), ("demo", "0.1.0")] {
+            mock_package(&site_packages, name, version, &[]);
+        }
+        let uv_lock = root.path().join("uv.lock");
+        fs::write(&uv_lock, contents).unwrap();
+        let venv = Venv::from_path(&venv_dir).unwrap();
+        let drift = check_drift(&venv, &uv_lock).unwrap();
+        let missing: Vec<&str> = drift.missing.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(missing, ["pygments"]);
+        assert!(drift.mismatched.is_empty() && drift.extra.is_empty());
+    }
+
+    #[test]
+    fn test_poetry_lock_markers() {
+        let contents = r#"
+[[package]]
+name = "colorama"
+version = "0.4.6"
+optional = false
+python-versions = "*"
+groups = ["main"]
+markers = "platform_system == \"Windows\""
+
+[[package]]
+name = "click"
+version = "8.1.8"
+optional = false
+python-versions = ">=3.7"
+groups = ["main"]
+
+[package.dependencies]
+colorama = {version = "*", markers = "platform_system == \"Windows\""}
+
+[[package]]
+name = "pytest"
+version = "8.3.5"
+optional = false
+python-versions = ">=3.8"
+groups = ["dev"]
+
+[[package]]
+name = "tomli"
+version = "2.2.1"
+optional = false
+python-versions = ">=3.8"
+groups = ["main", "dev"]
+markers = {main = "python_version < \"3.11\"", dev = "python_version < \"3.11\""}
+
+[[package]]
+name = "pyyaml"
+version = "6.0.2"
+optional = true
+python-versions = ">=3.8"
+groups = ["main"]
+"#;
+        let requirements = parse_poetry_lock(contents).unwrap();
+        let conditional: Vec<(&str, bool)> = requirements
+            .iter()
+            .map(|r| (r.name.as_str(), r.conditional))
+            .collect();
+        assert_eq!(
+            conditional,
+            [
+                ("colorama", true),
+                ("click", false),
+                ("pytest", true),
+                ("tomli", true),
+                ("pyyaml", true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_drift() {
+        let root = tempdir().unwrap();
+        let venv_dir = root.path().join(".venv");
+        fs::create_dir(&venv_dir).unwrap();
+        let site_packages = mock_venv(&venv_dir);
+        mock_package(&site_packages, "alpha", "1.0", &[]);
+        mock_package(&site_packages, "beta", "2.0", &[]);
+        mock_package(&site_packages, "extra_pkg", "0.1", &[]);
+        mock_package(&site_packages, "pip", "24.0", &[]);
+        let venv = Venv::from_path(&venv_dir).unwrap();
+
+        let base = root.path().join("base.txt");
+        fs::write(&base, "# pinned\nalpha==1.0.0\n").unwrap();
+        let requirements = root.path().join("requirements.txt");
+        fs::write(
+            &requirements,
+            "-r base.txt\n--index-url https://example.com\nbeta>=2.1 \\\n    --hash=sha256:abc\n\
+             gamma==3.0\nwin-only==1.0; sys_platform == 'win32'\n",
+        )
+        .unwrap();
+        let drift = check_drift(&venv, &requirements).unwrap();
+        assert_eq!(
+            drift.missing,
+            [Missing {
+                name: "gamma".to_string(),
+                expected: "3.0".to_string()
+            }]
+        );
+        assert_eq!(
+            drift.extra,
+            [Extra {
+                name: "extra_pkg".to_string(),
+                version: "0.1".to_string()
+            }]
+        );
+        assert_eq!(
+            drift.mismatched,
+            [Mismatch {
+                name: "beta".to_string(),
+                installed: "2.0".to_string(),
+                expected: ">=2.1".to_string()
+            }]
+        );
+        assert!(drift.drifted("extra_pkg") && !drift.drifted("alpha"));
+
+        let uv_lock = root.path().join("uv.lock");
+        fs::write(
+            &uv_lock,
+            r#"
+version = 1
+
+[[package]]
+name = "project"
+version = "0.1.0"
+source = { virtual = "." }
+
+[[package]]
+name = "alpha"
+version = "1.0"
+
+[[package]]
+name = "beta"
+version = "1.0"
+source = { registry = "https://pypi.org/simple" }
+
+[[package]]
+name = "beta"
+version = "2.0"
+source = { registry = "https://pypi.org/simple" }
+
+[[package]]
+name = "extra-pkg"
+version = "0.2"
+"#,
+        )
+        .unwrap();
+        assert_eq!(find_lock_file(&venv), Some(uv_lock.clone()));
+        let drift = check_drift(&venv, &uv_lock).unwrap();
+        assert!(drift.missing.is_empty() && drift.extra.is_empty());
+        assert_eq!(drift.mismatched.len(), 1);
+        assert_eq!(drift.mismatched[0].expected, "0.2");
+        assert!(drift.mismatch_of("extra_pkg").is_some());
+
+        let pylock = root.path().join("pylock.toml");
+        fs::write(
+            &pylock,
+            r#"
+lock-version = "1.0"
+created-by = "test"
+
+[[packages]]
+name = "alpha"
+version = "1.0"
+
+[[packages]]
+name = "delta"
+version = "1.0"
+marker = "sys_platform == 'win32'"
+"#,
+        )
+        .unwrap();
+        let drift = check_drift(&venv, &pylock).unwrap();
+        assert!(drift.missing.is_empty());
+        assert_eq!(drift.extra.len(), 2);
+    }
+}
@@ -0,0 +1,226 @@
+use std::{
+    collections::BTreeMap,
+    hash::{DefaultHasher, Hash, Hasher},
+    path::PathBuf,
+    sync::Arc,
+};
+
+use crate::{
+    dir_size::combine_usage,
+    venv::{Venv, model::Package, record::read_record, utils::canonical_name},
+};
+
+/// Canonical name, version and `RECORD` fingerprint
+type GroupKey = (String, String, u64);
+
+/// Whether the copies of a distribution share their files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkState {
+    /// Each copy takes its own space
+    None,
+    /// Some files are shared
+    Partial,
+    /// All copies are hardlinks of one
+    Full,
+}
+
+/// A copy of a distribution in one of the venvs.
+#[derive(Debug, Clone)]
+pub struct Copy {
+    pub venv: String,
+    pub venv_path: PathBuf,
+    pub dist_info: PathBuf,
+    pub size: u64,
+}
+
+/// The same distribution installed in several venvs.
+#[derive(Debug, Clone)]
+pub struct DuplicateGroup {
+    pub name: String,
+    pub version: String,
+    pub copies: Vec<Copy>,
+    /// Sum of the sizes of the copies
+    pub size: u64,
+    /// Space the copies take together, shared files counted once
+    pub on_disk: u64,
+    pub link_state: LinkState,
+}
+
+impl DuplicateGroup {
+    /// Space freed if every copy was a hardlink of the largest one.
+    pub fn reclaimable(&self) -> u64 {
+        let largest = self.copies.iter().map(|c| c.size).max().unwrap_or(0);
+        self.on_disk.saturating_sub(largest)
+    }
+}
+
+/// Groups the distributions installed in more than one venv by name and version. With
+/// `compare_hashes` the hashes in `RECORD` must match as well, so builds from different sources
+/// or locally modified copies are kept apart.
+pub fn find_duplicates(venvs: &[Arc<Venv>], compare_hashes: bool) -> Vec<DuplicateGroup> {
+    let mut groups: BTreeMap<GroupKey, Vec<(&Venv, &Package)>> = BTreeMap::new();
+    for venv in venvs {
+        for pkg in &venv.packages {
+            let fingerprint = if compare_hashes {
+                record_fingerprint(pkg)
+            } else {
+                0
+            };
+            groups
+                .entry((canonical_name(&pkg.name), pkg.version.clone(), fingerprint))
+                .or_default()
+                .push((venv, pkg));
+        }
+    }
+
+    let mut duplicates: Vec<DuplicateGroup> = groups
+        .into_values()
+        .filter(|members| members.len() > 1)
+        .map(|members| {
+            let (_, first) = members[0];
+            let size = members.iter().map(|(_, pkg)| pkg.usage.apparent).sum();
+            let on_disk = combine_usage(
+                members
+                    .iter()
+                    .map(|(_, pkg)| (pkg.usage, pkg.linked.0.as_slice())),
+            )
+            .unique;
+            let largest = members
+                .iter()
+                .map(|(_, pkg)| pkg.usage.unique)
+                .max()
+                .unwrap_or(0);
+            let link_state = if on_disk <= largest {
+                LinkState::Full
+            } else if on_disk < size {
+                LinkState::Partial
+            } else {
+                LinkState::None
+            };
+
+            DuplicateGroup {
+                name: first.name.clone(),
+                version: first.version.clone(),
+                copies: members
+                    .iter()
+                    .map(|(venv, pkg)| Copy {
+                        venv: venv.name.clone(),
+                        venv_path: venv.path.clone(),
+                        dist_info: pkg.dist_info.clone(),
+                        size: pkg.usage.apparent,
+                    })
+                    .collect(),
+                size,
+                on_disk,
+                link_state,
+            }
+        })
+        .collect();
+
+    duplicates.sort_by(|a, b| {
+        b.reclaimable()
+            .cmp(&a.reclaimable())
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    duplicates
+}
+
+/// Hash of the file hashes in `RECORD`. The dist-info files are left out since they differ
+/// between installers even for the same wheel. An unreadable `RECORD` gets its own fingerprint
+/// from the dist-info path so it isn't grouped with anything.
+fn record_fingerprint(pkg: &Package) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    match read_record(&pkg.dist_info) {
+        Ok(entries) => {
+            let mut hashes: Vec<(String, String)> = entries
+                .into_iter()
+                .filter(|e| !e.is_dist_info())
+                .filter_map(|e| e.hash.map(|h| (e.path, h.digest)))
+                .collect();
+            hashes.sort();
+            hashes.hash(&mut hasher);
+        }
+        Err(_) => pkg.dist_info.hash(&mut hasher),
+    }
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::venv::test_utils::{mock_package, mock_venv};
+
+    fn venv_with_alpha(root: &Path, name: &str, contents: &str) -> Arc<Venv> {
+        let path = root.join(name);
+        fs::create_dir(&path).unwrap();
+        let site_packages = mock_venv(&path);
+        mock_package(
+            &site_packages,
+            "alpha",
+            "1.0",
+            &[("alpha/__init__.py", contents)],
+        );
+        mock_package(&site_packages, name, "0.1", &[]);
+        Arc::new(Venv::from_path(&path).unwrap())
+    }
+
+    #[test]
+    fn test_find_duplicates() {
+        let root = tempdir().unwrap();
+        let venvs = vec![
+            venv_with_alpha(root.path(), "first", "a = 1"),
+            venv_with_alpha(root.path(), "second", "a = 1"),
+            venv_with_alpha(root.path(), "third", "a = 2"),
+        ];
+
+        let groups = find_duplicates(&venvs, false);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].name, "alpha");
+        assert_eq!(groups[0].copies.len(), 3);
+        assert_eq!(groups[0].link_state, LinkState::None);
+        assert_eq!(groups[0].on_disk, groups[0].size);
+
+        // the third copy has a different file
+        let groups = find_duplicates(&venvs, true);
+        assert_eq!(groups.len(), 1);
+        let venv_names: Vec<&str> = groups[0].copies.iter().map(|c| c.venv.as_str()).collect();
+        assert_eq!(venv_names, ["first", "second"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_hardlinked_copies() {
+        let root = tempdir().unwrap();
+        let first = venv_with_alpha(root.path(), "first", "a = 1");
+        let second_path = root.path().join("second");
+        fs::create_dir(&second_path).unwrap();
+        let site_packages = mock_venv(&second_path);
+        let first_site_packages = first.site_packages().unwrap();
+
+        // link every file of alpha like uv does
+        for dir in ["alpha", "alpha-1.0.dist-info"] {
+            fs::create_dir(site_packages.join(dir)).unwrap();
+            for entry in fs::read_dir(first_site_packages.join(dir)).unwrap() {
+                let entry = entry.unwrap();
+                fs::hard_link(
+                    entry.path(),
+                    site_packages.join(dir).join(entry.file_name()),
+                )
+                .unwrap();
+            }
+        }
+        let second = Arc::new(Venv::from_path(&second_path).unwrap());
+        // links are counted when parsing, the first copy had a single link back then
+        let first = Arc::new(Venv::from_path(&first.path).unwrap());
+
+        let groups = find_duplicates(&[first, second], true);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].link_state, LinkState::Full);
+        assert_eq!(groups[0].on_disk * 2, groups[0].size);
+        assert_eq!(groups[0].reclaimable(), 0);
+    }
+}
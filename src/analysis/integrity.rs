@@ -0,0 +1,183 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::Result;
+use rayon::prelude::*;
+
+use crate::venv::{
+    Venv,
+    model::Package,
+    orphans::{Orphan, find_orphans},
+    record::{RecordEntry, Records, read_record},
+};
+
+/// Differences between the files of a distribution and its `RECORD`.
+#[derive(Debug, Clone, Default)]
+pub struct PackageIntegrity {
+    pub name: String,
+    pub version: String,
+    /// Files whose hash doesn't match `RECORD`
+    pub modified: Vec<PathBuf>,
+    /// Files in `RECORD` that don't exist
+    pub missing: Vec<PathBuf>,
+    /// Files in the directories of the distribution that no `RECORD` lists
+    pub extra: Vec<PathBuf>,
+    /// Files that couldn't be checked, with the reason
+    pub errors: Vec<String>,
+}
+
+impl PackageIntegrity {
+    pub fn is_intact(&self) -> bool {
+        self.modified.is_empty()
+            && self.missing.is_empty()
+            && self.extra.is_empty()
+            && self.errors.is_empty()
+    }
+}
+
+/// Checks every file listed in the `RECORD` of each package of `venv` against its hash. Paths
+/// are relative to site-packages, like in `RECORD`. Files without a hash, like the `RECORD`
+/// itself or bytecode compiled after install, are only checked for extra files.
+pub fn verify_venv(venv: &Venv) -> Result<Vec<PackageIntegrity>> {
+    let site_packages = venv.site_packages()?;
+    let orphans = find_orphans(&site_packages, &mut Records::default())?;
+    Ok(venv
+        .packages
+        .par_iter()
+        .map(|pkg| verify_package(pkg, &site_packages, &orphans))
+        .collect())
+}
+
+fn verify_package(pkg: &Package, site_packages: &Path, orphans: &[Orphan]) -> PackageIntegrity {
+    let mut integrity = PackageIntegrity {
+        name: pkg.name.clone(),
+        version: pkg.version.clone(),
+        ..Default::default()
+    };
+    let record = match read_record(&pkg.dist_info) {
+        Ok(record) => record,
+        Err(e) => {
+            integrity.errors.push(e.to_string());
+            return integrity;
+        }
+    };
+
+    let results: Vec<(PathBuf, FileState)> = record
+        .par_iter()
+        .filter_map(|entry| {
+            let state = check_file(entry, site_packages)?;
+            Some((PathBuf::from(&entry.path), state))
+        })
+        .collect();
+    for (path, state) in results {
+        match state {
+            FileState::Modified => integrity.modified.push(path),
+            FileState::Missing => integrity.missing.push(path),
+            FileState::Error(e) => integrity.errors.push(format!("{}: {}", path.display(), e)),
+        }
+    }
+
+    let top_level = top_level_dirs(&record);
+    integrity.extra = orphans
+        .iter()
+        .filter(|o| {
+            o.path
+                .components()
+                .next()
+                .is_some_and(|first| top_level.contains(Path::new(first.as_os_str())))
+        })
+        .map(|o| o.path.clone())
+        .collect();
+    integrity
+}
+
+enum FileState {
+    Modified,
+    Missing,
+    Error(String),
+}
+
+/// State of a file that doesn't match its `RECORD` entry, `None` if it matches.
+fn check_file(entry: &RecordEntry, site_packages: &Path) -> Option<FileState> {
+    let hash = entry.hash.as_ref()?;
+    let path = site_packages.join(&entry.path);
+    let Ok(meta) = path.metadata() else {
+        return Some(FileState::Missing);
+    };
+    // a different size is a modification without reading the file
+    if entry.size.is_some_and(|size| size != meta.len()) {
+        return Some(FileState::Modified);
+    }
+    match hash.matches_file(&path) {
+        Ok(true) => None,
+        Ok(false) => Some(FileState::Modified),
+        Err(e) => Some(FileState::Error(e.to_string())),
+    }
+}
+
+/// Directories directly in site-packages that hold files of the distribution.
+fn top_level_dirs(record: &[RecordEntry]) -> HashSet<&Path> {
+    record
+        .iter()
+        .filter(|e| !e.is_dist_info())
+        .filter_map(|e| {
+            let (first, _) = e.path.split_once('/')?;
+            (first != ".." && first != "__pycache__").then_some(Path::new(first))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::venv::test_utils::{mock_package, mock_venv};
+
+    #[test]
+    fn test_verify_venv() {
+        let root = tempdir().unwrap();
+        let site_packages = mock_venv(root.path());
+        mock_package(
+            &site_packages,
+            "alpha",
+            "1.0",
+            &[
+                ("alpha/__init__.py", "a = 1"),
+                ("alpha/core.py", "b = 2"),
+                ("alpha/util.py", "c = 3"),
+            ],
+        );
+        mock_package(
+            &site_packages,
+            "beta",
+            "2.0",
+            &[("beta/__init__.py", "d = 4")],
+        );
+
+        // same size, different contents
+        fs::write(site_packages.join("alpha/core.py"), "b = 3").unwrap();
+        fs::remove_file(site_packages.join("alpha/util.py")).unwrap();
+        fs::write(site_packages.join("alpha/patch.py"), "e = 5").unwrap();
+
+        let venv = Venv::from_path(root.path()).unwrap();
+        let mut report = verify_venv(&venv).unwrap();
+        report.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let alpha = &report[0];
+        assert_eq!(alpha.name, "alpha");
+        assert_eq!(alpha.modified, [PathBuf::from("alpha/core.py")]);
+        assert_eq!(alpha.missing, [PathBuf::from("alpha/util.py")]);
+        assert_eq!(alpha.extra, [PathBuf::from("alpha/patch.py")]);
+        assert!(alpha.errors.is_empty());
+        assert!(!alpha.is_intact());
+
+        let beta = &report[1];
+        assert_eq!(beta.name, "beta");
+        assert!(beta.is_intact());
+    }
+}
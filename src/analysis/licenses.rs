@@ -0,0 +1,564 @@
+//! License inventory of a venv from the metadata of its distributions.
+
+use std::{collections::BTreeMap, fmt};
+
+use crate::venv::{Venv, metadata::Metadata};
+
+/// License shown for distributions that don't declare one we can read.
+pub const UNKNOWN_LICENSE: &str = "UNKNOWN";
+
+/// SPDX ids of the license classifiers that name a single license.
+const CLASSIFIER_IDS: &[(&str, &str)] = &[
+    ("MIT License", "MIT"),
+    ("MIT No Attribution License (MIT-0)", "MIT-0"),
+    ("ISC License (ISCL)", "ISC"),
+    ("Zero-Clause BSD (0BSD)", "0BSD"),
+    ("Apache Software License", "Apache-2.0"),
+    ("Python Software Foundation License", "PSF-2.0"),
+    ("Python License (CNRI Python License)", "CNRI-Python"),
+    ("Mozilla Public License 1.0 (MPL)", "MPL-1.0"),
+    ("Mozilla Public License 1.1 (MPL 1.1)", "MPL-1.1"),
+    ("Mozilla Public License 2.0 (MPL 2.0)", "MPL-2.0"),
+    ("The Unlicense (Unlicense)", "Unlicense"),
+    ("Zope Public License", "ZPL-2.1"),
+    ("zlib/libpng License", "Zlib"),
+    ("PostgreSQL License", "PostgreSQL"),
+    ("Historical Permission Notice and Disclaimer (HPND)", "HPND"),
+    ("University of Illinois/NCSA Open Source License", "NCSA"),
+    ("Universal Permissive License (UPL)", "UPL-1.0"),
+    ("Boost Software License 1.0 (BSL-1.0)", "BSL-1.0"),
+    ("SIL Open Font License 1.1 (OFL-1.1)", "OFL-1.1"),
+    ("Eclipse Public License 1.0 (EPL-1.0)", "EPL-1.0"),
+    ("Eclipse Public License 2.0 (EPL-2.0)", "EPL-2.0"),
+    (
+        "Common Development and Distribution License 1.0 (CDDL-1.0)",
+        "CDDL-1.0",
+    ),
+    ("European Union Public Licence 1.0 (EUPL 1.0)", "EUPL-1.0"),
+    ("European Union Public Licence 1.1 (EUPL 1.1)", "EUPL-1.1"),
+    ("European Union Public Licence 1.2 (EUPL 1.2)", "EUPL-1.2"),
+    ("Open Software License 3.0 (OSL-3.0)", "OSL-3.0"),
+    (
+        "Mulan Permissive Software License v2 (MulanPSL-2.0)",
+        "MulanPSL-2.0",
+    ),
+    ("Sleepycat License", "Sleepycat"),
+    ("GNU General Public License v2 (GPLv2)", "GPL-2.0-only"),
+    (
+        "GNU General Public License v2 or later (GPLv2+)",
+        "GPL-2.0-or-later",
+    ),
+    ("GNU General Public License v3 (GPLv3)", "GPL-3.0-only"),
+    (
+        "GNU General Public License v3 or later (GPLv3+)",
+        "GPL-3.0-or-later",
+    ),
+    (
+        "GNU Lesser General Public License v2 (LGPLv2)",
+        "LGPL-2.0-only",
+    ),
+    (
+        "GNU Lesser General Public License v2 or later (LGPLv2+)",
+        "LGPL-2.0-or-later",
+    ),
+    (
+        "GNU Lesser General Public License v3 (LGPLv3)",
+        "LGPL-3.0-only",
+    ),
+    (
+        "GNU Lesser General Public License v3 or later (LGPLv3+)",
+        "LGPL-3.0-or-later",
+    ),
+    ("GNU Affero General Public License v3", "AGPL-3.0-only"),
+    (
+        "GNU Affero General Public License v3 or later (AGPLv3+)",
+        "AGPL-3.0-or-later",
+    ),
+];
+
+/// SPDX ids of the ways a license is commonly written in the free text `License` field, keyed
+/// by [`name_key`]. Names that don't say which version, like `GPL` or `BSD`, are left out.
+const FREE_TEXT_IDS: &[(&str, &str)] = &[
+    ("mit", "MIT"),
+    ("isc", "ISC"),
+    ("0bsd", "0BSD"),
+    ("bsd2clause", "BSD-2-Clause"),
+    ("2clausebsd", "BSD-2-Clause"),
+    ("simplifiedbsd", "BSD-2-Clause"),
+    ("freebsd", "BSD-2-Clause"),
+    ("bsd3clause", "BSD-3-Clause"),
+    ("3clausebsd", "BSD-3-Clause"),
+    ("newbsd", "BSD-3-Clause"),
+    ("modifiedbsd", "BSD-3-Clause"),
+    ("revisedbsd", "BSD-3-Clause"),
+    ("apache2", "Apache-2.0"),
+    ("asl2", "Apache-2.0"),
+    ("psf", "PSF-2.0"),
+    ("psf2", "PSF-2.0"),
+    ("psfl", "PSF-2.0"),
+    ("pythonfoundation", "PSF-2.0"),
+    ("mpl2", "MPL-2.0"),
+    ("mozillapublic2", "MPL-2.0"),
+    ("unlicense", "Unlicense"),
+    ("cc0", "CC0-1.0"),
+    ("cc01", "CC0-1.0"),
+    ("zlib", "Zlib"),
+    ("bsl1", "BSL-1.0"),
+    ("boost", "BSL-1.0"),
+    ("gpl2", "GPL-2.0-only"),
+    ("gpl2+", "GPL-2.0-or-later"),
+    ("gpl3", "GPL-3.0-only"),
+    ("gpl3+", "GPL-3.0-or-later"),
+    ("lgpl2", "LGPL-2.0-only"),
+    ("lgpl2+", "LGPL-2.0-or-later"),
+    ("lgpl2.1", "LGPL-2.1-only"),
+    ("lgpl2.1+", "LGPL-2.1-or-later"),
+    ("lgpl3", "LGPL-3.0-only"),
+    ("lgpl3+", "LGPL-3.0-or-later"),
+    ("agpl3", "AGPL-3.0-only"),
+    ("agpl3+", "AGPL-3.0-or-later"),
+];
+
+/// Classifiers that say nothing about which license it is.
+const VAGUE_CLASSIFIERS: [&str; 2] = ["OSI Approved", "DFSG approved"];
+
+/// The license of a distribution as it declares it.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum License {
+    /// The `License-Expression`, or the SPDX ids of the license classifiers
+    Spdx(String),
+    /// Classifiers without an SPDX id or a short free text `License` field, several are read
+    /// as alternatives separated by ` OR `
+    Named(String),
+    /// Nothing, `UNKNOWN`, or the whole license text in the `License` field
+    Unknown,
+}
+
+impl fmt::Display for License {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            License::Spdx(text) | License::Named(text) => f.write_str(text),
+            License::Unknown => f.write_str(UNKNOWN_LICENSE),
+        }
+    }
+}
+
+/// Whether a license can be used under a deny list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LicenseStatus {
+    Allowed,
+    /// The license, or one of the alternatives that would decide, couldn't be identified
+    NeedsReview,
+    /// Can't be complied with without a denied license
+    Denied,
+}
+
+impl LicenseStatus {
+    fn or(self, other: LicenseStatus) -> LicenseStatus {
+        self.min(other)
+    }
+
+    fn and(self, other: LicenseStatus) -> LicenseStatus {
+        self.max(other)
+    }
+}
+
+/// The license of a distribution. `License-Expression` wins over the classifiers, which win
+/// over the free text `License` field. Several classifiers are read as alternatives.
+pub fn license_of(metadata: &Metadata) -> License {
+    if let Some(expression) = &metadata.license_expression {
+        let expression = expression.trim().to_string();
+        return if is_expression(&expression) {
+            License::Spdx(expression)
+        } else {
+            License::Named(expression)
+        };
+    }
+
+    let classifiers: Vec<(&str, bool)> = metadata
+        .license_classifiers
+        .iter()
+        .filter_map(|c| c.rsplit(" :: ").next())
+        .filter(|name| !VAGUE_CLASSIFIERS.contains(name))
+        .map(|name| {
+            CLASSIFIER_IDS
+                .iter()
+                .find(|(classifier, _)| *classifier == name)
+                .map_or((name, false), |(_, id)| (*id, true))
+        })
+        .collect();
+    if !classifiers.is_empty() {
+        let names: Vec<&str> = classifiers.iter().map(|(name, _)| *name).collect();
+        let text = names.join(" OR ");
+        return if classifiers.iter().all(|(_, is_id)| *is_id) {
+            License::Spdx(text)
+        } else {
+            License::Named(text)
+        };
+    }
+
+    // a folded field has its continuation lines joined with newlines
+    let license = metadata
+        .license
+        .as_deref()
+        .unwrap_or_default()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    // a long field is the license text itself, which names no license
+    if license.is_empty() || license == UNKNOWN_LICENSE || license.len() > 64 {
+        License::Unknown
+    } else {
+        License::Named(license)
+    }
+}
+
+/// Whether `license` can be used with the `denied` licenses. An SPDX expression is denied
+/// only if every alternative includes a denied license, `MIT OR GPL-3.0` is fine. Names are
+/// matched to SPDX ids where it's clear which license they mean, `GNU GPL v3` is
+/// `GPL-3.0-only`, and otherwise compared as a whole. Denying `GPL-3.0` also denies its
+/// `-only`, `-or-later` and `+` variants. Licenses that can't be identified need review, unless
+/// an alternative is allowed anyway.
+pub fn license_status(license: &License, denied: &[String]) -> LicenseStatus {
+    match license {
+        License::Spdx(expression) => {
+            let tokens = tokenize(expression);
+            let mut parser = ExpressionParser {
+                tokens: &tokens,
+                pos: 0,
+                denied,
+            };
+            match parser.or_expr() {
+                Some(status) if parser.pos == tokens.len() => status,
+                _ => LicenseStatus::NeedsReview,
+            }
+        }
+        License::Named(names) => names
+            .split(" OR ")
+            .map(|name| name_status(name, denied))
+            .fold(LicenseStatus::Denied, LicenseStatus::or),
+        License::Unknown => LicenseStatus::NeedsReview,
+    }
+}
+
+fn name_status(name: &str, denied: &[String]) -> LicenseStatus {
+    match spdx_id_of_name(name) {
+        Some(id) => id_status(id, denied),
+        None if denied.iter().any(|d| d.eq_ignore_ascii_case(name.trim())) => LicenseStatus::Denied,
+        None => LicenseStatus::NeedsReview,
+    }
+}
+
+fn id_status(id: &str, denied: &[String]) -> LicenseStatus {
+    if denied.iter().any(|d| id_matches(id, d)) {
+        LicenseStatus::Denied
+    } else if id.starts_with("LicenseRef-") || id.starts_with("DocumentRef-") {
+        // a license of its own, nothing says what it allows
+        LicenseStatus::NeedsReview
+    } else {
+        LicenseStatus::Allowed
+    }
+}
+
+/// The SPDX id a license name clearly means, from the ids and classifiers we know and the
+/// common ways of writing them.
+fn spdx_id_of_name(name: &str) -> Option<&'static str> {
+    let name = name.trim();
+    let known = CLASSIFIER_IDS.iter().chain(FREE_TEXT_IDS);
+    if let Some((_, id)) = known.clone().find(|(_, id)| id.eq_ignore_ascii_case(name)) {
+        return Some(id);
+    }
+    if let Some((_, id)) = CLASSIFIER_IDS.iter().find(|(c, _)| *c == name) {
+        return Some(id);
+    }
+    let key = name_key(name);
+    FREE_TEXT_IDS
+        .iter()
+        .find(|(k, _)| *k == key)
+        .map(|(_, id)| *id)
+}
+
+/// A license name reduced to its distinguishing words, `GNU GPL v3`, `GPLv3` and
+/// `GNU General Public License, version 3.0` all give `gpl3`.
+fn name_key(name: &str) -> String {
+    let name = name
+        .to_ascii_lowercase()
+        .replace("lesser general public", "lgpl")
+        .replace("library general public", "lgpl")
+        .replace("affero general public", "agpl")
+        .replace("general public", "gpl")
+        .replace("or later", "+")
+        .replace("or-later", "+");
+    let mut words: Vec<String> = Vec::new();
+    let split = name.split(|c: char| !(c.is_ascii_alphanumeric() || c == '.' || c == '+'));
+    for word in split.filter(|w| !w.is_empty()) {
+        if matches!(
+            word,
+            "the" | "gnu" | "license" | "licence" | "version" | "software" | "only" | "v"
+        ) {
+            continue;
+        }
+        let (word, plus) = match word.strip_suffix('+') {
+            Some(word) => (word, "+"),
+            None => (word, ""),
+        };
+        // `gplv3` and `v3`
+        let word = match word.rfind('v') {
+            Some(i) if word[i + 1..].starts_with(|c: char| c.is_ascii_digit()) => {
+                format!("{}{}", &word[..i], &word[i + 1..])
+            }
+            _ => word.to_string(),
+        };
+        // `3.0` and `3`
+        let mut word = word.as_str();
+        while word.len() > 2 && word.ends_with(".0") {
+            word = &word[..word.len() - 2];
+        }
+        let word = format!("{word}{plus}");
+        // `GNU General Public License (GPL)`
+        if words.last() != Some(&word) {
+            words.push(word);
+        }
+    }
+    words.concat()
+}
+
+/// Whether `license` is written like an SPDX expression. The ids aren't checked against the
+/// license list, `License-Expression` is validated by the build backends.
+fn is_expression(license: &str) -> bool {
+    let tokens: Vec<String> = tokenize(license);
+    let mut parser = ExpressionParser {
+        tokens: &tokens,
+        pos: 0,
+        denied: &[],
+    };
+    license != UNKNOWN_LICENSE
+        && parser.or_expr().is_some()
+        && parser.pos == tokens.len()
+        && tokens.iter().all(|t| {
+            t.chars().all(|c| {
+                c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '+' | ':' | '(' | ')')
+            })
+        })
+}
+
+fn tokenize(license: &str) -> Vec<String> {
+    license
+        .replace('(', " ( ")
+        .replace(')', " ) ")
+        .split_whitespace()
+        .map(str::to_string)
+        .collect()
+}
+
+fn id_matches(id: &str, denied: &str) -> bool {
+    let id = id.to_ascii_lowercase();
+    let denied = denied.to_ascii_lowercase();
+    id == denied || id == format!("{denied}+") || id.starts_with(&format!("{denied}-"))
+}
+
+/// Recursive descent over an SPDX expression, evaluating whether it can be satisfied without a
+/// denied license. `None` if the text isn't an expression.
+struct ExpressionParser<'a> {
+    tokens: &'a [String],
+    pos: usize,
+    denied: &'a [String],
+}
+
+impl ExpressionParser<'_> {
+    fn peek_is(&self, keyword: &str) -> bool {
+        self.tokens
+            .get(self.pos)
+            .is_some_and(|t| t.eq_ignore_ascii_case(keyword))
+    }
+
+    fn or_expr(&mut self) -> Option<LicenseStatus> {
+        let mut status = self.and_expr()?;
+        while self.peek_is("OR") {
+            self.pos += 1;
+            status = status.or(self.and_expr()?);
+        }
+        Some(status)
+    }
+
+    fn and_expr(&mut self) -> Option<LicenseStatus> {
+        let mut status = self.atom()?;
+        while self.peek_is("AND") {
+            self.pos += 1;
+            status = status.and(self.atom()?);
+        }
+        Some(status)
+    }
+
+    fn atom(&mut self) -> Option<LicenseStatus> {
+        let token = self.tokens.get(self.pos)?;
+        self.pos += 1;
+        if token == "(" {
+            let status = self.or_expr()?;
+            if self.tokens.get(self.pos).is_none_or(|t| t != ")") {
+                return None;
+            }
+            self.pos += 1;
+            return Some(status);
+        }
+        if token == ")"
+            || ["AND", "OR", "WITH"]
+                .iter()
+                .any(|k| token.eq_ignore_ascii_case(k))
+        {
+            return None;
+        }
+        // the exception only loosens the license
+        if self.peek_is("WITH") {
+            self.pos += 1;
+            self.tokens.get(self.pos)?;
+            self.pos += 1;
+        }
+        Some(id_status(token, self.denied))
+    }
+}
+
+/// Distributions of a venv that share a license.
+#[derive(Debug, Clone)]
+pub struct LicenseGroup {
+    pub license: License,
+    /// Names and versions, sorted by name
+    pub packages: Vec<(String, String)>,
+    pub status: LicenseStatus,
+}
+
+/// Groups the packages of `venv` by license, sorted by license.
+pub fn license_report(venv: &Venv, denied: &[String]) -> Vec<LicenseGroup> {
+    let mut groups: BTreeMap<License, Vec<(String, String)>> = BTreeMap::new();
+    for pkg in &venv.packages {
+        groups
+            .entry(license_of(&pkg.metadata))
+            .or_default()
+            .push((pkg.name.clone(), pkg.version.clone()));
+    }
+    groups
+        .into_iter()
+        .map(|(license, mut packages)| {
+            packages.sort();
+            LicenseGroup {
+                status: license_status(&license, denied),
+                license,
+                packages,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::venv::metadata::MetadataBuilder;
+
+    fn spdx(expression: &str) -> License {
+        License::Spdx(expression.to_string())
+    }
+
+    fn named(name: &str) -> License {
+        License::Named(name.to_string())
+    }
+
+    #[test]
+    fn test_license_of() {
+        let mut builder = MetadataBuilder::new();
+        builder.license(
+            "Copyright (c) 2010 the authors, all rights reserved. Permission is hereby granted"
+                .to_string(),
+        );
+        assert_eq!(license_of(&builder.build()), License::Unknown);
+
+        builder.license("BSD 3-Clause".to_string());
+        assert_eq!(license_of(&builder.build()), named("BSD 3-Clause"));
+
+        builder.license("GNU GPL\n  v3".to_string());
+        assert_eq!(license_of(&builder.build()), named("GNU GPL v3"));
+
+        builder.add_license_classifier(
+            "OSI Approved :: GNU General Public License v3 (GPLv3)".to_string(),
+        );
+        assert_eq!(license_of(&builder.build()), spdx("GPL-3.0-only"));
+
+        builder.add_license_classifier("OSI Approved :: BSD License".to_string());
+        assert_eq!(
+            license_of(&builder.build()),
+            named("GPL-3.0-only OR BSD License")
+        );
+
+        builder.license_expression("Apache-2.0 OR MIT".to_string());
+        assert_eq!(license_of(&builder.build()), spdx("Apache-2.0 OR MIT"));
+
+        builder.license_expression("Apache 2.0".to_string());
+        assert_eq!(license_of(&builder.build()), named("Apache 2.0"));
+
+        let mut builder = MetadataBuilder::new();
+        builder
+            .add_license_classifier("OSI Approved".to_string())
+            .add_license_classifier("Other/Proprietary License".to_string());
+        assert_eq!(
+            license_of(&builder.build()),
+            named("Other/Proprietary License")
+        );
+    }
+
+    #[test]
+    fn test_ids_are_spdx() {
+        for (_, id) in CLASSIFIER_IDS.iter().chain(FREE_TEXT_IDS) {
+            assert!(spdx::license_id(id).is_some(), "{id}");
+        }
+    }
+
+    #[test]
+    fn test_license_status() {
+        use LicenseStatus::*;
+
+        let denied = ["GPL-3.0".to_string(), "Proprietary License".to_string()];
+        let status = |license: License| license_status(&license, &denied);
+        assert_eq!(status(spdx("GPL-3.0-only")), Denied);
+        assert_eq!(status(spdx("gpl-3.0+")), Denied);
+        assert_eq!(status(spdx("LGPL-3.0-only")), Allowed);
+        assert_eq!(status(spdx("MIT OR GPL-3.0-or-later")), Allowed);
+        assert_eq!(status(spdx("MIT AND GPL-3.0-or-later")), Denied);
+        assert_eq!(status(spdx("(MIT OR Apache-2.0) AND GPL-3.0-only")), Denied);
+        assert_eq!(
+            status(spdx("GPL-2.0-only WITH Classpath-exception-2.0")),
+            Allowed
+        );
+        assert_eq!(status(named("Proprietary License")), Denied);
+        assert_eq!(status(named("MIT License")), Allowed);
+        assert_eq!(license_status(&spdx("MIT"), &[]), Allowed);
+
+        // free text naming a denied license
+        for name in [
+            "GPLv3",
+            "GNU GPL v3",
+            "GPL-3",
+            "GNU General Public License, version 3",
+            "GPLv3+",
+        ] {
+            assert_eq!(status(named(name)), Denied, "{name}");
+        }
+        assert_eq!(status(named("GPLv2 or later")), Allowed);
+        assert_eq!(status(named("Apache License, Version 2.0")), Allowed);
+
+        // no telling which GPL
+        assert_eq!(
+            status(named("GNU General Public License (GPL)")),
+            NeedsReview
+        );
+        assert_eq!(status(License::Unknown), NeedsReview);
+        assert_eq!(license_status(&License::Unknown, &[]), NeedsReview);
+        assert_eq!(status(named("BSD License OR GPL-3.0-only")), NeedsReview);
+        assert_eq!(status(named("MIT License OR GPL-3.0-only")), Allowed);
+        assert_eq!(status(spdx("LicenseRef-Acme OR GPL-3.0-only")), NeedsReview);
+        assert_eq!(status(spdx("MIT OR")), NeedsReview);
+
+        assert!(is_expression("(MIT OR Apache-2.0) AND BSD-3-Clause"));
+        assert!(!is_expression("MIT License"));
+        assert!(!is_expression("MIT OR"));
+        assert!(!is_expression(UNKNOWN_LICENSE));
+    }
+}
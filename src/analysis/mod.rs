@@ -0,0 +1,21 @@
+//! Reports that look into the packages of the cached venvs.
+
+pub mod dedupe;
+pub mod diff;
+pub mod drift;
+pub mod duplicates;
+pub mod integrity;
+pub mod licenses;
+pub mod portability;
+pub mod startup;
+pub mod vulns;
+
+pub use dedupe::{DedupeOutcome, DedupePlan, apply_dedupe, plan_dedupe};
+pub use diff::{ChangeKind, VenvDiff, diff_venvs};
+pub use drift::{Drift, check_drift, find_lock_file};
+pub use duplicates::{DuplicateGroup, LinkState, find_duplicates};
+pub use integrity::{PackageIntegrity, verify_venv};
+pub use licenses::{License, LicenseGroup, LicenseStatus, license_of, license_report};
+pub use portability::{PortabilityReport, portability_report};
+pub use startup::{HookKind, StartupHook, find_startup_hooks};
+pub use vulns::{AdvisoryDb, Vulnerability, audit_venv};
@@ -0,0 +1,141 @@
+//! Whether a venv can be copied to another machine, and which packages prevent it.
+
+use crate::venv::{Venv, wheel::Portability};
+
+/// A distribution built for a platform or interpreter ABI.
+#[derive(Debug, Clone)]
+pub struct PlatformPackage {
+    pub name: String,
+    pub version: String,
+    /// Platform tags of its wheel other than `any`
+    pub platforms: Vec<String>,
+    /// ABI tags of its wheel other than `none`
+    pub abis: Vec<String>,
+    /// Extension modules and shared libraries, relative to site-packages
+    pub native_files: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PortabilityReport {
+    /// Sorted by name
+    pub platform_specific: Vec<PlatformPackage>,
+    /// Names and versions of the packages without a `WHEEL` or native files to tell
+    pub unknown: Vec<(String, String)>,
+    pub pure: usize,
+}
+
+impl PortabilityReport {
+    /// Nothing ties the venv to the platform, only to its Python version.
+    pub fn is_portable(&self) -> bool {
+        self.platform_specific.is_empty()
+    }
+
+    /// Every ABI tag the platform-specific packages were built for.
+    pub fn abis(&self) -> Vec<&str> {
+        collect_unique(self.platform_specific.iter().flat_map(|p| &p.abis))
+    }
+
+    /// Every platform tag the platform-specific packages were built for.
+    pub fn platforms(&self) -> Vec<&str> {
+        collect_unique(self.platform_specific.iter().flat_map(|p| &p.platforms))
+    }
+}
+
+fn collect_unique<'a>(values: impl Iterator<Item = &'a String>) -> Vec<&'a str> {
+    let mut unique: Vec<&str> = values.map(String::as_str).collect();
+    unique.sort_unstable();
+    unique.dedup();
+    unique
+}
+
+/// Sorts the packages of `venv` into pure, platform-specific and unknown ones.
+pub fn portability_report(venv: &Venv) -> PortabilityReport {
+    let mut report = PortabilityReport {
+        platform_specific: Vec::new(),
+        unknown: Vec::new(),
+        pure: 0,
+    };
+    for pkg in &venv.packages {
+        match pkg.portability() {
+            Portability::Pure => report.pure += 1,
+            Portability::Unknown => report.unknown.push((pkg.name.clone(), pkg.version.clone())),
+            Portability::PlatformSpecific => report.platform_specific.push(PlatformPackage {
+                name: pkg.name.clone(),
+                version: pkg.version.clone(),
+                platforms: pkg
+                    .wheel
+                    .as_ref()
+                    .map(|w| w.platforms())
+                    .unwrap_or_default(),
+                abis: pkg.wheel.as_ref().map(|w| w.abis()).unwrap_or_default(),
+                native_files: pkg.native_files.clone(),
+            }),
+        }
+    }
+    report
+        .platform_specific
+        .sort_by_key(|p| p.name.to_lowercase());
+    report.unknown.sort();
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::venv::test_utils::{mock_package, mock_venv};
+
+    #[test]
+    fn test_portability_report() {
+        let root = tempdir().unwrap();
+        let site_packages = mock_venv(root.path());
+        let pure = mock_package(&site_packages, "pure", "1.0", &[("pure/__init__.py", "")]);
+        fs::write(
+            pure.join("WHEEL"),
+            "Root-Is-Purelib: true\nTag: py3-none-any\n",
+        )
+        .unwrap();
+        let native = mock_package(
+            &site_packages,
+            "native",
+            "2.0",
+            &[
+                ("native/__init__.py", ""),
+                ("native/_core.cpython-311-x86_64-linux-gnu.so", "\x7fELF"),
+            ],
+        );
+        fs::write(
+            native.join("WHEEL"),
+            "Root-Is-Purelib: false\nTag: cp311-cp311-manylinux_2_17_x86_64\n",
+        )
+        .unwrap();
+        mock_package(&site_packages, "legacy", "0.1", &[("legacy.py", "")]);
+        // an unreadable WHEEL tells nothing, but doesn't fail the parse
+        let broken = mock_package(&site_packages, "broken", "0.2", &[("broken.py", "")]);
+        fs::write(broken.join("WHEEL"), b"Tag: py3-none-any\n\xff\xfe").unwrap();
+
+        let venv = Venv::from_path(root.path()).unwrap();
+        let report = portability_report(&venv);
+        assert!(!report.is_portable());
+        assert_eq!(report.pure, 1);
+        assert_eq!(
+            report.unknown,
+            [
+                ("broken".to_string(), "0.2".to_string()),
+                ("legacy".to_string(), "0.1".to_string())
+            ]
+        );
+        assert_eq!(report.platform_specific.len(), 1);
+        let native = &report.platform_specific[0];
+        assert_eq!(native.name, "native");
+        assert_eq!(
+            native.native_files,
+            ["native/_core.cpython-311-x86_64-linux-gnu.so"]
+        );
+        assert_eq!(report.abis(), ["cp311"]);
+        assert_eq!(report.platforms(), ["manylinux_2_17_x86_64"]);
+    }
+}
@@ -0,0 +1,215 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::{Result, eyre::WrapErr};
+
+use crate::venv::{Venv, record::read_record};
+
+/// Kinds of files the interpreter runs at startup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookKind {
+    /// `.pth` file, its `import` lines are executed by `site`
+    Pth,
+    Sitecustomize,
+    Usercustomize,
+}
+
+impl HookKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            HookKind::Pth => ".pth",
+            HookKind::Sitecustomize => "sitecustomize",
+            HookKind::Usercustomize => "usercustomize",
+        }
+    }
+}
+
+/// A line of a startup hook.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HookLine {
+    /// 1-based line number
+    pub number: usize,
+    pub text: String,
+    /// The line imports or executes code
+    pub executes: bool,
+}
+
+/// A file in site-packages that runs when the interpreter starts.
+#[derive(Debug, Clone)]
+pub struct StartupHook {
+    pub path: PathBuf,
+    pub kind: HookKind,
+    /// Distribution that lists the file in its `RECORD`
+    pub owner: Option<String>,
+    /// Lines of the file, comments and blank lines left out
+    pub lines: Vec<HookLine>,
+}
+
+impl StartupHook {
+    pub fn executes(&self) -> bool {
+        self.lines.iter().any(|l| l.executes)
+    }
+}
+
+/// Lists the `.pth` files and `sitecustomize`/`usercustomize` modules in the site-packages of
+/// `venv`, each attributed to the distribution whose `RECORD` lists it.
+pub fn find_startup_hooks(venv: &Venv) -> Result<Vec<StartupHook>> {
+    let site_packages = venv.site_packages()?;
+
+    let mut hooks = Vec::new();
+    let entries = fs::read_dir(&site_packages)
+        .with_context(|| format!("Failed to read {}", site_packages.display()))?;
+    for entry in entries.filter_map(Result::ok) {
+        let path = entry.path();
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        let (kind, source) = match name {
+            _ if name.ends_with(".pth") => (HookKind::Pth, path.clone()),
+            "sitecustomize.py" => (HookKind::Sitecustomize, path.clone()),
+            "usercustomize.py" => (HookKind::Usercustomize, path.clone()),
+            "sitecustomize" => (HookKind::Sitecustomize, path.join("__init__.py")),
+            "usercustomize" => (HookKind::Usercustomize, path.join("__init__.py")),
+            _ => continue,
+        };
+        let Ok(contents) = fs::read_to_string(&source) else {
+            continue;
+        };
+        let lines = match kind {
+            HookKind::Pth => pth_lines(&contents),
+            _ => module_lines(&contents),
+        };
+        hooks.push(StartupHook {
+            owner: None,
+            path: source,
+            kind,
+            lines,
+        });
+    }
+    hooks.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let mut unowned: HashMap<PathBuf, &mut StartupHook> = hooks
+        .iter_mut()
+        .filter_map(|h| Some((h.path.strip_prefix(&site_packages).ok()?.to_path_buf(), h)))
+        .collect();
+    for pkg in &venv.packages {
+        let Ok(record) = read_record(&pkg.dist_info) else {
+            continue;
+        };
+        for entry in record {
+            if let Some(hook) = unowned.remove(Path::new(&entry.path)) {
+                hook.owner = Some(pkg.name.clone());
+            }
+        }
+    }
+    Ok(hooks)
+}
+
+/// `site` skips comments and blank lines, runs lines that start with `import` and adds every
+/// other line to `sys.path`.
+fn pth_lines(contents: &str) -> Vec<HookLine> {
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.starts_with('#'))
+        .map(|(i, line)| HookLine {
+            number: i + 1,
+            text: line.to_string(),
+            executes: line.starts_with("import ") || line.starts_with("import\t"),
+        })
+        .collect()
+}
+
+/// The whole module runs at startup, so only the lines that pull in or evaluate more code are
+/// highlighted.
+fn module_lines(contents: &str) -> Vec<HookLine> {
+    const EXECUTING: [&str; 5] = [
+        "exec(",
+        "eval(",
+        "compile(",
+        "__import__(",
+        "import_module(",
+    ];
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| {
+            let trimmed = line.trim();
+            !trimmed.is_empty() && !trimmed.starts_with('#')
+        })
+        .map(|(i, line)| {
+            let trimmed = line.trim_start();
+            HookLine {
+                number: i + 1,
+                text: line.to_string(),
+                executes: trimmed.starts_with("import ")
+                    || trimmed.starts_with("from ")
+                    || EXECUTING.iter().any(|e| line.contains(e)),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::venv::test_utils::{mock_package, mock_venv};
+
+    #[test]
+    fn test_find_startup_hooks() {
+        let root = tempdir().unwrap();
+        let site_packages = mock_venv(root.path());
+        mock_package(
+            &site_packages,
+            "alpha",
+            "1.0",
+            &[
+                ("alpha/__init__.py", ""),
+                (
+                    "alpha.pth",
+                    "# comment\n/some/path\nimport os; exec(os.environ['X'])\n",
+                ),
+            ],
+        );
+        fs::write(site_packages.join("zzz.pth"), "/other/path\n").unwrap();
+        fs::write(
+            site_packages.join("sitecustomize.py"),
+            "import sys\nx = 1\neval('x')\n",
+        )
+        .unwrap();
+
+        let venv = Venv::from_path(root.path()).unwrap();
+        let hooks = find_startup_hooks(&venv).unwrap();
+        let summary: Vec<(&str, HookKind, Option<&str>, bool)> = hooks
+            .iter()
+            .map(|h| {
+                (
+                    h.path.file_name().unwrap().to_str().unwrap(),
+                    h.kind,
+                    h.owner.as_deref(),
+                    h.executes(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("alpha.pth", HookKind::Pth, Some("alpha"), true),
+                ("sitecustomize.py", HookKind::Sitecustomize, None, true),
+                ("zzz.pth", HookKind::Pth, None, false),
+            ]
+        );
+
+        let pth = &hooks[0].lines;
+        assert_eq!(pth.len(), 2);
+        assert_eq!((pth[0].number, pth[0].executes), (2, false));
+        assert_eq!((pth[1].number, pth[1].executes), (3, true));
+        let executes: Vec<bool> = hooks[1].lines.iter().map(|l| l.executes).collect();
+        assert_eq!(executes, [true, false, true]);
+    }
+}
@@ -0,0 +1,458 @@
+//! Matching installed packages against a local dump of OSV advisories.
+
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::Read,
+    path::Path,
+};
+
+use color_eyre::{
+    Result,
+    eyre::{WrapErr, eyre},
+};
+use rayon::prelude::*;
+use serde::Deserialize;
+use serde_json::Value;
+use walkdir::WalkDir;
+use yaml_rust2::{Yaml, YamlLoader};
+
+use crate::venv::{Venv, utils::canonical_name, version::Version};
+
+/// An OSV advisory, only the fields the audit needs.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Advisory {
+    pub id: String,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    #[serde(default)]
+    pub summary: String,
+    #[serde(default)]
+    withdrawn: Option<String>,
+    #[serde(default)]
+    affected: Vec<Affected>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Affected {
+    package: Option<AffectedPackage>,
+    #[serde(default)]
+    ranges: Vec<Range>,
+    #[serde(default)]
+    versions: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct AffectedPackage {
+    ecosystem: String,
+    name: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Range {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    events: Vec<Event>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct Event {
+    introduced: Option<String>,
+    fixed: Option<String>,
+    last_affected: Option<String>,
+    limit: Option<String>,
+}
+
+impl Advisory {
+    fn affected_for<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Affected> {
+        self.affected.iter().filter(move |a| {
+            a.package
+                .as_ref()
+                .is_some_and(|p| p.ecosystem == "PyPI" && canonical_name(&p.name) == name)
+        })
+    }
+
+    /// Whether `version` of the package with the canonical `name` is affected.
+    pub fn affects(&self, name: &str, version: &Version) -> bool {
+        self.affected_for(name).any(|a| a.affects(version))
+    }
+
+    /// Versions that fix the advisory for the package with the canonical `name`.
+    pub fn fixed_versions(&self, name: &str) -> Vec<String> {
+        self.affected_for(name)
+            .flat_map(|a| &a.ranges)
+            .flat_map(|r| &r.events)
+            .filter_map(|e| e.fixed.clone())
+            .collect()
+    }
+}
+
+impl Affected {
+    fn affects(&self, version: &Version) -> bool {
+        let listed = self
+            .versions
+            .iter()
+            .any(|v| v.parse::<Version>().is_ok_and(|v| v == *version));
+        listed
+            || self
+                .ranges
+                .iter()
+                .filter(|r| r.kind == "ECOSYSTEM")
+                .any(|r| r.affects(version))
+    }
+}
+
+impl Range {
+    /// Evaluates the events in version order as the OSV schema describes. Events with versions
+    /// that aren't PEP 440 are ignored.
+    fn affects(&self, version: &Version) -> bool {
+        let parse = |v: &Option<String>| -> Option<Option<Version>> {
+            match v.as_deref() {
+                None => Some(None),
+                // introduced at "0" parses as the lowest release
+                Some(v) => v.parse().ok().map(Some),
+            }
+        };
+
+        let limits: Vec<Version> = self
+            .events
+            .iter()
+            .filter_map(|e| parse(&e.limit).flatten())
+            .collect();
+        if !limits.is_empty() && limits.iter().all(|limit| version >= limit) {
+            return false;
+        }
+
+        let mut events: Vec<(Version, &Event)> = self
+            .events
+            .iter()
+            .filter_map(|e| {
+                let v = parse(&e.introduced)?
+                    .or(parse(&e.fixed)?)
+                    .or(parse(&e.last_affected)?)?;
+                Some((v, e))
+            })
+            .collect();
+        events.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        let mut affected = false;
+        for (event_version, event) in events {
+            if event.introduced.is_some() && *version >= event_version {
+                affected = true;
+            } else if (event.fixed.is_some() && *version >= event_version)
+                || (event.last_affected.is_some() && *version > event_version)
+            {
+                affected = false;
+            }
+        }
+        affected
+    }
+}
+
+/// Advisories for PyPI packages, indexed by the canonical name of the affected packages.
+#[derive(Debug, Default)]
+pub struct AdvisoryDb {
+    advisories: Vec<Advisory>,
+    by_package: HashMap<String, Vec<usize>>,
+    /// Files that couldn't be read as an advisory
+    pub skipped: usize,
+}
+
+impl AdvisoryDb {
+    /// Loads the advisories from a directory, searched recursively, or from a zip archive. Both
+    /// the JSON of the osv.dev exports, like `all.zip`, and the YAML of a PyPA advisory-database
+    /// checkout are read. Withdrawn advisories are left out, files that can't be read or parsed
+    /// are counted in `skipped`.
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = if path.is_dir() {
+            read_dir_advisories(path)?
+        } else {
+            read_zip_advisories(path)
+                .with_context(|| format!("Failed to read advisories from {}", path.display()))?
+        };
+
+        let parsed: Vec<Option<Advisory>> = contents
+            .par_iter()
+            .map(|(format, text)| parse_advisory(*format, text.as_deref()?))
+            .collect();
+
+        let mut db = AdvisoryDb::default();
+        for advisory in parsed {
+            match advisory {
+                Some(advisory) if advisory.withdrawn.is_none() => db.insert(advisory),
+                Some(_) => {}
+                None => db.skipped += 1,
+            }
+        }
+        Ok(db)
+    }
+
+    fn insert(&mut self, advisory: Advisory) {
+        let index = self.advisories.len();
+        let mut names: Vec<String> = advisory
+            .affected
+            .iter()
+            .filter_map(|a| a.package.as_ref())
+            .filter(|p| p.ecosystem == "PyPI")
+            .map(|p| canonical_name(&p.name))
+            .collect();
+        if names.is_empty() {
+            return;
+        }
+        names.sort();
+        names.dedup();
+        for name in names {
+            self.by_package.entry(name).or_default().push(index);
+        }
+        self.advisories.push(advisory);
+    }
+
+    pub fn len(&self) -> usize {
+        self.advisories.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.advisories.is_empty()
+    }
+
+    /// Advisories that affect `version` of the package `name`. Versions that aren't PEP 440
+    /// match nothing.
+    pub fn affecting(&self, name: &str, version: &str) -> Vec<&Advisory> {
+        let name = canonical_name(name);
+        let Ok(version) = version.parse::<Version>() else {
+            return Vec::new();
+        };
+        self.by_package
+            .get(&name)
+            .into_iter()
+            .flatten()
+            .map(|&i| &self.advisories[i])
+            .filter(|a| a.affects(&name, &version))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Format {
+    Json,
+    Yaml,
+}
+
+impl Format {
+    fn of(name: &str) -> Option<Format> {
+        if name.ends_with(".json") {
+            Some(Format::Json)
+        } else if name.ends_with(".yaml") || name.ends_with(".yml") {
+            Some(Format::Yaml)
+        } else {
+            None
+        }
+    }
+}
+
+fn parse_advisory(format: Format, text: &str) -> Option<Advisory> {
+    match format {
+        Format::Json => serde_json::from_str(text).ok(),
+        Format::Yaml => {
+            let documents = YamlLoader::load_from_str(text).ok()?;
+            serde_json::from_value(yaml_to_json(documents.first()?)).ok()
+        }
+    }
+}
+
+/// YAML as JSON. Numbers are kept as written, advisories only hold strings and an unquoted
+/// version like `1.10` must not become `1.1`.
+fn yaml_to_json(yaml: &Yaml) -> Value {
+    match yaml {
+        Yaml::String(s) | Yaml::Real(s) => Value::String(s.clone()),
+        Yaml::Integer(i) => Value::String(i.to_string()),
+        Yaml::Boolean(b) => Value::Bool(*b),
+        Yaml::Array(items) => Value::Array(items.iter().map(yaml_to_json).collect()),
+        Yaml::Hash(hash) => Value::Object(
+            hash.iter()
+                .filter_map(|(key, value)| match yaml_to_json(key) {
+                    Value::String(key) => Some((key, yaml_to_json(value))),
+                    _ => None,
+                })
+                .collect(),
+        ),
+        Yaml::Null | Yaml::Alias(_) | Yaml::BadValue => Value::Null,
+    }
+}
+
+/// The advisory files under `dir` with their contents, `None` for files that can't be read.
+fn read_dir_advisories(dir: &Path) -> Result<Vec<(Format, Option<String>)>> {
+    let paths: Vec<_> = WalkDir::new(dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| Some((Format::of(e.file_name().to_str()?)?, e.into_path())))
+        .collect();
+    if paths.is_empty() {
+        return Err(eyre!("No JSON or YAML advisories in {}", dir.display()));
+    }
+    Ok(paths
+        .par_iter()
+        .map(|(format, p)| (*format, fs::read_to_string(p).ok()))
+        .collect())
+}
+
+fn read_zip_advisories(path: &Path) -> Result<Vec<(Format, Option<String>)>> {
+    let mut archive = zip::ZipArchive::new(File::open(path)?)?;
+    let mut contents = Vec::with_capacity(archive.len());
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        let Some(format) = Format::of(file.name()).filter(|_| file.is_file()) else {
+            continue;
+        };
+        let mut text = String::new();
+        let text = file.read_to_string(&mut text).ok().map(|_| text);
+        contents.push((format, text));
+    }
+    Ok(contents)
+}
+
+/// A package of a venv with the advisories that affect its installed version.
+#[derive(Debug, Clone)]
+pub struct Vulnerability {
+    pub name: String,
+    pub version: String,
+    pub advisories: Vec<Advisory>,
+}
+
+/// Vulnerable packages of `venv`, sorted by name.
+pub fn audit_venv(venv: &Venv, db: &AdvisoryDb) -> Vec<Vulnerability> {
+    let mut vulnerable: Vec<Vulnerability> = venv
+        .packages
+        .iter()
+        .filter_map(|pkg| {
+            let advisories = db.affecting(&pkg.name, &pkg.version);
+            (!advisories.is_empty()).then(|| Vulnerability {
+                name: pkg.name.clone(),
+                version: pkg.version.clone(),
+                advisories: advisories.into_iter().cloned().collect(),
+            })
+        })
+        .collect();
+    vulnerable.sort_by(|a, b| a.name.cmp(&b.name));
+    vulnerable
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use tempfile::tempdir;
+
+    use super::*;
+
+    const ADVISORY: &str = r#"{
+        "id": "PYSEC-2024-1",
+        "aliases": ["CVE-2024-0001"],
+        "summary": "Remote code execution",
+        "affected": [{
+            "package": {"ecosystem": "PyPI", "name": "Alpha_Pkg"},
+            "ranges": [{
+                "type": "ECOSYSTEM",
+                "events": [{"introduced": "0"}, {"fixed": "1.2.0"},
+                           {"introduced": "2.0a1"}, {"last_affected": "2.1"}]
+            }],
+            "versions": ["1.0", "1.1"]
+        }]
+    }"#;
+
+    const WITHDRAWN: &str = r#"{
+        "id": "PYSEC-2024-2",
+        "withdrawn": "2024-02-01T00:00:00Z",
+        "affected": [{"package": {"ecosystem": "PyPI", "name": "alpha-pkg"}, "versions": ["1.0"]}]
+    }"#;
+
+    fn check(db: &AdvisoryDb) {
+        assert_eq!(db.len(), 1);
+        let affected = |version| !db.affecting("alpha.pkg", version).is_empty();
+        assert!(affected("1.0"));
+        assert!(affected("1.1.9"));
+        assert!(!affected("1.2"));
+        assert!(!affected("1.9"));
+        assert!(affected("2.0"));
+        assert!(affected("2.1.0"));
+        assert!(!affected("2.1.1"));
+        assert!(!affected("not a version"));
+        assert!(db.affecting("beta", "1.0").is_empty());
+        assert_eq!(
+            db.affecting("alpha-pkg", "1.0")[0].fixed_versions("alpha-pkg"),
+            ["1.2.0"]
+        );
+    }
+
+    #[test]
+    fn test_load_dir() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join("PyPI")).unwrap();
+        fs::write(dir.path().join("PyPI/PYSEC-2024-1.json"), ADVISORY).unwrap();
+        fs::write(dir.path().join("PyPI/PYSEC-2024-2.json"), WITHDRAWN).unwrap();
+        fs::write(dir.path().join("broken.json"), "{").unwrap();
+
+        let db = AdvisoryDb::load(dir.path()).unwrap();
+        assert_eq!(db.skipped, 1);
+        check(&db);
+    }
+
+    #[test]
+    fn test_load_yaml_dir() {
+        // as the PyPA advisory-database writes them, versions unquoted
+        let yaml = r#"
+id: PYSEC-2024-1
+aliases:
+- CVE-2024-0001
+summary: Remote code execution
+modified: 2024-03-01T00:00:00Z
+affected:
+- package:
+    name: Alpha_Pkg
+    ecosystem: PyPI
+  ranges:
+  - type: ECOSYSTEM
+    events:
+    - introduced: 0
+    - fixed: 1.2.0
+    - introduced: 2.0a1
+    - last_affected: 2.1
+  versions:
+  - 1.0
+  - "1.1"
+"#;
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("vulns/alpha-pkg")).unwrap();
+        fs::write(dir.path().join("vulns/alpha-pkg/PYSEC-2024-1.yaml"), yaml).unwrap();
+        fs::write(dir.path().join("vulns/broken.yaml"), "id: [unclosed").unwrap();
+        fs::write(dir.path().join("README.md"), "# advisories").unwrap();
+
+        let db = AdvisoryDb::load(dir.path()).unwrap();
+        assert_eq!(db.skipped, 1);
+        check(&db);
+        let versions = &db.advisories[0].affected[0].versions;
+        assert_eq!(versions, &["1.0", "1.1"]);
+    }
+
+    #[test]
+    fn test_load_dir_without_advisories() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("README.md"), "# advisories").unwrap();
+        assert!(AdvisoryDb::load(dir.path()).is_err());
+    }
+
+    #[test]
+    fn test_load_zip() {
+        let dir = tempdir().unwrap();
+        let zip_path = dir.path().join("all.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&zip_path).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        zip.start_file("PYSEC-2024-1.json", options).unwrap();
+        zip.write_all(ADVISORY.as_bytes()).unwrap();
+        zip.finish().unwrap();
+
+        check(&AdvisoryDb::load(&zip_path).unwrap());
+    }
+}
@@ -1,4 +1,8 @@
-use std::{fs, path::PathBuf};
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
 
 use clap::{Parser, Subcommand};
 use color_eyre::{
@@ -6,7 +10,25 @@ use color_eyre::{
     owo_colors::OwoColorize,
 };
 
-use crate::{config::Settings, core::VenvManager, shell::Shell, venv::utils::search_venvs};
+use crate::{
+    analysis::{
+        AdvisoryDb, ChangeKind, DedupeOutcome, DedupePlan, Drift, DuplicateGroup, LicenseGroup,
+        LicenseStatus, LinkState, PackageIntegrity, PortabilityReport, StartupHook, VenvDiff,
+        Vulnerability, apply_dedupe, audit_venv, check_drift, diff_venvs, find_duplicates,
+        find_startup_hooks, license_report, plan_dedupe, portability_report, verify_venv,
+    },
+    config::Settings,
+    core::VenvManager,
+    dir_size::{Category, SizeFormat},
+    export::{ExportFormat, export},
+    shell::Shell,
+    venv::{
+        Venv,
+        bytecode::{clean_bytecode, compile_bytecode},
+        conflicts::Conflict,
+        utils::{canonical_name, search_venvs},
+    },
+};
 // use venv_rs_lib::{config::Settings, core::VenvManager, shell::Shell, venv::utils::search_venvs};
 
 #[derive(Parser, Debug)]
@@ -18,6 +40,21 @@ pub struct Cli {
     /// Shell for the activation command
     #[arg(short, long)]
     pub shell: Option<String>,
+
+    /// Print sizes as plain numbers of bytes
+    #[arg(long, global = true)]
+    pub bytes: bool,
+}
+
+impl Cli {
+    /// Size format for command output, `--bytes` overrides the configured units.
+    pub fn size_format(&self, config: &Settings) -> SizeFormat {
+        if self.bytes {
+            SizeFormat::Bytes
+        } else {
+            config.size_units
+        }
+    }
 }
 
 #[derive(Subcommand, Debug)]
@@ -34,10 +71,106 @@ pub enum Kind {
     /// List available shells
     #[command(visible_alias = "ls")]
     ListShells,
+    /// Report distributions installed in more than one cached venv
+    #[command(visible_alias = "dups")]
+    Duplicates {
+        /// Only group copies whose RECORD hashes match
+        #[arg(long)]
+        hashes: bool,
+    },
+    /// List the files in site-packages that no distribution owns
+    Orphans {
+        /// Path to virtual environment
+        path: PathBuf,
+    },
+    /// Check a venv for files that more than one distribution owns and audit the .pth files
+    /// and sitecustomize modules that run at startup. Exits with 1 on conflicts or on startup
+    /// code that no distribution owns
+    Check {
+        /// Path to virtual environment
+        path: PathBuf,
+        /// Compare the packages to a requirements file, pylock.toml, uv.lock or poetry.lock
+        /// instead. Exits with 1 on missing, extra or mismatched packages
+        #[arg(long)]
+        against: Option<PathBuf>,
+        /// Print the comparison as JSON
+        #[arg(long, requires = "against")]
+        json: bool,
+    },
+    /// Check the files of every package against the hashes in its RECORD. Exits with 1 if any
+    /// file was modified, removed or added
+    Verify {
+        /// Path to virtual environment
+        path: PathBuf,
+    },
+    /// Remove the __pycache__ directories and .pyc files of a venv
+    CleanBytecode {
+        /// Path to virtual environment
+        path: PathBuf,
+    },
+    /// Compile the bytecode of a venv with its interpreter
+    Compile {
+        /// Path to virtual environment
+        path: PathBuf,
+    },
+    /// Hardlink identical files of distributions duplicated across cached venvs
+    Dedupe {
+        /// Only report what would be linked
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Report the licenses of the packages of a venv, grouped by license. Exits with 1 if a
+    /// package needs a denied license
+    Licenses {
+        /// Path to virtual environment
+        path: PathBuf,
+        /// License to deny as an SPDX id, added to `license_deny` in the config
+        #[arg(long)]
+        deny: Vec<String>,
+        /// Also exit with 2 if a license couldn't be identified and needs review
+        #[arg(long)]
+        strict: bool,
+    },
+    /// Write a software bill of materials or a `pip inspect` report for a venv
+    Export {
+        /// Path to virtual environment
+        path: PathBuf,
+        #[arg(long, value_enum)]
+        format: ExportFormat,
+        /// File to write to instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Match the packages of a venv against a local OSV advisory database. Exits with 1 if any
+    /// package is vulnerable
+    Audit {
+        /// Path to virtual environment
+        path: PathBuf,
+        /// Directory or zip of OSV JSON advisories, defaults to `advisory_db` in the config
+        #[arg(long)]
+        db: Option<PathBuf>,
+    },
+    /// Compare the packages, sizes and Python versions of two venvs
+    Diff {
+        /// Path to the first virtual environment
+        a: PathBuf,
+        /// Path to the second virtual environment
+        b: PathBuf,
+    },
+    /// Report the packages that tie a venv to its platform through compiled extensions or
+    /// platform specific wheel tags
+    Portability {
+        /// Path to virtual environment
+        path: PathBuf,
+        /// List the native files of each package
+        #[arg(long)]
+        files: bool,
+    },
 }
 
 pub fn handle_commands(vm: &mut VenvManager, config: &Settings) -> Result<bool> {
     let cli = Cli::parse();
+    let size_format = cli.size_format(config);
     match cli.kind {
         Kind::Venv { path } => {
             let _ = vm.get(&path)?;
@@ -68,6 +201,683 @@ pub fn handle_commands(vm: &mut VenvManager, config: &Settings) -> Result<bool>
             );
             return Ok(true);
         }
+        Kind::Duplicates { hashes } => {
+            vm.sync_cache()?;
+            let groups = find_duplicates(&vm.present_venvs(), hashes);
+            print_duplicates(&groups, size_format);
+            return Ok(true);
+        }
+        Kind::Orphans { path } => {
+            // the cached copy may predate files copied in by hand
+            let venv = vm.get_fresh(&path)?;
+            vm.save_cache()?;
+            print_skipped(&venv);
+            print_orphans(&venv, size_format)?;
+            return Ok(true);
+        }
+        Kind::Check {
+            path,
+            against,
+            json,
+        } => {
+            // a stale cache would hide conflicts from a recent install
+            let venv = vm.get_fresh(&path)?;
+            vm.save_cache()?;
+            if let Some(against) = against {
+                let drift = check_drift(&venv, &against)?;
+                if json {
+                    println!("{}", serde_json::to_string_pretty(&drift)?);
+                } else {
+                    print_drift(&drift);
+                }
+                if !drift.is_clean() {
+                    std::process::exit(1);
+                }
+                return Ok(true);
+            }
+            print_conflicts(&venv.conflicts);
+            let hooks = find_startup_hooks(&venv)?;
+            print_startup_hooks(&hooks);
+            let unowned_code = hooks.iter().any(|h| h.executes() && h.owner.is_none());
+            if !venv.conflicts.is_empty() || unowned_code {
+                std::process::exit(1);
+            }
+            return Ok(true);
+        }
+        Kind::Verify { path } => {
+            // the package list has to match what's on disk to verify it
+            let venv = vm.get_fresh(&path)?;
+            vm.save_cache()?;
+            let mut report = verify_venv(&venv)?;
+            report.sort_by(|a, b| a.name.cmp(&b.name));
+            print_integrity(&report);
+            if !report.iter().all(PackageIntegrity::is_intact) {
+                std::process::exit(1);
+            }
+            return Ok(true);
+        }
+        Kind::CleanBytecode { path } => {
+            let venv = vm.get(&path)?;
+            let cleanup = clean_bytecode(&venv.path)?;
+            vm.reload_venv(&venv.path)?;
+            vm.save_cache()?;
+            println!(
+                "{} {} __pycache__ dirs and {} .pyc files, {} freed",
+                "Removed".bold().green(),
+                cleanup.dirs,
+                cleanup.files,
+                size_format.format(cleanup.freed).bold()
+            );
+            return Ok(true);
+        }
+        Kind::Compile { path } => {
+            let venv = vm.get(&path)?;
+            compile_bytecode(&venv)?;
+            vm.reload_venv(&venv.path)?;
+            vm.save_cache()?;
+            let size = vm
+                .cached(&venv.path)
+                .map(|v| v.breakdown.get(Category::Bytecode))
+                .unwrap_or(0);
+            println!(
+                "{} {}, bytecode takes {}",
+                "Compiled".bold().green(),
+                venv.name,
+                size_format.format(size).bold()
+            );
+            return Ok(true);
+        }
+        Kind::Dedupe { dry_run } => {
+            vm.sync_cache()?;
+            let plan = plan_dedupe(&find_duplicates(&vm.present_venvs(), true));
+            print_dedupe_plan(&plan, size_format);
+            if dry_run || plan.links().next().is_none() {
+                return Ok(true);
+            }
+
+            let outcome = apply_dedupe(&plan);
+            print_dedupe_outcome(&outcome, size_format);
+            // links don't change directory mtimes, so a refresh would miss them
+            let mut venvs: Vec<&PathBuf> = plan
+                .packages
+                .iter()
+                .filter(|p| !p.links.is_empty())
+                .flat_map(|p| &p.venvs)
+                .collect();
+            venvs.sort();
+            venvs.dedup();
+            for venv in venvs {
+                vm.reload_venv(venv)?;
+            }
+            vm.save_cache()?;
+            return Ok(true);
+        }
+        Kind::Licenses { path, deny, strict } => {
+            let denied: Vec<String> = config.license_deny.iter().cloned().chain(deny).collect();
+            let code = check_licenses(vm, &path, &denied, strict)?;
+            if code != 0 {
+                std::process::exit(code);
+            }
+            return Ok(true);
+        }
+        Kind::Export {
+            path,
+            format,
+            output,
+        } => {
+            // an SBOM or report missing the last installs is worse than a slow export
+            let venv = vm.get_fresh(&path)?;
+            vm.save_cache()?;
+            let document = serde_json::to_string_pretty(&export(&venv, format))?;
+            match output {
+                Some(output) => fs::write(output, document + "\n")?,
+                None => {
+                    // piping into `head` closes stdout early, which isn't a failure
+                    if let Err(e) = writeln!(io::stdout().lock(), "{document}")
+                        && e.kind() != io::ErrorKind::BrokenPipe
+                    {
+                        return Err(e.into());
+                    }
+                }
+            }
+            return Ok(true);
+        }
+        Kind::Audit { path, db } => {
+            let db_path = db
+                .or_else(|| config.advisory_db.clone().map(PathBuf::from))
+                .ok_or_else(|| eyre!("No advisory database provided and none set in config"))?;
+            let db = AdvisoryDb::load(&db_path)?;
+            // a vulnerable version installed since the cache was saved must be caught
+            let venv = vm.get_fresh(&path)?;
+            vm.save_cache()?;
+            let vulnerable = audit_venv(&venv, &db);
+            print_vulnerabilities(&vulnerable, &db);
+            if !vulnerable.is_empty() {
+                std::process::exit(1);
+            }
+            return Ok(true);
+        }
+        Kind::Diff { a, b } => {
+            let a = vm.get(&a)?;
+            let b = vm.get(&b)?;
+            vm.save_cache()?;
+            print_skipped(&a);
+            print_skipped(&b);
+            print_venv_diff(&diff_venvs(&a, &b, config.size_mode), size_format);
+            return Ok(true);
+        }
+        Kind::Portability { path, files } => {
+            let venv = vm.get_fresh(&path)?;
+            vm.save_cache()?;
+            print_portability(&portability_report(&venv), &venv.version, files);
+            return Ok(true);
+        }
     };
     Ok(false)
 }
+
+/// Prints the licenses of the venv at `path` and returns the exit code, 1 if a package needs
+/// a denied license and 2 if one needs review in `strict` mode.
+fn check_licenses(
+    vm: &mut VenvManager,
+    path: &Path,
+    denied: &[String],
+    strict: bool,
+) -> Result<i32> {
+    // a package installed since the cache was saved must not get past the deny list
+    let venv = vm.get_fresh(path)?;
+    vm.save_cache()?;
+    let groups = license_report(&venv, denied);
+    print_licenses(&groups);
+    Ok(match groups.iter().map(|g| g.status).max() {
+        Some(LicenseStatus::Denied) => 1,
+        Some(LicenseStatus::NeedsReview) if strict => 2,
+        _ => 0,
+    })
+}
+
+fn print_duplicates(groups: &[DuplicateGroup], size_format: SizeFormat) {
+    let size: u64 = groups.iter().map(|g| g.size).sum();
+    let on_disk: u64 = groups.iter().map(|g| g.on_disk).sum();
+    let reclaimable: u64 = groups.iter().map(DuplicateGroup::reclaimable).sum();
+    println!(
+        "{} {} ({} total, {} on disk, {} reclaimable)",
+        "Duplicate distributions:".bold().bright_blue(),
+        groups.len(),
+        size_format.format(size),
+        size_format.format(on_disk),
+        size_format.format(reclaimable).bold()
+    );
+
+    for group in groups {
+        let link_state = match group.link_state {
+            LinkState::Full => "hardlinked".green().to_string(),
+            LinkState::Partial => "partly hardlinked".yellow().to_string(),
+            LinkState::None => "not hardlinked".red().to_string(),
+        };
+        println!(
+            "{} {}  {} copies  {} total  {} on disk  {}",
+            group.name.bold(),
+            group.version,
+            group.copies.len(),
+            size_format.format(group.size),
+            size_format.format(group.on_disk),
+            link_state
+        );
+        for copy in &group.copies {
+            println!(
+                "    {} {}",
+                copy.venv_path.display().dimmed(),
+                size_format.format(copy.size)
+            );
+        }
+    }
+}
+
+fn print_conflicts(conflicts: &[Conflict]) {
+    for conflict in conflicts {
+        let owners: Vec<&str> = conflict.owner_names().collect();
+        println!(
+            "{} {} {}",
+            "conflict".red(),
+            conflict.path.display(),
+            format!("owned by {}", owners.join(", ")).dimmed()
+        );
+    }
+    if conflicts.is_empty() {
+        println!(
+            "{} no files owned by more than one distribution",
+            "Check:".bold().green()
+        );
+    } else {
+        println!(
+            "{} {} files owned by more than one distribution",
+            "Check:".bold().red(),
+            conflicts.len()
+        );
+    }
+}
+
+fn print_drift(drift: &Drift) {
+    for missing in &drift.missing {
+        println!(
+            "{} {} {}",
+            "missing ".red(),
+            missing.name.bold(),
+            missing.expected
+        );
+    }
+    for extra in &drift.extra {
+        println!(
+            "{} {} {}",
+            "extra   ".yellow(),
+            extra.name.bold(),
+            extra.version
+        );
+    }
+    for mismatch in &drift.mismatched {
+        println!(
+            "{} {} {} {}",
+            "mismatch".magenta(),
+            mismatch.name.bold(),
+            mismatch.installed,
+            format!("expected {}", mismatch.expected).dimmed()
+        );
+    }
+    let summary = format!(
+        "{} missing, {} extra, {} mismatched against {}",
+        drift.missing.len(),
+        drift.extra.len(),
+        drift.mismatched.len(),
+        drift.file.display()
+    );
+    if drift.is_clean() {
+        println!("{} {}", "Drift:".bold().green(), summary);
+    } else {
+        println!("{} {}", "Drift:".bold().red(), summary);
+    }
+}
+
+fn print_startup_hooks(hooks: &[StartupHook]) {
+    for hook in hooks {
+        let owner = match &hook.owner {
+            Some(owner) => format!("owned by {owner}").dimmed().to_string(),
+            None => "not owned by any distribution".yellow().to_string(),
+        };
+        println!(
+            "{} {} {}",
+            hook.kind.name().bright_blue(),
+            hook.path.display(),
+            owner
+        );
+        for line in hook.lines.iter().filter(|l| l.executes) {
+            println!("    {:>4}: {}", line.number, line.text.red());
+        }
+    }
+    let executing = hooks.iter().filter(|h| h.executes()).count();
+    println!(
+        "{} {} startup hooks, {} run code",
+        "Audit:".bold().bright_blue(),
+        hooks.len(),
+        executing
+    );
+}
+
+fn print_licenses(groups: &[LicenseGroup]) {
+    for group in groups {
+        let license = match group.status {
+            LicenseStatus::Allowed => group.license.bold().to_string(),
+            LicenseStatus::NeedsReview => format!("{} (needs review)", group.license)
+                .bold()
+                .yellow()
+                .to_string(),
+            LicenseStatus::Denied => format!("{} (denied)", group.license)
+                .bold()
+                .red()
+                .to_string(),
+        };
+        println!(
+            "{} {}",
+            license,
+            format!("{} packages", group.packages.len()).dimmed()
+        );
+        for (name, version) in &group.packages {
+            println!("    {name} {version}");
+        }
+    }
+    let packages: usize = groups.iter().map(|g| g.packages.len()).sum();
+    let with_status = |status: LicenseStatus| -> usize {
+        groups
+            .iter()
+            .filter(|g| g.status == status)
+            .map(|g| g.packages.len())
+            .sum()
+    };
+    let denied = with_status(LicenseStatus::Denied);
+    let review = with_status(LicenseStatus::NeedsReview);
+    let summary = format!(
+        "{} packages under {} licenses, {} denied, {} to review",
+        packages,
+        groups.len(),
+        denied,
+        review
+    );
+    if denied > 0 {
+        println!("{} {}", "Licenses:".bold().red(), summary);
+    } else if review > 0 {
+        println!("{} {}", "Licenses:".bold().yellow(), summary);
+    } else {
+        println!("{} {}", "Licenses:".bold().green(), summary);
+    }
+}
+
+fn print_portability(report: &PortabilityReport, python: &str, files: bool) {
+    for pkg in &report.platform_specific {
+        let tags: Vec<&str> = pkg
+            .abis
+            .iter()
+            .chain(&pkg.platforms)
+            .map(String::as_str)
+            .collect();
+        println!(
+            "{} {}  {}  {} native files",
+            pkg.name.bold().yellow(),
+            pkg.version,
+            tags.join(", ").dimmed(),
+            pkg.native_files.len()
+        );
+        if files {
+            for file in &pkg.native_files {
+                println!("    {file}");
+            }
+        }
+    }
+    for (name, version) in &report.unknown {
+        println!(
+            "{} {}  {}",
+            name.bold(),
+            version,
+            "no WHEEL file or native files".dimmed()
+        );
+    }
+
+    let summary = format!(
+        "{} platform-specific, {} pure Python, {} unknown",
+        report.platform_specific.len(),
+        report.pure,
+        report.unknown.len()
+    );
+    if report.is_portable() {
+        println!("{} {}", "Portable:".bold().green(), summary);
+        println!("Can be copied to any machine with Python {python}");
+    } else {
+        println!("{} {}", "Not portable:".bold().yellow(), summary);
+        if !report.abis().is_empty() {
+            println!("Needs the ABI of {}", report.abis().join(", "));
+        }
+        if !report.platforms().is_empty() {
+            println!("Built for {}", report.platforms().join(", "));
+        }
+    }
+}
+
+fn print_venv_diff(diff: &VenvDiff, size_format: SizeFormat) {
+    println!("{} {}", "---".red(), diff.a.to_string_lossy().bold());
+    println!("{} {}", "+++".green(), diff.b.to_string_lossy().bold());
+    let (python_a, python_b) = &diff.python;
+    if python_a == python_b {
+        println!("Python: {python_a}");
+    } else {
+        println!("Python: {} → {}", python_a.red(), python_b.green());
+    }
+    println!(
+        "Size: {} → {} ({})",
+        size_format.format(diff.size.0),
+        size_format.format(diff.size.1),
+        size_format.format_delta(diff.size_delta())
+    );
+
+    for change in &diff.changes {
+        let old = change.old.as_deref().unwrap_or_default();
+        let new = change.new.as_deref().unwrap_or_default();
+        let delta = size_format.format_delta(change.size_delta());
+        match change.kind {
+            ChangeKind::Added => {
+                println!("{} {} {}  {}", "+".green(), change.name.green(), new, delta)
+            }
+            ChangeKind::Removed => {
+                println!("{} {} {}  {}", "-".red(), change.name.red(), old, delta)
+            }
+            ChangeKind::Upgraded => println!(
+                "{} {} {} → {}  {}",
+                "↑".cyan(),
+                change.name.bold(),
+                old,
+                new,
+                delta
+            ),
+            ChangeKind::Downgraded => println!(
+                "{} {} {} → {}  {}",
+                "↓".yellow(),
+                change.name.bold(),
+                old,
+                new,
+                delta
+            ),
+            ChangeKind::Changed => println!(
+                "{} {} {} → {}  {}",
+                "~".yellow(),
+                change.name.bold(),
+                old,
+                new,
+                delta
+            ),
+        }
+    }
+
+    let count = |kind| diff.of_kind(kind).count();
+    println!(
+        "{} {} added, {} removed, {} upgraded, {} downgraded, {} changed, {} unchanged",
+        "Diff:".bold(),
+        count(ChangeKind::Added),
+        count(ChangeKind::Removed),
+        count(ChangeKind::Upgraded),
+        count(ChangeKind::Downgraded),
+        count(ChangeKind::Changed),
+        diff.unchanged
+    );
+}
+
+fn print_vulnerabilities(vulnerable: &[Vulnerability], db: &AdvisoryDb) {
+    for vuln in vulnerable {
+        println!("{} {}", vuln.name.bold().red(), vuln.version);
+        let name = canonical_name(&vuln.name);
+        for advisory in &vuln.advisories {
+            let aliases = if advisory.aliases.is_empty() {
+                String::new()
+            } else {
+                format!(" ({})", advisory.aliases.join(", "))
+            };
+            let fixed = advisory.fixed_versions(&name);
+            let fixed = if fixed.is_empty() {
+                "no fix".yellow().to_string()
+            } else {
+                format!("fixed in {}", fixed.join(", ")).green().to_string()
+            };
+            println!("    {}{}  {}", advisory.id.bold(), aliases.dimmed(), fixed);
+            if !advisory.summary.is_empty() {
+                println!("        {}", advisory.summary.dimmed());
+            }
+        }
+    }
+    let summary = format!(
+        "{} vulnerable packages, {} advisories checked",
+        vulnerable.len(),
+        db.len()
+    );
+    if vulnerable.is_empty() {
+        println!("{} {}", "Audit:".bold().green(), summary);
+    } else {
+        println!("{} {}", "Audit:".bold().red(), summary);
+    }
+    if db.skipped > 0 {
+        println!(
+            "{} {} files couldn't be read as advisories",
+            "warning:".yellow(),
+            db.skipped
+        );
+    }
+}
+
+fn print_integrity(report: &[PackageIntegrity]) {
+    for pkg in report.iter().filter(|p| !p.is_intact()) {
+        println!("{} {}", pkg.name.bold(), pkg.version);
+        for path in &pkg.modified {
+            println!("    {} {}", "modified".red(), path.display());
+        }
+        for path in &pkg.missing {
+            println!("    {} {}", "missing ".red(), path.display());
+        }
+        for path in &pkg.extra {
+            println!("    {} {}", "extra   ".yellow(), path.display());
+        }
+        for error in &pkg.errors {
+            println!("    {} {}", "error   ".magenta(), error);
+        }
+    }
+    let changed = report.iter().filter(|p| !p.is_intact()).count();
+    let summary = format!(
+        "{} of {} packages differ from RECORD",
+        changed,
+        report.len()
+    );
+    if changed == 0 {
+        println!("{} {}", "Verified:".bold().green(), summary);
+    } else {
+        println!("{} {}", "Verified:".bold().red(), summary);
+    }
+}
+
+fn print_orphans(venv: &Venv, size_format: SizeFormat) -> Result<()> {
+    let site_packages = venv.site_packages()?;
+    for orphan in &venv.orphans {
+        let kind = if orphan.is_dir { "dir " } else { "file" };
+        println!(
+            "{} {:>10}  {}",
+            kind.dimmed(),
+            size_format.format(orphan.size),
+            site_packages.join(&orphan.path).display()
+        );
+    }
+    println!(
+        "{} {} ({})",
+        "Orphaned files:".bold().bright_blue(),
+        venv.orphans.len(),
+        size_format.format(venv.orphaned_size()).bold()
+    );
+    Ok(())
+}
+
+/// Warns about the entries of `venv` that couldn't be read, on stderr so it doesn't mix with the
+/// output of the command.
+fn print_skipped(venv: &Venv) {
+    if venv.skipped.is_empty() {
+        return;
+    }
+    eprintln!(
+        "{} {} entries of {} couldn't be read and are left out of its size",
+        "Warning:".bold().yellow(),
+        venv.skipped.len(),
+        venv.name
+    );
+    for skipped in &venv.skipped {
+        eprintln!(
+            "    {} {}",
+            "skipped".yellow(),
+            format!("{}: {}", skipped.path.display(), skipped.reason).dimmed()
+        );
+    }
+}
+
+fn print_dedupe_plan(plan: &DedupePlan, size_format: SizeFormat) {
+    for package in &plan.packages {
+        println!(
+            "{} {}  {} files to link  {} reclaimable",
+            package.name.bold(),
+            package.version,
+            package.links.len(),
+            size_format.format(package.freed())
+        );
+        for skipped in &package.skipped {
+            println!(
+                "    {} {}",
+                "skipped".yellow(),
+                format!("{}: {}", skipped.path.display(), skipped.reason).dimmed()
+            );
+        }
+    }
+    println!(
+        "{} {} files, {} reclaimable",
+        "Dedupe:".bold().bright_blue(),
+        plan.links().count(),
+        size_format.format(plan.freed()).bold()
+    );
+}
+
+fn print_dedupe_outcome(outcome: &DedupeOutcome, size_format: SizeFormat) {
+    for failed in &outcome.failed {
+        println!(
+            "{} {}: {}",
+            "failed".red(),
+            failed.path.display(),
+            failed.reason
+        );
+    }
+    println!(
+        "{} {} files, {} reclaimed",
+        "Linked:".bold().green(),
+        outcome.linked,
+        size_format.format(outcome.freed).bold()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::OpenOptions;
+
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::venv::test_utils::{mock_package, mock_venv};
+
+    #[test]
+    fn licenses_see_packages_installed_after_caching() {
+        let cache_dir = tempdir().unwrap();
+        let venv_dir = tempdir().unwrap();
+        let site_packages = mock_venv(venv_dir.path());
+        mock_package(&site_packages, "alpha", "1.0", &[("alpha.py", "")]);
+        let denied = ["GPL-3.0-only".to_string()];
+
+        let mut vm = VenvManager::with_cache_path(cache_dir.path().to_path_buf());
+        assert_eq!(
+            check_licenses(&mut vm, venv_dir.path(), &denied, false).unwrap(),
+            0
+        );
+
+        let beta = mock_package(&site_packages, "beta", "1.0", &[("beta.py", "")]);
+        let mut metadata = OpenOptions::new()
+            .append(true)
+            .open(beta.join("METADATA"))
+            .unwrap();
+        writeln!(metadata, "License-Expression: GPL-3.0-only").unwrap();
+
+        // a later run starts from the saved cache
+        let mut vm = VenvManager::with_cache_path(cache_dir.path().to_path_buf());
+        vm.load_cache().unwrap();
+        assert_eq!(vm.get(venv_dir.path()).unwrap().packages.len(), 1);
+        assert_eq!(
+            check_licenses(&mut vm, venv_dir.path(), &denied, false).unwrap(),
+            1
+        );
+    }
+}
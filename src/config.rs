@@ -4,18 +4,31 @@ use config::Config;
 use dirs::config_dir;
 use serde::Deserialize;
 
-use crate::shell::Shell;
+use crate::{
+    dir_size::{SizeFormat, SizeMode},
+    shell::Shell,
+};
 
 #[derive(Deserialize)]
 pub struct Settings {
     pub shell: Shell,
     pub venvs_dir: Option<String>,
+    /// Whether sizes are file lengths or allocated blocks
+    pub size_mode: SizeMode,
+    /// Units sizes are shown in
+    pub size_units: SizeFormat,
+    /// Directory or zip of OSV advisories for `audit` and the TUI
+    pub advisory_db: Option<String>,
+    /// Licenses the `licenses` command fails on, as SPDX ids
+    pub license_deny: Vec<String>,
     pub extra: ExtraFeatures,
 }
 
 #[derive(Deserialize)]
 pub struct ExtraFeatures {
     pub use_xclip: bool,
+    /// Watch the venvs for changes while the TUI is open
+    pub watch: bool,
 }
 
 impl Settings {
@@ -27,6 +40,9 @@ impl Settings {
 
             self.venvs_dir = Some(canon.to_string_lossy().into_owned());
         }
+        if let Some(advisory_db) = &self.advisory_db {
+            self.advisory_db = Some(shellexpand::tilde(advisory_db).into_owned());
+        }
         self
     }
 }
@@ -39,7 +55,13 @@ pub fn get_config() -> Result<Settings, config::ConfigError> {
     fs::create_dir_all(config_dir.as_path())
         .expect("Could not create config directories for some reason");
 
-    let settings = Config::builder().set_default("venvs_dir", Option::<String>::None)?;
+    let settings = Config::builder()
+        .set_default("venvs_dir", Option::<String>::None)?
+        .set_default("advisory_db", Option::<String>::None)?
+        .set_default("license_deny", Vec::<String>::new())?
+        .set_default("size_mode", "apparent")?
+        .set_default("size_units", "iec")?
+        .set_default("extra.watch", true)?;
 
     let settings = if cfg!(not(windows)) {
         settings
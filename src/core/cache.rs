@@ -1,24 +1,40 @@
 use std::{
-    collections::BTreeMap,
+    collections::{BTreeMap, BTreeSet},
     fs,
     path::{Path, PathBuf},
-    sync::{Arc, RwLock, mpsc::Sender},
+    sync::{
+        Arc, RwLock,
+        atomic::{AtomicBool, Ordering},
+        mpsc::Sender,
+    },
     thread::{self, JoinHandle},
-    time::SystemTime,
+    time::Instant,
 };
 
-use color_eyre::Result;
+use color_eyre::{Result, eyre::WrapErr};
+use rayon::prelude::*;
 
 use dirs::cache_dir;
 
 use crate::{
+    dir_size::{Category, DirMemo, DiskUsage, combine_usage},
     tui::SyncMsg,
-    venv::{Venv, parser::VenvParser},
+    venv::{
+        Venv,
+        bytecode::{BytecodeAction, clean_bytecode, compile_bytecode},
+        parser::VenvParser,
+        utils::search_venvs_within,
+    },
 };
 
+/// How many levels below the old location are searched for a moved venv
+const LOCATE_DEPTH: usize = 4;
+
 #[derive(Debug)]
 pub struct VenvManager {
     cache: BTreeMap<PathBuf, Arc<Venv>>,
+    /// Cached venvs that no longer exist on disk
+    missing: BTreeSet<PathBuf>,
     cache_path: PathBuf,
 }
 
@@ -33,83 +49,308 @@ impl VenvManager {
         let cache_path = cache_dir()
             .expect("Could not get cache dir")
             .join("venv_rs");
+        Self::with_cache_path(cache_path)
+    }
 
+    /// A manager keeping its cache files in `cache_path`.
+    pub fn with_cache_path(cache_path: PathBuf) -> Self {
         fs::create_dir_all(&cache_path).expect("Failed to create them dirs");
 
         Self {
             cache: BTreeMap::new(),
+            missing: BTreeSet::new(),
             cache_path,
         }
     }
 
-    pub fn threaded_sync(vm_arc: Arc<RwLock<Self>>, sender: Sender<SyncMsg>) -> JoinHandle<()> {
+    /// Checks the cached venvs in parallel and re-parses the stale ones. Setting `cancel`
+    /// stops the sync before the next venv is checked; the cache isn't saved in that case.
+    pub fn threaded_sync(
+        vm_arc: Arc<RwLock<Self>>,
+        sender: Sender<SyncMsg>,
+        cancel: Arc<AtomicBool>,
+    ) -> JoinHandle<()> {
+        Self::spawn_sync(vm_arc, sender, None, cancel)
+    }
+
+    /// Like [`VenvManager::threaded_sync`], but only for the venvs at `paths`, which are
+    /// re-parsed incrementally whether they look stale or not.
+    pub fn threaded_refresh(
+        vm_arc: Arc<RwLock<Self>>,
+        sender: Sender<SyncMsg>,
+        paths: Vec<PathBuf>,
+        cancel: Arc<AtomicBool>,
+    ) -> JoinHandle<()> {
+        Self::spawn_sync(vm_arc, sender, Some(paths), cancel)
+    }
+
+    fn spawn_sync(
+        vm_arc: Arc<RwLock<Self>>,
+        sender: Sender<SyncMsg>,
+        paths: Option<Vec<PathBuf>>,
+        cancel: Arc<AtomicBool>,
+    ) -> JoinHandle<()> {
         thread::spawn(move || {
             let _ = sender.send(SyncMsg::Started);
+            let force = paths.is_some();
 
             // get the entires with a read lock
             let snapshot: Vec<(PathBuf, Arc<Venv>)> = {
                 let vm_r = vm_arc.read().expect("rwlock poisoned");
                 vm_r.cache
                     .iter()
+                    .filter(|(p, _)| paths.as_ref().is_none_or(|paths| paths.contains(p)))
                     .map(|(p, v)| (p.clone(), Arc::clone(v)))
                     .collect()
             };
 
             // check for stale venvs off lock
-            for (path, cached_venv) in snapshot {
-                let _ = sender.send(SyncMsg::Progress {
-                    venv: cached_venv.name.clone(),
+            snapshot.into_par_iter().for_each_with(
+                sender.clone(),
+                |sender, (path, cached_venv)| {
+                    if cancel.load(Ordering::Relaxed) {
+                        return;
+                    }
+                    Self::sync_venv(&vm_arc, sender, path, cached_venv, force);
+                },
+            );
+
+            if cancel.load(Ordering::Relaxed) {
+                let _ = sender.send(SyncMsg::Finished);
+                return;
+            }
+            // optionally save cache at the end under read lock (assuming save reads cache)
+            {
+                let vm_r = vm_arc.read().expect("rwlock poisoned");
+                if let Err(e) = vm_r.save_cache() {
+                    let _ = sender.send(SyncMsg::Error(format!("Failed to save cache: {e}")));
+                }
+            }
+            let _ = sender.send(SyncMsg::Finished);
+        })
+    }
+
+    /// Checks a single venv and re-parses it if it's stale, or regardless with `force`.
+    fn sync_venv(
+        vm_arc: &RwLock<Self>,
+        sender: &Sender<SyncMsg>,
+        path: PathBuf,
+        cached_venv: Arc<Venv>,
+        force: bool,
+    ) {
+        let start = Instant::now();
+        let _ = sender.send(SyncMsg::Progress { path: path.clone() });
+
+        let cached_most_recent = cached_venv.last_modified();
+
+        // compute most recent on-disk dist-info mtime (expensive, but off-lock)
+        let most_recent_on_disk =
+            match VenvParser::new(path.clone()).recent_dist_info_modification() {
+                Ok(t) => t,
+                Err(e) => {
+                    if path.join("pyvenv.cfg").exists() {
+                        let _ = sender.send(SyncMsg::Error(format!(
+                            "Failed to check {}: {}",
+                            path.display(),
+                            e
+                        )));
+                        let _ = sender.send(SyncMsg::VenvUpdated {
+                            path: path.clone(),
+                            elapsed: start.elapsed(),
+                        });
+                    } else {
+                        // the venv was deleted or moved, keep it around until the user
+                        // decides what to do with it
+                        vm_arc.write().expect("rwlock poisoned").mark_missing(&path);
+                        let _ = sender.send(SyncMsg::VenvMissing(path));
+                    }
+                    return;
+                }
+            };
+        // it might have been missing earlier and come back since
+        vm_arc
+            .write()
+            .expect("rwlock poisoned")
+            .missing
+            .remove(&path);
+
+        // decide if stale
+        if force || cached_most_recent < most_recent_on_disk {
+            // expensive parse (off-lock), only changed packages are re-parsed
+            match cached_venv.refresh() {
+                Ok(new_venv) => {
+                    // short write lock to update the cache atomically
+                    let mut vm_w = vm_arc.write().expect("rwlock poisoned");
+                    vm_w.cache.insert(path.clone(), Arc::new(new_venv));
+                }
+                Err(e) => {
+                    let _ = sender.send(SyncMsg::Error(format!(
+                        "Failed to parse {}: {}",
+                        path.display(),
+                        e
+                    )));
+                }
+            }
+        }
+        let _ = sender.send(SyncMsg::VenvUpdated {
+            path,
+            elapsed: start.elapsed(),
+        });
+    }
+
+    /// Parses the venvs at `paths` from scratch like [`VenvManager::reload_venv`], regardless of
+    /// whether they're stale. The parse happens off-lock, the write lock is only taken to insert
+    /// the result.
+    pub fn threaded_reload(
+        vm_arc: Arc<RwLock<Self>>,
+        sender: Sender<SyncMsg>,
+        paths: Vec<PathBuf>,
+        cancel: Arc<AtomicBool>,
+    ) -> JoinHandle<()> {
+        thread::spawn(move || {
+            let _ = sender.send(SyncMsg::Started);
+
+            for path in paths {
+                if cancel.load(Ordering::Relaxed) {
+                    let _ = sender.send(SyncMsg::Finished);
+                    return;
+                }
+                let start = Instant::now();
+                let _ = sender.send(SyncMsg::Progress { path: path.clone() });
+
+                match Venv::from_path(&path) {
+                    Ok(venv) => {
+                        let mut vm_w = vm_arc.write().expect("rwlock poisoned");
+                        vm_w.cache.insert(path.clone(), Arc::new(venv));
+                    }
+                    Err(e) => {
+                        let _ = sender.send(SyncMsg::Error(format!(
+                            "Failed to reload {}: {}",
+                            path.display(),
+                            e
+                        )));
+                    }
+                }
+                let _ = sender.send(SyncMsg::VenvUpdated {
+                    path,
+                    elapsed: start.elapsed(),
                 });
+            }
+            {
+                let vm_r = vm_arc.read().expect("rwlock poisoned");
+                if let Err(e) = vm_r.save_cache() {
+                    let _ = sender.send(SyncMsg::Error(format!("Failed to save cache: {e}")));
+                }
+            }
+            let _ = sender.send(SyncMsg::Finished);
+        })
+    }
 
-                let cached_most_recent = cached_venv
-                    .packages
-                    .iter()
-                    .map(|pkg| pkg.last_modified)
-                    .max()
-                    .unwrap_or(SystemTime::UNIX_EPOCH);
-
-                // compute most recent on-disk dist-info mtime (expensive, but off-lock)
-                let most_recent_on_disk =
-                    match VenvParser::new(path.clone()).recent_dist_info_modification() {
-                        Ok(t) => t,
-                        Err(_) => {
-                            // This usually happens when a venv is deleted
-                            // TODO: not sure how to handle this one rn
-                            {
-                                let mut vm_w = vm_arc.write().expect("rwlock poisoned");
-                                vm_w.cache
-                                    .remove(&path)
-                                    .expect("couldn't remove venv from cache");
-                            }
-                            continue;
-                        }
-                    };
-                // decide if stale
-                if cached_most_recent < most_recent_on_disk {
-                    // expensive parse (off-lock)
-                    match Venv::from_path(&path) {
-                        Ok(new_venv) => {
-                            // short write lock to update the cache atomically
-                            {
-                                let mut vm_w = vm_arc.write().expect("rwlock poisoned");
-                                vm_w.cache.insert(path.clone(), Arc::new(new_venv));
-                            }
-                            let _ = sender.send(SyncMsg::VenvUpdated(cached_venv.name.clone()));
+    /// Searches for a missing venv with [`VenvManager::locate`] and moves it over to the new
+    /// path if it's found.
+    pub fn threaded_locate(
+        vm_arc: Arc<RwLock<Self>>,
+        sender: Sender<SyncMsg>,
+        path: PathBuf,
+    ) -> JoinHandle<()> {
+        thread::spawn(move || {
+            let _ = sender.send(SyncMsg::Started);
+            let _ = sender.send(SyncMsg::Progress { path: path.clone() });
+
+            let found = vm_arc.read().expect("rwlock poisoned").locate(&path);
+            match found {
+                Some(new_path) => {
+                    let res = vm_arc
+                        .write()
+                        .expect("rwlock poisoned")
+                        .relocate(&path, &new_path);
+                    match res {
+                        Ok(_) => {
+                            let _ = sender.send(SyncMsg::VenvMoved {
+                                from: path,
+                                to: new_path,
+                            });
                         }
                         Err(e) => {
                             let _ = sender.send(SyncMsg::Error(format!(
                                 "Failed to parse {}: {}",
-                                path.display(),
+                                new_path.display(),
                                 e
                             )));
                         }
                     }
-                } else {
-                    // no change — optionally still notify so UI can clear spinner
-                    let _ = sender.send(SyncMsg::VenvUpdated(cached_venv.name.clone()));
+                }
+                None => {
+                    let _ = sender.send(SyncMsg::Error(format!(
+                        "Could not find where {} was moved to",
+                        path.display()
+                    )));
                 }
             }
-            // optionally save cache at the end under read lock (assuming save reads cache)
+            {
+                let vm_r = vm_arc.read().expect("rwlock poisoned");
+                if let Err(e) = vm_r.save_cache() {
+                    let _ = sender.send(SyncMsg::Error(format!("Failed to save cache: {e}")));
+                }
+            }
+            let _ = sender.send(SyncMsg::Finished);
+        })
+    }
+
+    /// Cleans or compiles the bytecode of `venv` with [`clean_bytecode`] or
+    /// [`compile_bytecode`], then re-parses it.
+    pub fn threaded_bytecode(
+        vm_arc: Arc<RwLock<Self>>,
+        sender: Sender<SyncMsg>,
+        venv: Arc<Venv>,
+        action: BytecodeAction,
+    ) -> JoinHandle<()> {
+        thread::spawn(move || {
+            let _ = sender.send(SyncMsg::Started);
+            let start = Instant::now();
+            let _ = sender.send(SyncMsg::Progress {
+                path: venv.path.clone(),
+            });
+
+            let res = match action {
+                BytecodeAction::Clean => clean_bytecode(&venv.path).map(Some),
+                BytecodeAction::Compile => compile_bytecode(&venv).map(|_| None),
+            };
+            // parsed off-lock like in `threaded_reload`
+            let reloaded = Venv::from_path(&venv.path).map(|parsed| {
+                let parsed = Arc::new(parsed);
+                vm_arc
+                    .write()
+                    .expect("rwlock poisoned")
+                    .cache
+                    .insert(venv.path.clone(), Arc::clone(&parsed));
+                parsed
+            });
+            match (res, reloaded) {
+                (Err(e), _) | (_, Err(e)) => {
+                    let _ = sender.send(SyncMsg::Error(format!(
+                        "Failed to update the bytecode of {}: {}",
+                        venv.path.display(),
+                        e
+                    )));
+                }
+                (Ok(Some(cleanup)), Ok(_)) => {
+                    let _ = sender.send(SyncMsg::BytecodeCleaned {
+                        venv: venv.name.clone(),
+                        cleanup,
+                    });
+                }
+                (Ok(None), Ok(reloaded)) => {
+                    let _ = sender.send(SyncMsg::BytecodeCompiled {
+                        venv: venv.name.clone(),
+                        size: reloaded.breakdown.get(Category::Bytecode),
+                    });
+                }
+            }
+            let _ = sender.send(SyncMsg::VenvUpdated {
+                path: venv.path.clone(),
+                elapsed: start.elapsed(),
+            });
             {
                 let vm_r = vm_arc.read().expect("rwlock poisoned");
                 if let Err(e) = vm_r.save_cache() {
@@ -121,91 +362,205 @@ impl VenvManager {
     }
 
     pub fn venvs_from_cache(&self) -> Result<Vec<Venv>> {
-        fs::read_dir(&self.cache_path)?
+        // entries that can't be decoded (e.g. written by an older version) are skipped and
+        // parsed again when they're requested
+        let venvs = fs::read_dir(&self.cache_path)?
             .filter_map(Result::ok)
             .map(|entry| entry.path())
-            .map(|path| Venv::load_cache(&path))
-            .collect()
+            .filter(|path| path.extension().is_some_and(|ext| ext == "bin"))
+            .filter_map(|path| Venv::load_cache(&path).ok())
+            .collect();
+        Ok(venvs)
     }
 
+    /// Loads the cached venvs along with their directory records.
     pub fn load_cache(&mut self) -> Result<()> {
         if let Ok(venvs) = self.venvs_from_cache() {
             self.cache = venvs
                 .into_iter()
-                .map(|v| (v.path.clone(), Arc::new(v)))
+                .map(|mut v| {
+                    load_dir_memo(&mut v, &self.cache_path);
+                    (v.path.clone(), Arc::new(v))
+                })
                 .collect();
         }
         Ok(())
     }
 
+    /// Writes every venv to its cache file and its directory records to a side file. Venvs
+    /// without records, like ones whose side file couldn't be read, leave theirs as it is.
     pub fn save_cache(&self) -> Result<()> {
         for v in self.cache.values() {
             if let Some(cache_path) = to_cache_path(&v.path, &self.cache_path) {
                 v.save_cache_to(&cache_path)?;
             }
+            if !v.dir_memo.0.is_empty() {
+                v.dir_memo
+                    .0
+                    .save_to(&v.path, &to_memo_path(&v.path, &self.cache_path))?;
+            }
         }
 
         Ok(())
     }
 
     pub fn get(&mut self, p: &Path) -> Result<Arc<Venv>> {
+        // venvs are keyed by their canonical path, same as `Venv::path`
+        let p = &dunce::canonicalize(p)?;
         // println!("Getting {}", p.to_string_lossy().yellow().italic());
         let kee = self.cache.contains_key(p);
         // let stale = self.is_venv_stale(p);
         // println!("Contains: {kee}, Stale: {stale}");
         if !kee {
             let venv = Venv::from_path(p)?;
+            // a new venv that's identical to a vanished one is the same venv after a move
+            if let Some(old_path) = self.find_moved_from(&venv) {
+                self.forget(&old_path)?;
+            }
             self.cache.insert(p.to_path_buf(), venv.into());
         }
         Ok(self.cache.get(p).unwrap().clone())
     }
 
+    /// The venv at `p` as it is on disk now. A cached venv is parsed again from scratch, for
+    /// reports that would be wrong about packages installed since the cache was saved.
+    pub fn get_fresh(&mut self, p: &Path) -> Result<Arc<Venv>> {
+        let p = &dunce::canonicalize(p)?;
+        if self.cache.contains_key(p) {
+            self.reload_venv(p)?;
+        }
+        self.get(p)
+    }
+
+    /// The cached venv at `p`, without parsing it if it isn't cached.
+    pub fn cached(&self, p: &Path) -> Option<Arc<Venv>> {
+        self.cache.get(p).cloned()
+    }
+
+    /// Parses a venv from scratch, nothing of the cached one is reused. Files rewritten in place
+    /// and new hardlinks don't change any mtime, so this is the only way to pick them up.
     pub fn reload_venv(&mut self, p: &Path) -> Result<()> {
         let venv = Venv::from_path(p)?;
         self.cache.insert(p.to_path_buf(), venv.into());
         Ok(())
     }
 
-    pub fn sync_cache(&mut self) {
-        // Collect keys to update to avoid mutable/immutable borrow conflict
-        let keys_to_update: Vec<PathBuf> = self
+    /// Marks a cached venv as missing on disk. Missing venvs stay in the cache until they're
+    /// forgotten or located again.
+    pub fn mark_missing(&mut self, p: &Path) {
+        self.missing.insert(p.to_path_buf());
+    }
+
+    pub fn is_missing(&self, p: &Path) -> bool {
+        self.missing.contains(p)
+    }
+
+    /// Removes a venv from the cache, along with its cache and directory record files.
+    pub fn forget(&mut self, p: &Path) -> Result<()> {
+        self.missing.remove(p);
+        if self.cache.remove(p).is_some() {
+            if let Some(cache_file) = to_cache_path(p, &self.cache_path)
+                && cache_file.exists()
+            {
+                fs::remove_file(cache_file)?;
+            }
+            let memo_file = to_memo_path(p, &self.cache_path);
+            if memo_file.exists() {
+                fs::remove_file(memo_file)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Looks for a missing venv around its old location. The nearest existing ancestor of the
+    /// old path is searched a few levels deep for a venv with the same `pyvenv.cfg` and
+    /// packages.
+    pub fn locate(&self, p: &Path) -> Option<PathBuf> {
+        let venv = self.cache.get(p)?;
+        let search_root = p.ancestors().skip(1).find(|a| a.is_dir())?;
+
+        search_venvs_within(search_root.to_path_buf(), LOCATE_DEPTH)
+            .into_iter()
+            .filter(|candidate| !self.cache.contains_key(candidate))
+            .find(|candidate| venv.is_same_venv_at(candidate))
+    }
+
+    /// Replaces the venv at `from` with the one parsed at `to`.
+    pub fn relocate(&mut self, from: &Path, to: &Path) -> Result<Arc<Venv>> {
+        let venv = Arc::new(Venv::from_path(to)?);
+        self.forget(from)?;
+        self.cache.insert(venv.path.clone(), Arc::clone(&venv));
+        Ok(venv)
+    }
+
+    fn find_moved_from(&self, venv: &Venv) -> Option<PathBuf> {
+        self.cache
+            .values()
+            .filter(|cached| !cached.path.join("pyvenv.cfg").exists())
+            .find(|cached| cached.is_same_venv(venv))
+            .map(|cached| cached.path.clone())
+    }
+
+    /// Re-parses the stale venvs, the blocking version of [`VenvManager::threaded_sync`]. Venvs
+    /// that are no longer on disk are marked as missing and kept as they are.
+    pub fn sync_cache(&mut self) -> Result<()> {
+        let paths: Vec<PathBuf> = self
             .cache
             .keys()
-            .filter(|k| self.is_venv_stale(k))
+            .filter(|p| !self.missing.contains(*p))
             .cloned()
             .collect();
 
-        for k in keys_to_update {
-            let venv = Venv::from_path(&k).unwrap();
-            self.cache.insert(k, venv.into());
+        for path in paths {
+            if !path.join("pyvenv.cfg").exists() {
+                self.mark_missing(&path);
+                continue;
+            }
+            if self.is_venv_stale(&path)? {
+                let cached = Arc::clone(&self.cache[&path]);
+                let venv = cached
+                    .refresh()
+                    .with_context(|| format!("Failed to parse {}", path.display()))?;
+                self.cache.insert(path, venv.into());
+            }
         }
+        Ok(())
     }
 
-    pub fn is_venv_stale(&self, p: &Path) -> bool {
-        if !self.cache.contains_key(p) {
-            return true;
-        }
-        let v = self.cache.get(p).unwrap();
-
-        let last_modified = v
-            .packages
-            .iter()
-            .map(|p| p.last_modified)
-            .max()
-            .unwrap_or(SystemTime::UNIX_EPOCH);
+    /// Whether packages were installed, removed or upgraded since the venv at `p` was cached.
+    /// Venvs that aren't cached are always stale.
+    pub fn is_venv_stale(&self, p: &Path) -> Result<bool> {
+        let Some(v) = self.cache.get(p) else {
+            return Ok(true);
+        };
 
         // get the package files to check the versions, if they changed
         let parser = VenvParser::new(p.to_path_buf());
         let most_recent_update = parser
             .recent_dist_info_modification()
-            .expect("Failed to get recent update");
+            .with_context(|| format!("Failed to check {}", p.display()))?;
 
-        last_modified < most_recent_update
+        Ok(v.last_modified() < most_recent_update)
     }
 
     pub fn get_venvs(&self) -> Vec<Arc<Venv>> {
         self.cache.values().cloned().collect()
     }
+
+    /// Cached venvs that aren't known to be missing on disk.
+    pub fn present_venvs(&self) -> Vec<Arc<Venv>> {
+        self.cache
+            .iter()
+            .filter(|(path, _)| !self.missing.contains(*path))
+            .map(|(_, venv)| Arc::clone(venv))
+            .collect()
+    }
+
+    /// Usage of all cached venvs together, files hardlinked between venvs are counted once.
+    pub fn disk_usage(&self) -> DiskUsage {
+        let parts = self.cache.values().flat_map(|venv| venv.usage_parts());
+        combine_usage(parts)
+    }
 }
 
 fn to_cache_path(venv_path: &Path, cache_dir: &Path) -> Option<PathBuf> {
@@ -221,6 +576,26 @@ fn to_cache_path(venv_path: &Path, cache_dir: &Path) -> Option<PathBuf> {
     Some(cached_file)
 }
 
+/// The side file with the directory records of the venv at `venv_path`.
+fn to_memo_path(venv_path: &Path, cache_dir: &Path) -> PathBuf {
+    let fname = venv_path
+        .file_name()
+        .expect("Could not get the filename")
+        .to_str()
+        .unwrap();
+
+    cache_dir.join(format!("{fname}.memo"))
+}
+
+/// Reads the directory records saved along with `venv`, so that a refresh only walks the
+/// directories that changed since. The hardlinked files of its packages are restored from them.
+fn load_dir_memo(venv: &mut Venv, cache_dir: &Path) {
+    if let Ok(memo) = DirMemo::load(&venv.path, &to_memo_path(&venv.path, cache_dir)) {
+        venv.dir_memo.0 = memo;
+        venv.restore_linked();
+    }
+}
+
 // TODO: proper tests
 // #[cfg(test)]
 // mod tests {
@@ -282,3 +657,388 @@ fn to_cache_path(venv_path: &Path, cache_dir: &Path) -> Option<PathBuf> {
 //         assert!(!res);
 //     }
 // }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{BTreeMap, BTreeSet},
+        fs::{self, File},
+        path::Path,
+        sync::{Arc, RwLock, atomic::AtomicBool, mpsc},
+        time::{Duration, SystemTime},
+    };
+
+    use tempfile::tempdir;
+
+    use crate::{
+        analysis::{apply_dedupe, find_duplicates, plan_dedupe},
+        core::VenvManager,
+        tui::SyncMsg,
+        venv::test_utils::{mock_package, mock_venv},
+    };
+
+    fn manager(cache_path: &Path) -> VenvManager {
+        VenvManager {
+            cache: BTreeMap::new(),
+            missing: BTreeSet::new(),
+            cache_path: cache_path.to_path_buf(),
+        }
+    }
+
+    fn manager_with_venvs(cache_path: &Path, venvs_dir: &Path, n: usize) -> VenvManager {
+        let mut vm = manager(cache_path);
+        for i in 0..n {
+            let venv_path = venvs_dir.join(format!("env{i}"));
+            fs::create_dir(&venv_path).unwrap();
+            let site_packages = mock_venv(&venv_path);
+            mock_package(&site_packages, "alpha", "1.0", &[("alpha.py", "a = 1")]);
+            vm.get(&venv_path).unwrap();
+        }
+        vm
+    }
+
+    #[test]
+    fn sync_reports_every_venv() {
+        let cache_dir = tempdir().unwrap();
+        let venvs_dir = tempdir().unwrap();
+        let vm = manager_with_venvs(cache_dir.path(), venvs_dir.path(), 3);
+        fs::remove_dir_all(venvs_dir.path().join("env1")).unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let vm_arc = Arc::new(RwLock::new(vm));
+        VenvManager::threaded_sync(Arc::clone(&vm_arc), tx, cancel)
+            .join()
+            .unwrap();
+
+        let msgs: Vec<SyncMsg> = rx.try_iter().collect();
+        let updated = msgs
+            .iter()
+            .filter(|m| matches!(m, SyncMsg::VenvUpdated { .. }))
+            .count();
+        let missing = msgs
+            .iter()
+            .filter(|m| matches!(m, SyncMsg::VenvMissing(_)))
+            .count();
+        assert_eq!(updated, 2);
+        assert_eq!(missing, 1);
+        assert!(matches!(msgs.last(), Some(SyncMsg::Finished)));
+        // missing venvs are kept in the cache
+        assert_eq!(vm_arc.read().unwrap().get_venvs().len(), 3);
+    }
+
+    #[test]
+    fn sync_progress_tells_same_named_venvs_apart() {
+        let cache_dir = tempdir().unwrap();
+        let projects = tempdir().unwrap();
+        let mut vm = manager(cache_dir.path());
+        for project in ["first", "second"] {
+            let venv_path = projects.path().join(project).join(".venv");
+            fs::create_dir_all(&venv_path).unwrap();
+            let site_packages = mock_venv(&venv_path);
+            mock_package(&site_packages, "alpha", "1.0", &[("alpha.py", "a = 1")]);
+            vm.get(&venv_path).unwrap();
+        }
+
+        let (tx, rx) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        VenvManager::threaded_sync(Arc::new(RwLock::new(vm)), tx, cancel)
+            .join()
+            .unwrap();
+
+        let mut updated: Vec<_> = rx
+            .try_iter()
+            .filter_map(|m| match m {
+                SyncMsg::VenvUpdated { path, .. } => Some(path),
+                _ => None,
+            })
+            .collect();
+        updated.sort();
+        updated.dedup();
+        assert_eq!(updated.len(), 2);
+    }
+
+    #[test]
+    fn cancelled_sync_checks_nothing() {
+        let cache_dir = tempdir().unwrap();
+        let venvs_dir = tempdir().unwrap();
+        let vm = manager_with_venvs(cache_dir.path(), venvs_dir.path(), 3);
+
+        let (tx, rx) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(true));
+        VenvManager::threaded_sync(Arc::new(RwLock::new(vm)), tx, cancel)
+            .join()
+            .unwrap();
+
+        let msgs: Vec<SyncMsg> = rx.try_iter().collect();
+        assert!(!msgs.iter().any(|m| matches!(m, SyncMsg::Progress { .. })));
+        assert!(matches!(msgs.last(), Some(SyncMsg::Finished)));
+    }
+
+    #[test]
+    fn sync_reuses_saved_dir_records() {
+        let cache_dir = tempdir().unwrap();
+        let venvs_dir = tempdir().unwrap();
+        let venv_path = venvs_dir.path().join("env");
+        fs::create_dir(&venv_path).unwrap();
+        let site_packages = mock_venv(&venv_path);
+        let dist_info = mock_package(
+            &site_packages,
+            "alpha",
+            "1.0",
+            &[("alpha/__init__.py", "a = 1")],
+        );
+        let mut vm = manager(cache_dir.path());
+        let size = vm.get(&venv_path).unwrap().packages[0].size;
+        vm.save_cache().unwrap();
+        assert!(cache_dir.path().join("env.memo").exists());
+
+        // rewriting a file in place leaves the mtime of its directory alone, so a walk that
+        // starts from the saved records keeps the old size of `alpha/`
+        fs::write(site_packages.join("alpha/__init__.py"), "a = 1 + 1").unwrap();
+        let later = SystemTime::now() + Duration::from_secs(10);
+        File::open(&dist_info).unwrap().set_modified(later).unwrap();
+
+        let mut vm = manager(cache_dir.path());
+        vm.load_cache().unwrap();
+        vm.sync_cache().unwrap();
+        let venv = vm.get(&venv_path).unwrap();
+        assert_eq!(venv.packages[0].size, size);
+        assert!(!venv.dir_memo.0.is_empty());
+    }
+
+    #[test]
+    fn reload_replaces_cached_venv() {
+        let cache_dir = tempdir().unwrap();
+        let venvs_dir = tempdir().unwrap();
+        let vm = manager_with_venvs(cache_dir.path(), venvs_dir.path(), 1);
+        let venv = vm.get_venvs()[0].clone();
+        let site_packages = venv.site_packages().unwrap();
+        mock_package(&site_packages, "beta", "2.0", &[("beta.py", "b = 2")]);
+
+        let (tx, rx) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let vm_arc = Arc::new(RwLock::new(vm));
+        VenvManager::threaded_reload(Arc::clone(&vm_arc), tx, vec![venv.path.clone()], cancel)
+            .join()
+            .unwrap();
+
+        let msgs: Vec<SyncMsg> = rx.try_iter().collect();
+        assert!(!msgs.iter().any(|m| matches!(m, SyncMsg::Error(_))));
+        let reloaded = vm_arc.read().unwrap().cached(&venv.path).unwrap();
+        assert_eq!(reloaded.packages.len(), 2);
+    }
+
+    #[test]
+    fn reload_sees_files_rewritten_in_place() {
+        let cache_dir = tempdir().unwrap();
+        let venvs_dir = tempdir().unwrap();
+        let mut vm = manager_with_venvs(cache_dir.path(), venvs_dir.path(), 1);
+        let venv = vm.get_venvs()[0].clone();
+        let module = venv.site_packages().unwrap().join("alpha.py");
+        let before = venv.usage.apparent;
+
+        // writing to an existing file leaves the directory mtime alone
+        fs::write(&module, "x".repeat(4096)).unwrap();
+        vm.reload_venv(&venv.path).unwrap();
+
+        let reloaded = vm.cached(&venv.path).unwrap();
+        assert!(reloaded.usage.apparent > before + 4000);
+    }
+
+    #[test]
+    fn dir_memo_is_not_in_cache_file() {
+        let cache_dir = tempdir().unwrap();
+        let venvs_dir = tempdir().unwrap();
+        let vm = manager_with_venvs(cache_dir.path(), venvs_dir.path(), 1);
+        assert!(!vm.get_venvs()[0].dir_memo.0.is_empty());
+        vm.save_cache().unwrap();
+
+        // the records go to their own file, which is read along with the cache files
+        let mut loaded = manager(cache_dir.path());
+        let venvs = loaded.venvs_from_cache().unwrap();
+        assert_eq!(venvs.len(), 1);
+        assert!(venvs[0].dir_memo.0.is_empty());
+        assert_eq!(venvs[0].usage, vm.get_venvs()[0].usage);
+
+        loaded.load_cache().unwrap();
+        assert_eq!(
+            loaded.get_venvs()[0].dir_memo.0,
+            vm.get_venvs()[0].dir_memo.0
+        );
+    }
+
+    #[test]
+    fn duplicates_skip_deleted_venv() {
+        let cache_dir = tempdir().unwrap();
+        let venvs_dir = tempdir().unwrap();
+        let mut vm = manager_with_venvs(cache_dir.path(), venvs_dir.path(), 3);
+        let deleted = vm.get_venvs()[1].path.clone();
+        fs::remove_dir_all(&deleted).unwrap();
+
+        vm.sync_cache().unwrap();
+        assert!(vm.is_missing(&deleted));
+        assert!(vm.is_venv_stale(&deleted).is_err());
+
+        let groups = find_duplicates(&vm.present_venvs(), true);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].copies.len(), 2);
+        assert!(groups[0].copies.iter().all(|c| c.venv_path != deleted));
+    }
+
+    #[test]
+    fn dedupe_skips_deleted_venv() {
+        let cache_dir = tempdir().unwrap();
+        let venvs_dir = tempdir().unwrap();
+        let mut vm = manager_with_venvs(cache_dir.path(), venvs_dir.path(), 3);
+        let deleted = vm.get_venvs()[1].path.clone();
+        fs::remove_dir_all(&deleted).unwrap();
+
+        // what `dedupe --dry-run` prints
+        vm.sync_cache().unwrap();
+        let plan = plan_dedupe(&find_duplicates(&vm.present_venvs(), true));
+        assert_eq!(plan.links().count(), 1);
+        assert!(plan.packages.iter().all(|p| !p.venvs.contains(&deleted)));
+
+        let outcome = apply_dedupe(&plan);
+        assert!(outcome.failed.is_empty());
+        assert_eq!(outcome.linked, 1);
+    }
+
+    #[test]
+    fn forget_removes_cache_file() {
+        let cache_dir = tempdir().unwrap();
+        let venvs_dir = tempdir().unwrap();
+        let venv_path = venvs_dir.path().join("env");
+        fs::create_dir(&venv_path).unwrap();
+        let site_packages = mock_venv(&venv_path);
+        mock_package(&site_packages, "alpha", "1.0", &[("alpha.py", "a = 1")]);
+
+        let mut vm = manager(cache_dir.path());
+        let venv = vm.get(&venv_path).unwrap();
+        vm.save_cache().unwrap();
+        // the venv and its directory records
+        assert_eq!(fs::read_dir(cache_dir.path()).unwrap().count(), 2);
+
+        vm.forget(&venv.path).unwrap();
+        assert!(vm.get_venvs().is_empty());
+        assert_eq!(fs::read_dir(cache_dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn moved_venv_is_located() {
+        let cache_dir = tempdir().unwrap();
+        let venvs_dir = tempdir().unwrap();
+        let venv_path = venvs_dir.path().join("env");
+        fs::create_dir(&venv_path).unwrap();
+        let site_packages = mock_venv(&venv_path);
+        mock_package(&site_packages, "alpha", "1.0", &[("alpha.py", "a = 1")]);
+
+        let mut vm = manager(cache_dir.path());
+        let old_path = vm.get(&venv_path).unwrap().path.clone();
+
+        let new_path = venvs_dir.path().join("renamed");
+        fs::rename(&venv_path, &new_path).unwrap();
+        vm.mark_missing(&old_path);
+
+        let found = vm.locate(&old_path).expect("moved venv wasn't found");
+        assert_eq!(found, new_path);
+
+        vm.relocate(&old_path, &found).unwrap();
+        assert!(!vm.is_missing(&old_path));
+        let paths: Vec<_> = vm.get_venvs().iter().map(|v| v.path.clone()).collect();
+        assert_eq!(paths, vec![dunce::canonicalize(&new_path).unwrap()]);
+    }
+
+    #[test]
+    fn moved_venv_replaces_old_entry_on_get() {
+        let cache_dir = tempdir().unwrap();
+        let venvs_dir = tempdir().unwrap();
+        let venv_path = venvs_dir.path().join("env");
+        fs::create_dir(&venv_path).unwrap();
+        let site_packages = mock_venv(&venv_path);
+        mock_package(&site_packages, "alpha", "1.0", &[("alpha.py", "a = 1")]);
+
+        let mut vm = manager(cache_dir.path());
+        vm.get(&venv_path).unwrap();
+
+        let new_path = venvs_dir.path().join("renamed");
+        fs::rename(&venv_path, &new_path).unwrap();
+        vm.get(&new_path).unwrap();
+
+        assert_eq!(vm.get_venvs().len(), 1);
+    }
+
+    #[test]
+    fn recreated_venv_is_not_a_move() {
+        let cache_dir = tempdir().unwrap();
+        let venvs_dir = tempdir().unwrap();
+        let old_path = venvs_dir.path().join("env");
+        fs::create_dir(&old_path).unwrap();
+        let site_packages = mock_venv(&old_path);
+        mock_package(&site_packages, "alpha", "1.0", &[("alpha.py", "a = 1")]);
+
+        let mut vm = manager(cache_dir.path());
+        let old_path = vm.get(&old_path).unwrap().path.clone();
+        // the tempdir is kept alive so the new dist-info can't reuse the old inode
+        let trash = venvs_dir.path().join("trash");
+        fs::rename(&old_path, &trash).unwrap();
+
+        // same pyvenv.cfg and packages, but installed anew
+        let new_path = venvs_dir.path().join("project");
+        fs::create_dir(&new_path).unwrap();
+        let site_packages = mock_venv(&new_path);
+        mock_package(&site_packages, "alpha", "1.0", &[("alpha.py", "a = 1")]);
+        vm.get(&new_path).unwrap();
+
+        assert_eq!(vm.get_venvs().len(), 2);
+        assert!(vm.cached(&old_path).is_some());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn disk_usage_counts_hardlinks_once() {
+        let cache_dir = tempdir().unwrap();
+        let venvs_dir = tempdir().unwrap();
+        let mut vm = manager(cache_dir.path());
+
+        let contents = "x".repeat(1000);
+        let first = venvs_dir.path().join("first");
+        let second = venvs_dir.path().join("second");
+        fs::create_dir(&first).unwrap();
+        fs::create_dir(&second).unwrap();
+        let first_sp = mock_venv(&first);
+        let second_sp = mock_venv(&second);
+        mock_package(
+            &first_sp,
+            "alpha",
+            "1.0",
+            &[("alpha/__init__.py", &contents)],
+        );
+        mock_package(&second_sp, "alpha", "1.0", &[("alpha/__init__.py", "")]);
+        // like installers that link files from a shared cache
+        fs::remove_file(second_sp.join("alpha/__init__.py")).unwrap();
+        fs::hard_link(
+            first_sp.join("alpha/__init__.py"),
+            second_sp.join("alpha/__init__.py"),
+        )
+        .unwrap();
+
+        let first_usage = vm.get(&first).unwrap().usage;
+        let second = vm.get(&second).unwrap();
+        assert_eq!(second.linked_size(), 1000);
+
+        let total = vm.disk_usage();
+        assert_eq!(total.apparent, first_usage.apparent + second.usage.apparent);
+        assert_eq!(total.unique, total.apparent - 1000);
+
+        // the linked files aren't in the cache files, they're restored from the records
+        vm.save_cache().unwrap();
+        let cached = vm.venvs_from_cache().unwrap();
+        assert!(cached.iter().all(|v| v.linked_size() == 0));
+        let mut loaded = manager(cache_dir.path());
+        loaded.load_cache().unwrap();
+        assert_eq!(loaded.cached(&second.path).unwrap().linked_size(), 1000);
+        assert_eq!(loaded.disk_usage(), total);
+    }
+}
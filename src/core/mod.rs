@@ -1,3 +1,5 @@
 pub mod cache;
+pub mod watcher;
 
 pub use cache::VenvManager;
+pub use watcher::VenvWatcher;
@@ -0,0 +1,137 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver},
+};
+
+use color_eyre::Result;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::venv::Venv;
+
+/// Watches the site-packages directories of venvs and tells which venvs changed.
+///
+/// Only the top level of site-packages is watched. Installing, upgrading or removing a
+/// distribution always adds or removes a `.dist-info` directory there, so that's enough to
+/// notice `pip install` and friends without watching every file.
+pub struct VenvWatcher {
+    watcher: RecommendedWatcher,
+    rx: Receiver<notify::Result<Event>>,
+    /// site-packages -> venv
+    watched: HashMap<PathBuf, PathBuf>,
+}
+
+impl std::fmt::Debug for VenvWatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VenvWatcher")
+            .field("watched", &self.watched)
+            .finish_non_exhaustive()
+    }
+}
+
+impl VenvWatcher {
+    pub fn new() -> Result<Self> {
+        let (tx, rx) = mpsc::channel();
+        let watcher = notify::recommended_watcher(tx)?;
+        Ok(Self {
+            watcher,
+            rx,
+            watched: HashMap::new(),
+        })
+    }
+
+    /// Watches exactly the given venvs, dropping the ones that aren't in `venvs` anymore.
+    /// Venvs that can't be watched (e.g. missing ones) are skipped and returned as errors.
+    pub fn watch_venvs(&mut self, venvs: &[&Venv]) -> Vec<color_eyre::Report> {
+        let mut errors = Vec::new();
+        let mut wanted = HashMap::new();
+        for venv in venvs {
+            match venv.site_packages() {
+                Ok(site_packages) => {
+                    wanted.insert(site_packages, venv.path.clone());
+                }
+                Err(e) => errors.push(e),
+            }
+        }
+
+        let stale: Vec<PathBuf> = self
+            .watched
+            .keys()
+            .filter(|sp| !wanted.contains_key(*sp))
+            .cloned()
+            .collect();
+        for site_packages in stale {
+            // the directory might be gone already, nothing to do then
+            let _ = self.watcher.unwatch(&site_packages);
+            self.watched.remove(&site_packages);
+        }
+
+        for (site_packages, venv_path) in wanted {
+            if self.watched.contains_key(&site_packages) || !site_packages.is_dir() {
+                continue;
+            }
+            match self
+                .watcher
+                .watch(&site_packages, RecursiveMode::NonRecursive)
+            {
+                Ok(_) => {
+                    self.watched.insert(site_packages, venv_path);
+                }
+                Err(e) => errors.push(e.into()),
+            }
+        }
+        errors
+    }
+
+    /// Venvs that changed since the last call.
+    pub fn changed(&self) -> BTreeSet<PathBuf> {
+        self.rx
+            .try_iter()
+            .filter_map(Result::ok)
+            .filter(|event| !matches!(event.kind, EventKind::Access(_)))
+            .flat_map(|event| event.paths)
+            .filter_map(|path| self.venv_of(&path))
+            .collect()
+    }
+
+    fn venv_of(&self, path: &Path) -> Option<PathBuf> {
+        self.watched
+            .get(path)
+            .or_else(|| path.parent().and_then(|parent| self.watched.get(parent)))
+            .cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::venv::test_utils::{mock_package, mock_venv};
+
+    #[test]
+    fn install_is_noticed() {
+        let dir = tempdir().unwrap();
+        let site_packages = mock_venv(dir.path());
+        mock_package(&site_packages, "alpha", "1.0", &[("alpha.py", "a = 1")]);
+        let venv = Venv::from_path(dir.path()).unwrap();
+
+        let mut watcher = VenvWatcher::new().unwrap();
+        assert!(watcher.watch_venvs(&[&venv]).is_empty());
+
+        mock_package(&site_packages, "beta", "2.0", &[("beta.py", "b = 2")]);
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut changed = BTreeSet::new();
+        while changed.is_empty() && Instant::now() < deadline {
+            changed = watcher.changed();
+            thread::sleep(Duration::from_millis(50));
+        }
+        assert_eq!(changed, BTreeSet::from([venv.path.clone()]));
+    }
+}
@@ -0,0 +1,189 @@
+use bincode::{Decode, Encode};
+use std::{
+    ffi::OsStr,
+    ops::{Add, AddAssign, Sub},
+    path::{Component, Path},
+};
+
+use super::hardlink::sub_size;
+
+/// What a file in a venv is for, decided by its path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    /// `.py` and `.pyi` files
+    Sources,
+    /// Anything in `__pycache__` and `.pyc` files
+    Bytecode,
+    /// Compiled extension modules
+    Native,
+    /// Shared libraries bundled by auditwheel and delocate in `<package>.libs`
+    Libs,
+    Data,
+    /// Anything in `test` or `tests` directories
+    Tests,
+    DistInfo,
+}
+
+impl Category {
+    pub const ALL: [Category; 7] = [
+        Category::Sources,
+        Category::Bytecode,
+        Category::Native,
+        Category::Libs,
+        Category::Data,
+        Category::Tests,
+        Category::DistInfo,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Category::Sources => "sources",
+            Category::Bytecode => "bytecode",
+            Category::Native => "native",
+            Category::Libs => "libs",
+            Category::Data => "data",
+            Category::Tests => "tests",
+            Category::DistInfo => "dist-info",
+        }
+    }
+
+    /// Category of a file from its path relative to the directory being walked. The first
+    /// matching rule wins, so bytecode of tests is bytecode and extensions in tests are tests.
+    pub fn of(relative: &Path) -> Self {
+        let dirs: Vec<&OsStr> = relative
+            .parent()
+            .into_iter()
+            .flat_map(Path::components)
+            .filter_map(|c| match c {
+                Component::Normal(name) => Some(name),
+                _ => None,
+            })
+            .collect();
+        let dir_ends_with = |suffix: &str| {
+            dirs.iter()
+                .any(|d| d.to_str().is_some_and(|d| d.ends_with(suffix)))
+        };
+        let extension = relative.extension().and_then(OsStr::to_str);
+        // versioned libraries like `libgfortran.so.5.0.0` end in their version
+        let is_native = relative
+            .file_name()
+            .and_then(OsStr::to_str)
+            .is_some_and(|name| {
+                name.split('.')
+                    .skip(1)
+                    .any(|ext| matches!(ext, "so" | "pyd" | "dylib"))
+            });
+
+        if dir_ends_with(".dist-info") {
+            Category::DistInfo
+        } else if dirs.iter().any(|d| *d == "__pycache__") || extension == Some("pyc") {
+            Category::Bytecode
+        } else if dir_ends_with(".libs") {
+            Category::Libs
+        } else if dirs.iter().any(|d| *d == "tests" || *d == "test") {
+            Category::Tests
+        } else if is_native {
+            Category::Native
+        } else {
+            match extension {
+                Some("py" | "pyi") => Category::Sources,
+                _ => Category::Data,
+            }
+        }
+    }
+}
+
+/// Apparent size of a directory tree split by [`Category`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Encode, Decode)]
+pub struct SizeBreakdown {
+    sizes: [u64; Category::ALL.len()],
+}
+
+impl SizeBreakdown {
+    pub fn get(&self, category: Category) -> u64 {
+        self.sizes[category as usize]
+    }
+
+    pub fn add_size(&mut self, category: Category, size: u64) {
+        self.sizes[category as usize] += size;
+    }
+
+    pub fn total(&self) -> u64 {
+        self.sizes.iter().sum()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Category, u64)> + '_ {
+        Category::ALL.into_iter().map(|c| (c, self.get(c)))
+    }
+}
+
+impl Add for SizeBreakdown {
+    type Output = Self;
+
+    fn add(mut self, rhs: Self) -> Self {
+        for (size, other) in self.sizes.iter_mut().zip(rhs.sizes) {
+            *size += other;
+        }
+        self
+    }
+}
+
+impl AddAssign for SizeBreakdown {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for SizeBreakdown {
+    type Output = Self;
+
+    fn sub(mut self, rhs: Self) -> Self {
+        for (size, other) in self.sizes.iter_mut().zip(rhs.sizes) {
+            *size = sub_size(*size, other);
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    #[test]
+    fn test_category_of() {
+        let cases = [
+            ("numpy/__init__.py", Category::Sources),
+            ("numpy/__init__.pyi", Category::Sources),
+            (
+                "numpy/__pycache__/__init__.cpython-313.pyc",
+                Category::Bytecode,
+            ),
+            (
+                "numpy/_core/_multiarray_umath.cpython-313-x86_64-linux-gnu.so",
+                Category::Native,
+            ),
+            (
+                "numpy.libs/libscipy_openblas64_-ff651d7f.so",
+                Category::Libs,
+            ),
+            ("scipy.libs/libgfortran-040039e1.so.5.0.0", Category::Libs),
+            ("pkg/_vendored/libffi.so.8", Category::Native),
+            ("pkg/libfoo.so.1.2", Category::Native),
+            ("pkg/also.sorted.txt", Category::Data),
+            ("numpy/_core/include/numpy/ndarraytypes.h", Category::Data),
+            ("numpy/tests/test_ctypeslib.py", Category::Tests),
+            (
+                "numpy/tests/__pycache__/test_ctypeslib.pyc",
+                Category::Bytecode,
+            ),
+            ("numpy-2.2.4.dist-info/RECORD", Category::DistInfo),
+            // only directories count, a module called tests.py is a source
+            ("pkg/tests.py", Category::Sources),
+        ];
+        for (path, category) in cases {
+            assert_eq!(Category::of(Path::new(path)), category, "{path}");
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use serde::Deserialize;
+
+const SI_UNITS: [&str; 6] = ["B", "kB", "MB", "GB", "TB", "PB"];
+const IEC_UNITS: [&str; 6] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+
+/// How sizes are printed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SizeFormat {
+    /// Powers of 1000, e.g. `1.5 MB`
+    Si,
+    /// Powers of 1024, e.g. `1.5 MiB`
+    #[default]
+    Iec,
+    /// Number of bytes without a unit, for scripts
+    Bytes,
+}
+
+impl SizeFormat {
+    pub fn format(self, size: u64) -> String {
+        let (base, units) = match self {
+            SizeFormat::Si => (1000.0, SI_UNITS),
+            SizeFormat::Iec => (1024.0, IEC_UNITS),
+            SizeFormat::Bytes => return size.to_string(),
+        };
+
+        let mut value = size as f64;
+        let mut unit = 0;
+        // compare the rounded value so 1023.96 KiB is shown as 1.0 MiB instead of 1024.0 KiB
+        while unit < units.len() - 1 && (value * 10.0).round() / 10.0 >= base {
+            value /= base;
+            unit += 1;
+        }
+
+        if unit == 0 {
+            format!("{size} {}", units[0])
+        } else {
+            format!("{value:.1} {}", units[unit])
+        }
+    }
+
+    /// A size difference with its sign, `+1.5 MiB` or `-3 B`.
+    pub fn format_delta(self, delta: i64) -> String {
+        let sign = if delta < 0 { '-' } else { '+' };
+        format!("{sign}{}", self.format(delta.unsigned_abs()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_si() {
+        assert_eq!(SizeFormat::Si.format(0), "0 B");
+        assert_eq!(SizeFormat::Si.format(999), "999 B");
+        assert_eq!(SizeFormat::Si.format(1_000), "1.0 kB");
+        assert_eq!(SizeFormat::Si.format(1_550_000), "1.6 MB");
+        assert_eq!(SizeFormat::Si.format(2_000_000_000_000), "2.0 TB");
+        assert_eq!(SizeFormat::Si.format(3_200_000_000_000_000), "3.2 PB");
+        // no unit above PB
+        assert_eq!(
+            SizeFormat::Si.format(5_000_000_000_000_000_000),
+            "5000.0 PB"
+        );
+    }
+
+    #[test]
+    fn test_format_iec() {
+        assert_eq!(SizeFormat::Iec.format(1023), "1023 B");
+        assert_eq!(SizeFormat::Iec.format(1024), "1.0 KiB");
+        assert_eq!(SizeFormat::Iec.format(1536), "1.5 KiB");
+        assert_eq!(SizeFormat::Iec.format(1024 * 1024 - 1), "1.0 MiB");
+        assert_eq!(SizeFormat::Iec.format(3 << 40), "3.0 TiB");
+        assert_eq!(SizeFormat::Iec.format(1 << 50), "1.0 PiB");
+    }
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(SizeFormat::Bytes.format(1_234_567), "1234567");
+        assert_eq!(SizeFormat::Bytes.format_delta(-42), "-42");
+        assert_eq!(SizeFormat::Iec.format_delta(1536), "+1.5 KiB");
+        assert_eq!(SizeFormat::Iec.format_delta(0), "+0 B");
+    }
+}
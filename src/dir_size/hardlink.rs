@@ -0,0 +1,482 @@
+use bincode::{Decode, Encode};
+use serde::Deserialize;
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, Metadata},
+    io,
+    ops::{Add, AddAssign, Sub},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use super::{
+    breakdown::{Category, SizeBreakdown},
+    chonk::Chonk,
+    memo::{DirMemo, DirRecord, dir_ino},
+    stealing::{SkippedEntry, StealingReader},
+};
+
+/// Which number is shown as the size of packages and venvs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SizeMode {
+    /// Sum of file lengths
+    #[default]
+    Apparent,
+    /// Space taken by the blocks allocated to the files, same as `du`
+    Allocated,
+}
+
+/// Size of a directory tree counted a few ways.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Encode, Decode)]
+pub struct DiskUsage {
+    /// Sum of file lengths, a file with several hardlinks is counted for each of them
+    pub apparent: u64,
+    /// Sum of file lengths, each `(device, inode)` is counted once
+    pub unique: u64,
+    /// Allocated blocks in bytes, each `(device, inode)` is counted once
+    pub allocated: u64,
+}
+
+impl DiskUsage {
+    /// Size of the tree according to `mode`.
+    pub fn size(&self, mode: SizeMode) -> u64 {
+        match mode {
+            SizeMode::Apparent => self.apparent,
+            SizeMode::Allocated => self.allocated,
+        }
+    }
+
+    /// Space actually taken on disk according to `mode`, hardlinks counted once.
+    pub fn on_disk(&self, mode: SizeMode) -> u64 {
+        match mode {
+            SizeMode::Apparent => self.unique,
+            SizeMode::Allocated => self.allocated,
+        }
+    }
+}
+
+impl Add for DiskUsage {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self {
+            apparent: self.apparent + rhs.apparent,
+            unique: self.unique + rhs.unique,
+            allocated: self.allocated + rhs.allocated,
+        }
+    }
+}
+
+impl Sub for DiskUsage {
+    type Output = Self;
+
+    /// Only parts of a usage are ever taken out of it, see [`sub_size`].
+    fn sub(self, rhs: Self) -> Self {
+        Self {
+            apparent: sub_size(self.apparent, rhs.apparent),
+            unique: sub_size(self.unique, rhs.unique),
+            allocated: sub_size(self.allocated, rhs.allocated),
+        }
+    }
+}
+
+/// `size - part`, clamped to zero. Parts of a size are measured separately and can add up to more
+/// than the whole, like a directory that two distributions both claim.
+pub(super) fn sub_size(size: u64, part: u64) -> u64 {
+    size.saturating_sub(part)
+}
+
+impl AddAssign for DiskUsage {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+/// A file that has more than one hardlink, so it might be shared with other venvs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Encode, Decode)]
+pub struct LinkedFile {
+    pub dev: u64,
+    pub ino: u64,
+    pub size: u64,
+    pub allocated: u64,
+}
+
+/// Sizes directories while keeping track of the hardlinked files it has seen. Installers like uv
+/// link the same file into many venvs, which every other reader counts in full each time.
+///
+/// Reuse the same reader for several directories to count files shared between them once.
+/// Entries below the walked directory that can't be read are left out of the size and kept, see
+/// [`HardlinkReader::take_skipped`]. The subdirectories are read by a [`StealingReader`].
+#[derive(Debug, Default)]
+pub struct HardlinkReader {
+    walker: StealingReader,
+    seen: Mutex<HashMap<(u64, u64), (u64, u64)>>,
+    memo: Arc<DirMemo>,
+    visited: Mutex<HashMap<PathBuf, DirRecord>>,
+    skipped: Mutex<Vec<SkippedEntry>>,
+}
+
+impl HardlinkReader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A reader that doesn't list the directories that are unchanged since `memo` was made.
+    pub fn with_memo(memo: Arc<DirMemo>) -> Self {
+        Self {
+            memo,
+            ..Default::default()
+        }
+    }
+
+    pub fn get_dir_usage(&self, dir: &Path) -> color_eyre::Result<DiskUsage> {
+        Ok(self.walk(dir, dir)?.0)
+    }
+
+    /// Usage of `dir` along with its size split by category. Categories are decided from the
+    /// paths relative to `root`, which should be the venv or the site-packages directory.
+    pub fn get_dir_breakdown(
+        &self,
+        dir: &Path,
+        root: &Path,
+    ) -> color_eyre::Result<(DiskUsage, SizeBreakdown)> {
+        Ok(self.walk(dir, root)?)
+    }
+
+    /// Records of the directories walked so far, to be added to a [`DirMemo`].
+    pub fn take_visited(&self) -> HashMap<PathBuf, DirRecord> {
+        std::mem::take(&mut *self.visited.lock().expect("mutex poisoned"))
+    }
+
+    /// Entries that couldn't be read so far, sorted by path.
+    pub fn take_skipped(&self) -> Vec<SkippedEntry> {
+        let mut skipped = std::mem::take(&mut *self.skipped.lock().expect("mutex poisoned"));
+        skipped.sort_by(|a, b| a.path.cmp(&b.path));
+        skipped
+    }
+
+    fn skip(&self, path: PathBuf, error: &io::Error) {
+        self.skipped
+            .lock()
+            .expect("mutex poisoned")
+            .push(SkippedEntry::new(path, error));
+    }
+
+    fn walk(&self, dir: &Path, root: &Path) -> io::Result<(DiskUsage, SizeBreakdown)> {
+        let meta = fs::symlink_metadata(dir)?;
+        // only the directory itself has to be readable, entries below it are skipped
+        let (mut total, subdirs) = self.visit(dir, root, &meta)?;
+        let totals =
+            self.walker
+                .visit_dirs(subdirs, |dir, total: &mut (DiskUsage, SizeBreakdown)| {
+                    let visited = fs::symlink_metadata(dir).and_then(|m| self.visit(dir, root, &m));
+                    match visited {
+                        Ok(((usage, breakdown), subdirs)) => {
+                            total.0 += usage;
+                            total.1 += breakdown;
+                            subdirs
+                        }
+                        Err(e) => {
+                            self.skip(dir.to_path_buf(), &e);
+                            Vec::new()
+                        }
+                    }
+                });
+        for (usage, breakdown) in totals {
+            total.0 += usage;
+            total.1 += breakdown;
+        }
+        Ok(total)
+    }
+
+    /// Usage of the files right in `dir` and the subdirectories to visit next. A path that
+    /// isn't a directory is counted as a single file.
+    fn visit(
+        &self,
+        dir: &Path,
+        root: &Path,
+        meta: &Metadata,
+    ) -> io::Result<((DiskUsage, SizeBreakdown), Vec<PathBuf>)> {
+        if !meta.is_dir() {
+            let mut record = DirRecord::new(SystemTime::UNIX_EPOCH, 0);
+            add_file(&mut record, dir, root, meta);
+            return Ok((self.count(&record), Vec::new()));
+        }
+
+        let (record, complete) = match self.memo.get(dir, meta) {
+            Some(record) => (record.clone(), true),
+            None => self.list_dir(dir, root, meta)?,
+        };
+        let own = self.count(&record);
+        let subdirs = record.subdirs.iter().map(|name| dir.join(name)).collect();
+
+        // a record that misses entries would hide them on the next walk
+        if complete {
+            self.visited
+                .lock()
+                .expect("mutex poisoned")
+                .insert(dir.to_path_buf(), record);
+        }
+        Ok((own, subdirs))
+    }
+
+    /// Lists a directory and sums up the files in it. Subdirectories are told apart by the file
+    /// type of their entry, only files are stat'ed. Also tells whether every entry could be read.
+    fn list_dir(&self, dir: &Path, root: &Path, meta: &Metadata) -> io::Result<(DirRecord, bool)> {
+        let modified = meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+        let mut record = DirRecord::new(modified, dir_ino(meta));
+        let mut complete = true;
+        for entry in fs::read_dir(dir)? {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    self.skip(dir.to_path_buf(), &e);
+                    complete = false;
+                    continue;
+                }
+            };
+            // doesn't follow symlinks, neither does the metadata of the entry
+            let stat = entry.file_type().and_then(|ft| {
+                if ft.is_dir() {
+                    record.subdirs.push(PathBuf::from(entry.file_name()));
+                    Ok(())
+                } else {
+                    let m = entry.metadata()?;
+                    add_file(&mut record, &entry.path(), root, &m);
+                    Ok(())
+                }
+            });
+            if let Err(e) = stat {
+                self.skip(entry.path(), &e);
+                complete = false;
+            }
+        }
+        Ok((record, complete))
+    }
+
+    /// Hardlinked files seen so far.
+    pub fn linked_files(&self) -> Vec<LinkedFile> {
+        self.seen
+            .lock()
+            .expect("mutex poisoned")
+            .iter()
+            .map(|(&(dev, ino), &(size, allocated))| LinkedFile {
+                dev,
+                ino,
+                size,
+                allocated,
+            })
+            .collect()
+    }
+
+    /// Usage of the files of a record, linked files that were already seen aren't unique.
+    fn count(&self, record: &DirRecord) -> (DiskUsage, SizeBreakdown) {
+        let mut usage = record.usage;
+        if !record.linked.is_empty() {
+            let mut seen = self.seen.lock().expect("mutex poisoned");
+            for f in &record.linked {
+                usage.apparent += f.size;
+                if seen.insert((f.dev, f.ino), (f.size, f.allocated)).is_none() {
+                    usage.unique += f.size;
+                    usage.allocated += f.allocated;
+                }
+            }
+        }
+        (usage, record.breakdown)
+    }
+}
+
+fn add_file(record: &mut DirRecord, path: &Path, root: &Path, meta: &Metadata) {
+    let size = meta.len();
+    let allocated = allocated_size(meta);
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    record.breakdown.add_size(Category::of(relative), size);
+
+    match link_key(meta) {
+        Some((dev, ino)) => record.linked.push(LinkedFile {
+            dev,
+            ino,
+            size,
+            allocated,
+        }),
+        None => {
+            record.usage += DiskUsage {
+                apparent: size,
+                unique: size,
+                allocated,
+            }
+        }
+    }
+}
+
+impl Chonk for HardlinkReader {
+    fn get_dir_size(&self, dir: &Path) -> color_eyre::Result<u64> {
+        Ok(self.get_dir_usage(dir)?.unique)
+    }
+}
+
+/// Adds up the usages of several trees, counting the hardlinked files they share once. Each part
+/// is a usage along with the linked files that were seen while computing it.
+pub fn combine_usage<'a>(
+    parts: impl IntoIterator<Item = (DiskUsage, &'a [LinkedFile])>,
+) -> DiskUsage {
+    let mut total = DiskUsage::default();
+    let mut seen: HashSet<(u64, u64)> = HashSet::new();
+
+    for (usage, linked) in parts {
+        let linked_size: u64 = linked.iter().map(|f| f.size).sum();
+        let linked_allocated: u64 = linked.iter().map(|f| f.allocated).sum();
+        let (newly_seen, newly_allocated) = linked
+            .iter()
+            .filter(|f| seen.insert((f.dev, f.ino)))
+            .fold((0, 0), |(size, allocated), f| {
+                (size + f.size, allocated + f.allocated)
+            });
+
+        total.apparent += usage.apparent;
+        total.unique += usage.unique.saturating_sub(linked_size) + newly_seen;
+        total.allocated += usage.allocated.saturating_sub(linked_allocated) + newly_allocated;
+    }
+    total
+}
+
+#[cfg(unix)]
+fn link_key(meta: &Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    (meta.nlink() > 1).then(|| (meta.dev(), meta.ino()))
+}
+
+// inode numbers aren't available on stable for windows, every file is counted as unique there
+#[cfg(not(unix))]
+fn link_key(_meta: &Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// `st_blocks` is always in 512 byte units, regardless of the block size of the filesystem
+#[cfg(unix)]
+fn allocated_size(meta: &Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    meta.blocks() * 512
+}
+
+#[cfg(not(unix))]
+fn allocated_size(meta: &Metadata) -> u64 {
+    meta.len()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_hardlinks_counted_once() -> color_eyre::Result<()> {
+        let first = tempdir()?;
+        let second = tempdir()?;
+        fs::write(first.path().join("a.py"), "0123456789")?;
+        fs::hard_link(first.path().join("a.py"), first.path().join("b.py"))?;
+        fs::hard_link(first.path().join("a.py"), second.path().join("c.py"))?;
+        fs::write(second.path().join("d.py"), "01234")?;
+
+        let reader = HardlinkReader::new();
+        let usage = reader.get_dir_usage(first.path())?;
+        assert_eq!((usage.apparent, usage.unique), (20, 10));
+
+        // the file linked into the second tree was already counted
+        let usage = reader.get_dir_usage(second.path())?;
+        assert_eq!((usage.apparent, usage.unique), (15, 5));
+        Ok(())
+    }
+
+    #[test]
+    fn test_combine_usage() -> color_eyre::Result<()> {
+        let first = tempdir()?;
+        let second = tempdir()?;
+        fs::write(first.path().join("a.py"), "0123456789")?;
+        fs::hard_link(first.path().join("a.py"), second.path().join("a.py"))?;
+        fs::write(second.path().join("b.py"), "01234")?;
+
+        // separate readers, like packages that were parsed on their own
+        let first_reader = HardlinkReader::new();
+        let first_usage = first_reader.get_dir_usage(first.path())?;
+        let second_reader = HardlinkReader::new();
+        let second_usage = second_reader.get_dir_usage(second.path())?;
+
+        let first_linked = first_reader.linked_files();
+        let second_linked = second_reader.linked_files();
+        let total = combine_usage([
+            (first_usage, first_linked.as_slice()),
+            (second_usage, second_linked.as_slice()),
+        ]);
+        assert_eq!((total.apparent, total.unique), (25, 15));
+        assert_eq!(
+            total.allocated,
+            first_usage.allocated + second_usage.allocated - first_linked[0].allocated
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_allocated_size() -> color_eyre::Result<()> {
+        let dir = tempdir()?;
+        fs::write(dir.path().join("small.pyc"), "0")?;
+        // sparse, nothing is allocated for the hole
+        let sparse = fs::File::create(dir.path().join("sparse.bin"))?;
+        sparse.set_len(1 << 20)?;
+
+        let usage = HardlinkReader::new().get_dir_usage(dir.path())?;
+        assert_eq!(usage.apparent, (1 << 20) + 1);
+        // a one byte file still takes a whole block
+        assert!(usage.allocated >= 512);
+        assert!(usage.allocated < usage.apparent);
+        Ok(())
+    }
+
+    #[test]
+    fn test_unreadable_dir_is_skipped() -> color_eyre::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = tempdir()?;
+        let locked = root.path().join("locked");
+        fs::create_dir(&locked)?;
+        fs::write(locked.join("secret.py"), "1234")?;
+        fs::write(root.path().join("open.py"), "12")?;
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o000))?;
+        // root can read anything, there's nothing to skip then
+        if fs::read_dir(&locked).is_ok() {
+            return Ok(());
+        }
+
+        let reader = HardlinkReader::new();
+        assert_eq!(reader.get_dir_usage(root.path())?.apparent, 2);
+        let skipped = reader.take_skipped();
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].path, locked);
+        // the root is complete, the locked dir has no record to hide it next time
+        let visited = reader.take_visited();
+        assert!(visited.contains_key(root.path()));
+        assert!(!visited.contains_key(&locked));
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o755))?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_sub_saturates() {
+        let usage = DiskUsage {
+            apparent: 1,
+            unique: 1,
+            allocated: 1,
+        };
+        let rest = usage
+            - DiskUsage {
+                apparent: 2,
+                ..usage
+            };
+        assert_eq!(rest, DiskUsage::default());
+    }
+}
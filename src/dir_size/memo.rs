@@ -0,0 +1,222 @@
+use std::{
+    collections::HashMap,
+    fs::{self, Metadata},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use bincode::{Decode, Encode, config};
+use color_eyre::Result;
+
+use super::{DiskUsage, LinkedFile, SizeBreakdown};
+
+/// What a directory held the last time it was walked. Only the files right in the directory are
+/// summed up, subdirectories have records of their own.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct DirRecord {
+    modified: SystemTime,
+    ino: u64,
+    /// Files with a single link
+    pub(super) usage: DiskUsage,
+    /// Files with several links, they're counted once per reader when the record is used
+    pub(super) linked: Vec<LinkedFile>,
+    pub(super) breakdown: SizeBreakdown,
+    /// Names of the subdirectories
+    pub(super) subdirs: Vec<PathBuf>,
+}
+
+impl DirRecord {
+    pub(super) fn new(modified: SystemTime, ino: u64) -> Self {
+        Self {
+            modified,
+            ino,
+            usage: DiskUsage::default(),
+            linked: Vec::new(),
+            breakdown: SizeBreakdown::default(),
+            subdirs: Vec::new(),
+        }
+    }
+}
+
+/// Directory records from earlier walks, keyed by path.
+///
+/// Installers add, remove and rename files, all of which update the mtime of the directory they
+/// happen in. A record is reused as long as the mtime and the inode of its directory are the
+/// same, so only the changed subtrees are listed again. Files that are rewritten in place and new
+/// hardlinks to existing files aren't noticed until the venv is parsed from scratch. Records are
+/// saved to a file of their own next to the cache file of the venv, see [`DirMemo::save_to`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DirMemo {
+    dirs: HashMap<PathBuf, DirRecord>,
+}
+
+impl DirMemo {
+    pub fn len(&self) -> usize {
+        self.dirs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dirs.is_empty()
+    }
+
+    /// Writes the records of the directories under `root` to `path`, with their paths relative
+    /// to `root` to keep the file small.
+    pub fn save_to(&self, root: &Path, path: &Path) -> Result<()> {
+        let records: Vec<(&Path, &DirRecord)> = self
+            .dirs
+            .iter()
+            .filter_map(|(dir, record)| Some((dir.strip_prefix(root).ok()?, record)))
+            .collect();
+        let encoded = bincode::encode_to_vec(records, config::standard())?;
+        fs::write(path, encoded)?;
+        Ok(())
+    }
+
+    /// Reads records written by [`DirMemo::save_to`] for the directories under `root`.
+    pub fn load(root: &Path, path: &Path) -> Result<Self> {
+        let bytes = fs::read(path)?;
+        let (records, _len): (Vec<(PathBuf, DirRecord)>, usize) =
+            bincode::decode_from_slice(&bytes, config::standard())?;
+        let dirs = records
+            .into_iter()
+            .map(|(dir, record)| (root.join(dir), record))
+            .collect();
+        Ok(Self { dirs })
+    }
+
+    /// Hardlinked files in the records of `roots` and the directories under them, each file
+    /// once. Directories without a record are left out along with everything under them.
+    pub fn linked_files<'a>(&self, roots: impl IntoIterator<Item = &'a Path>) -> Vec<LinkedFile> {
+        let mut linked = HashMap::new();
+        let mut dirs: Vec<PathBuf> = roots.into_iter().map(Path::to_path_buf).collect();
+        while let Some(dir) = dirs.pop() {
+            let Some(record) = self.dirs.get(&dir) else {
+                continue;
+            };
+            for f in &record.linked {
+                linked.insert((f.dev, f.ino), *f);
+            }
+            dirs.extend(record.subdirs.iter().map(|name| dir.join(name)));
+        }
+        linked.into_values().collect()
+    }
+
+    /// The record of `dir` if it hasn't changed since it was made.
+    pub(super) fn get(&self, dir: &Path, meta: &Metadata) -> Option<&DirRecord> {
+        let record = self.dirs.get(dir)?;
+        let modified = meta.modified().ok()?;
+        (record.modified == modified && record.ino == dir_ino(meta)).then_some(record)
+    }
+
+    /// Adds the records of a walk. Records of subdirectories that are no longer in a re-listed
+    /// directory are dropped along with everything under them.
+    pub fn update(&mut self, visited: HashMap<PathBuf, DirRecord>) {
+        for (dir, record) in visited {
+            if let Some(old) = self.dirs.get(&dir) {
+                let gone: Vec<PathBuf> = old
+                    .subdirs
+                    .iter()
+                    .filter(|name| !record.subdirs.contains(name))
+                    .map(|name| dir.join(name))
+                    .collect();
+                for gone in gone {
+                    self.forget_under(&gone);
+                }
+            }
+            self.dirs.insert(dir, record);
+        }
+    }
+
+    /// Drops the records of directories right under `dir` that no longer exist, along with
+    /// everything under them. Used when `dir` itself isn't walked, like site-packages when only
+    /// the changed packages are parsed.
+    pub fn prune_missing_children(&mut self, dir: &Path) {
+        let gone: Vec<PathBuf> = self
+            .dirs
+            .keys()
+            .filter(|path| path.parent() == Some(dir) && !path.exists())
+            .cloned()
+            .collect();
+        for gone in gone {
+            self.forget_under(&gone);
+        }
+    }
+
+    fn forget_under(&mut self, dir: &Path) {
+        self.dirs.retain(|path, _| !path.starts_with(dir));
+    }
+}
+
+#[cfg(unix)]
+pub fn dir_ino(meta: &Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    meta.ino()
+}
+
+#[cfg(not(unix))]
+pub fn dir_ino(_meta: &Metadata) -> u64 {
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, sync::Arc};
+
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::dir_size::HardlinkReader;
+
+    fn walk(dir: &Path, memo: &DirMemo) -> (u64, DirMemo) {
+        let reader = HardlinkReader::with_memo(Arc::new(memo.clone()));
+        let size = reader.get_dir_usage(dir).unwrap().apparent;
+        let mut memo = memo.clone();
+        memo.update(reader.take_visited());
+        (size, memo)
+    }
+
+    #[test]
+    fn test_only_changed_dirs_are_walked() {
+        let root = tempdir().unwrap();
+        let dir = root.path();
+        fs::create_dir_all(dir.join("a/deep")).unwrap();
+        fs::create_dir_all(dir.join("b")).unwrap();
+        fs::write(dir.join("a/deep/x.py"), "12345").unwrap();
+        fs::write(dir.join("b/y.py"), "123").unwrap();
+
+        let (size, memo) = walk(dir, &DirMemo::default());
+        assert_eq!(size, 8);
+        assert_eq!(memo.len(), 4);
+
+        // rewriting a file in place leaves the directory alone, so the old size is reused
+        fs::write(dir.join("a/deep/x.py"), "1234567890").unwrap();
+        // a new file changes the mtime of b
+        fs::write(dir.join("b/z.py"), "1").unwrap();
+        let (size, memo) = walk(dir, &memo);
+        assert_eq!(size, 9);
+
+        fs::remove_dir_all(dir.join("a")).unwrap();
+        let (size, memo) = walk(dir, &memo);
+        assert_eq!(size, 4);
+        assert_eq!(memo.len(), 2);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let root = tempdir().unwrap();
+        let dir = root.path().join("venv");
+        fs::create_dir_all(dir.join("a")).unwrap();
+        fs::write(dir.join("a/x.py"), "12345").unwrap();
+        let (_, memo) = walk(&dir, &DirMemo::default());
+
+        let file = root.path().join("venv.memo");
+        memo.save_to(&dir, &file).unwrap();
+        let loaded = DirMemo::load(&dir, &file).unwrap();
+        assert_eq!(loaded, memo);
+
+        // the loaded records are used like the ones of the walk
+        fs::write(dir.join("a/x.py"), "1234567890").unwrap();
+        let (size, _) = walk(&dir, &loaded);
+        assert_eq!(size, 5);
+    }
+}
@@ -1,9 +1,19 @@
+pub mod breakdown;
 pub mod chonk;
+pub mod format;
+pub mod hardlink;
 pub mod iterative;
+pub mod memo;
 pub mod parallel;
 pub mod recursive;
+pub mod stealing;
 
+pub use breakdown::{Category, SizeBreakdown};
 pub use chonk::Chonk;
+pub use format::SizeFormat;
+pub use hardlink::{DiskUsage, HardlinkReader, LinkedFile, SizeMode, combine_usage};
 pub use iterative::IterativeReader;
+pub use memo::{DirMemo, dir_ino};
 pub use parallel::ParallelReader;
 pub use recursive::RecursiveReader;
+pub use stealing::{SkippedEntry, StealingReader, WalkReport};
@@ -0,0 +1,339 @@
+use bincode::{Decode, Encode};
+use crossbeam_deque::{Injector, Steal, Stealer, Worker};
+use std::{
+    fs, io, iter,
+    path::{Path, PathBuf},
+    sync::{
+        Condvar, Mutex,
+        atomic::{self, AtomicUsize, Ordering},
+    },
+    thread,
+};
+
+use super::chonk::Chonk;
+
+/// Upper bound on the worker threads, each one keeps at most one directory open.
+const MAX_WORKERS: usize = 16;
+
+/// An entry that couldn't be read and was left out of the size.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct SkippedEntry {
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+impl SkippedEntry {
+    pub fn new(path: PathBuf, error: &io::Error) -> Self {
+        Self {
+            path,
+            reason: error.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct WalkReport {
+    pub size: u64,
+    pub files: u64,
+    pub dirs: u64,
+    pub skipped: Vec<SkippedEntry>,
+}
+
+/// Walks a directory with a fixed number of threads that steal directories from each other.
+///
+/// Directories are read with the file type that comes with the entry, so only files are
+/// stat'ed. Each worker reads one directory to the end before taking the next, which keeps the
+/// number of open file descriptors to the number of workers no matter how deep the tree is.
+#[derive(Debug, Clone, Copy)]
+pub struct StealingReader {
+    pub workers: usize,
+}
+
+impl Default for StealingReader {
+    fn default() -> Self {
+        let workers = thread::available_parallelism().map_or(4, |n| n.get());
+        Self {
+            workers: workers.min(MAX_WORKERS),
+        }
+    }
+}
+
+impl StealingReader {
+    pub fn walk(&self, dir: &Path) -> color_eyre::Result<WalkReport> {
+        let meta = fs::symlink_metadata(dir)?;
+        if !meta.is_dir() {
+            return Ok(WalkReport {
+                size: meta.len(),
+                files: 1,
+                ..Default::default()
+            });
+        }
+
+        let reports = self.visit_dirs(vec![dir.to_path_buf()], read_dir);
+        Ok(reports
+            .into_iter()
+            .fold(WalkReport::default(), |mut acc, report| {
+                acc.size += report.size;
+                acc.files += report.files;
+                acc.dirs += report.dirs;
+                acc.skipped.extend(report.skipped);
+                acc
+            }))
+    }
+
+    /// Calls `visit` once for each of `dirs` and every directory below them. `visit` reads a
+    /// directory into the state of the worker it runs on and returns the subdirectories to
+    /// visit next. The states of the workers are returned once every directory is visited.
+    pub fn visit_dirs<T, F>(&self, dirs: Vec<PathBuf>, visit: F) -> Vec<T>
+    where
+        T: Default + Send,
+        F: Fn(&Path, &mut T) -> Vec<PathBuf> + Sync,
+    {
+        if dirs.is_empty() {
+            return Vec::new();
+        }
+        // directories that are queued or being read
+        let pending = AtomicUsize::new(dirs.len());
+        let injector = Injector::new();
+        for dir in dirs {
+            injector.push(dir);
+        }
+        let idle = Idle::default();
+
+        let workers: Vec<Worker<PathBuf>> = (0..self.workers.max(1))
+            .map(|_| Worker::new_lifo())
+            .collect();
+        let stealers: Vec<Stealer<PathBuf>> = workers.iter().map(Worker::stealer).collect();
+
+        thread::scope(|s| {
+            let handles: Vec<_> = workers
+                .into_iter()
+                .map(|local| {
+                    let walk = Walk {
+                        injector: &injector,
+                        stealers: &stealers,
+                        pending: &pending,
+                        idle: &idle,
+                    };
+                    let visit = &visit;
+                    s.spawn(move || walk.work(local, visit))
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|h| h.join().expect("walker thread panicked"))
+                .collect()
+        })
+    }
+}
+
+impl Chonk for StealingReader {
+    fn get_dir_size(&self, dir: &Path) -> color_eyre::Result<u64> {
+        Ok(self.walk(dir)?.size)
+    }
+}
+
+/// What the workers of a walk share.
+struct Walk<'a> {
+    injector: &'a Injector<PathBuf>,
+    stealers: &'a [Stealer<PathBuf>],
+    pending: &'a AtomicUsize,
+    idle: &'a Idle,
+}
+
+/// Lets workers without anything to steal sleep until new directories are queued or the walk
+/// is over.
+#[derive(Default)]
+struct Idle {
+    sleeping: AtomicUsize,
+    lock: Mutex<()>,
+    wake: Condvar,
+}
+
+impl Idle {
+    fn notify(&self) {
+        // pairs with the fence of a worker going to sleep, either it sees the new work or the
+        // notifier sees it sleeping
+        atomic::fence(Ordering::SeqCst);
+        if self.sleeping.load(Ordering::SeqCst) > 0 {
+            let _guard = self.lock.lock().expect("mutex poisoned");
+            self.wake.notify_all();
+        }
+    }
+}
+
+impl Walk<'_> {
+    fn work<T, F>(&self, local: Worker<PathBuf>, visit: &F) -> T
+    where
+        T: Default,
+        F: Fn(&Path, &mut T) -> Vec<PathBuf>,
+    {
+        let mut state = T::default();
+        while let Some(dir) = self.next_task(&local) {
+            let subdirs = visit(&dir, &mut state);
+            if !subdirs.is_empty() {
+                self.pending.fetch_add(subdirs.len(), Ordering::SeqCst);
+                for subdir in subdirs {
+                    local.push(subdir);
+                }
+                self.idle.notify();
+            }
+            if self.pending.fetch_sub(1, Ordering::SeqCst) == 1 {
+                // the last directory is done, wake everyone up to leave
+                self.idle.notify();
+            }
+        }
+        state
+    }
+
+    /// The next directory to read, `None` once every directory has been read.
+    fn next_task(&self, local: &Worker<PathBuf>) -> Option<PathBuf> {
+        if let Some(dir) = find_task(local, self.injector, self.stealers) {
+            return Some(dir);
+        }
+        let mut guard = self.idle.lock.lock().expect("mutex poisoned");
+        self.idle.sleeping.fetch_add(1, Ordering::SeqCst);
+        atomic::fence(Ordering::SeqCst);
+        let task = loop {
+            // others might still find subdirectories
+            if let Some(dir) = find_task(local, self.injector, self.stealers) {
+                break Some(dir);
+            }
+            if self.pending.load(Ordering::SeqCst) == 0 {
+                break None;
+            }
+            guard = self.idle.wake.wait(guard).expect("mutex poisoned");
+        };
+        self.idle.sleeping.fetch_sub(1, Ordering::SeqCst);
+        task
+    }
+}
+
+fn find_task(
+    local: &Worker<PathBuf>,
+    injector: &Injector<PathBuf>,
+    stealers: &[Stealer<PathBuf>],
+) -> Option<PathBuf> {
+    local.pop().or_else(|| {
+        iter::repeat_with(|| {
+            injector
+                .steal_batch_and_pop(local)
+                .or_else(|| stealers.iter().map(Stealer::steal).collect::<Steal<_>>())
+        })
+        .find(|s| !s.is_retry())
+        .and_then(Steal::success)
+    })
+}
+
+fn read_dir(dir: &Path, report: &mut WalkReport) -> Vec<PathBuf> {
+    let mut subdirs = Vec::new();
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(error) => {
+            report
+                .skipped
+                .push(SkippedEntry::new(dir.to_path_buf(), &error));
+            return subdirs;
+        }
+    };
+    report.dirs += 1;
+
+    for entry in entries {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(error) => {
+                report
+                    .skipped
+                    .push(SkippedEntry::new(dir.to_path_buf(), &error));
+                continue;
+            }
+        };
+        // comes from the directory listing on most platforms, no stat needed
+        match entry.file_type() {
+            Ok(ft) if ft.is_dir() => subdirs.push(entry.path()),
+            // doesn't follow symlinks, same as the file type
+            Ok(_) => match entry.metadata() {
+                Ok(meta) => {
+                    report.size += meta.len();
+                    report.files += 1;
+                }
+                Err(error) => report.skipped.push(SkippedEntry::new(entry.path(), &error)),
+            },
+            Err(error) => report.skipped.push(SkippedEntry::new(entry.path(), &error)),
+        }
+    }
+    subdirs
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::dir_size::ParallelReader;
+
+    #[test]
+    fn test_walk_matches_parallel_reader() -> color_eyre::Result<()> {
+        let root = tempdir()?;
+        for i in 0..20 {
+            let dir = root.path().join(format!("pkg{i}/sub/deeper"));
+            fs::create_dir_all(&dir)?;
+            fs::write(dir.join("mod.py"), "x".repeat(i))?;
+            fs::write(root.path().join(format!("pkg{i}/__init__.py")), "init")?;
+        }
+
+        let reader = StealingReader { workers: 4 };
+        let report = reader.walk(root.path())?;
+        assert_eq!(report.size, ParallelReader.get_dir_size(root.path())?);
+        assert_eq!(report.files, 40);
+        assert_eq!(report.dirs, 61);
+        assert!(report.skipped.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_idle_workers_wait_for_deep_dirs() -> color_eyre::Result<()> {
+        // a single chain leaves every worker but one without work most of the time
+        let root = tempdir()?;
+        let deepest = (0..50).fold(root.path().to_path_buf(), |dir, i| dir.join(i.to_string()));
+        fs::create_dir_all(&deepest)?;
+
+        let reader = StealingReader { workers: 8 };
+        let visited: usize = reader
+            .visit_dirs(vec![root.path().to_path_buf()], |dir, count: &mut usize| {
+                *count += 1;
+                read_dir(dir, &mut WalkReport::default())
+            })
+            .into_iter()
+            .sum();
+        assert_eq!(visited, 51);
+        assert!(reader.visit_dirs(Vec::new(), read_dir).is_empty());
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unreadable_dir_is_reported() -> color_eyre::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = tempdir()?;
+        let locked = root.path().join("locked");
+        fs::create_dir(&locked)?;
+        fs::write(locked.join("secret.py"), "1234")?;
+        fs::write(root.path().join("open.py"), "12")?;
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o000))?;
+        // root can read anything, there's nothing to skip then
+        if fs::read_dir(&locked).is_ok() {
+            return Ok(());
+        }
+
+        let report = StealingReader::default().walk(root.path())?;
+        assert_eq!(report.size, 2);
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(report.skipped[0].path, locked);
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o755))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,312 @@
+//! The report `pip inspect` prints, generated without running pip or the interpreter.
+
+use std::{fs, path::Path};
+
+use serde_json::{Map, Value, json};
+
+use crate::venv::{Venv, model::Package, utils::canonical_name};
+
+/// `METADATA` fields that may appear more than once and are lists in the JSON form.
+const MULTIPLE_USE: [&str; 11] = [
+    "Classifier",
+    "Dynamic",
+    "License-File",
+    "Obsoletes-Dist",
+    "Platform",
+    "Project-URL",
+    "Provides-Dist",
+    "Provides-Extra",
+    "Requires-Dist",
+    "Requires-External",
+    "Supported-Platform",
+];
+
+/// A `pip inspect` report, version 1 of its format. The environment markers come from
+/// `pyvenv.cfg` and the platform venv-rs runs on, since the interpreter isn't run.
+pub fn pip_inspect(venv: &Venv) -> Value {
+    let pip_version = venv
+        .packages
+        .iter()
+        .find(|p| canonical_name(&p.name) == "pip")
+        .map_or("", |p| p.version.as_str());
+    let installed: Vec<Value> = venv.packages.iter().map(installed_entry).collect();
+    json!({
+        "version": "1",
+        "pip_version": pip_version,
+        "installed": installed,
+        "environment": environment(venv),
+    })
+}
+
+fn installed_entry(pkg: &Package) -> Value {
+    let mut entry = Map::new();
+    let metadata = fs::read_to_string(pkg.dist_info.join("METADATA"))
+        .map(|m| metadata_json(&m))
+        .unwrap_or_else(|_| Value::Object(Map::new()));
+    entry.insert("metadata".to_string(), metadata);
+    entry.insert(
+        "metadata_location".to_string(),
+        json!(pkg.dist_info.to_string_lossy()),
+    );
+    if let Some(direct_url) = fs::read_to_string(pkg.dist_info.join("direct_url.json"))
+        .ok()
+        .and_then(|d| serde_json::from_str::<Value>(&d).ok())
+    {
+        entry.insert("direct_url".to_string(), direct_url);
+    }
+    if let Ok(installer) = fs::read_to_string(pkg.dist_info.join("INSTALLER"))
+        && !installer.trim().is_empty()
+    {
+        entry.insert("installer".to_string(), json!(installer.trim()));
+    }
+    entry.insert(
+        "requested".to_string(),
+        json!(pkg.dist_info.join("REQUESTED").exists()),
+    );
+    Value::Object(entry)
+}
+
+/// `METADATA` in the JSON form of the core metadata spec: keys lowercased with `_`, multiple
+/// use fields as lists, `keywords` split and the body as `description`.
+pub fn metadata_json(contents: &str) -> Value {
+    let contents = contents.replace("\r\n", "\n");
+    let (headers, body) = contents.split_once("\n\n").unwrap_or((&contents, ""));
+
+    let mut fields: Vec<(String, String)> = Vec::new();
+    for line in headers.lines() {
+        // folded lines continue the previous field
+        if line.starts_with([' ', '\t'])
+            && let Some((_, value)) = fields.last_mut()
+        {
+            value.push('\n');
+            value.push_str(line.trim_start_matches([' ', '\t']).trim_start_matches('|'));
+            continue;
+        }
+        if let Some((key, value)) = line.split_once(':') {
+            fields.push((key.trim().to_string(), value.trim().to_string()));
+        }
+    }
+
+    let mut json = Map::new();
+    for (key, value) in fields {
+        let name = key.to_ascii_lowercase().replace('-', "_");
+        if MULTIPLE_USE.iter().any(|m| m.eq_ignore_ascii_case(&key)) {
+            json.entry(name)
+                .or_insert_with(|| Value::Array(Vec::new()))
+                .as_array_mut()
+                .expect("multiple use fields are arrays")
+                .push(json!(value));
+        } else if name == "keywords" {
+            let keywords: Vec<&str> = if value.contains(',') {
+                value.split(',').map(str::trim).collect()
+            } else {
+                value.split_whitespace().collect()
+            };
+            json.insert(name, json!(keywords));
+        } else {
+            json.insert(name, json!(value));
+        }
+    }
+    if !body.trim().is_empty() {
+        json.insert("description".to_string(), json!(body));
+    }
+    Value::Object(json)
+}
+
+/// PEP 508 environment markers of the venv.
+fn environment(venv: &Venv) -> Value {
+    let implementation = cfg_value(&venv.pyvenv_cfg, "implementation").unwrap_or("CPython");
+    let python_version = venv
+        .version
+        .split('.')
+        .take(2)
+        .collect::<Vec<_>>()
+        .join(".");
+    let (os_name, sys_platform, platform_system) = if cfg!(windows) {
+        ("nt", "win32", "Windows")
+    } else if cfg!(target_os = "macos") {
+        ("posix", "darwin", "Darwin")
+    } else {
+        ("posix", std::env::consts::OS, "Linux")
+    };
+    let machine = match (
+        std::env::consts::ARCH,
+        cfg!(windows),
+        cfg!(target_os = "macos"),
+    ) {
+        ("x86_64", true, _) => "AMD64",
+        ("aarch64", true, _) => "ARM64",
+        ("aarch64", _, true) => "arm64",
+        (arch, _, _) => arch,
+    };
+    // what `platform.release()` and `platform.version()` read from uname on linux
+    let kernel = |name: &str| {
+        fs::read_to_string(Path::new("/proc/sys/kernel").join(name)).unwrap_or_default()
+    };
+    json!({
+        "implementation_name": implementation.to_ascii_lowercase(),
+        "implementation_version": venv.version,
+        "os_name": os_name,
+        "platform_machine": machine,
+        "platform_release": kernel("osrelease").trim(),
+        "platform_system": platform_system,
+        "platform_version": kernel("version").trim(),
+        "python_full_version": venv.version,
+        "platform_python_implementation": implementation,
+        "python_version": python_version,
+        "sys_platform": sys_platform,
+    })
+}
+
+/// Value of `key` in `pyvenv.cfg`.
+fn cfg_value<'a>(cfg: &'a str, key: &str) -> Option<&'a str> {
+    cfg.lines().find_map(|line| {
+        let (k, v) = line.split_once('=')?;
+        (k.trim() == key).then(|| v.trim())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::{
+        core::VenvManager,
+        venv::test_utils::{mock_package, mock_venv},
+    };
+
+    #[test]
+    fn test_pip_inspect() {
+        let root = tempdir().unwrap();
+        let site_packages = mock_venv(root.path());
+        let pip = mock_package(&site_packages, "pip", "24.0", &[("pip/__init__.py", "")]);
+        let alpha = mock_package(&site_packages, "alpha", "1.0", &[("alpha/__init__.py", "")]);
+        fs::write(
+            alpha.join("METADATA"),
+            "Metadata-Version: 2.1\nName: alpha\nVersion: 1.0\nKeywords: a,b\n\
+             License: MIT\n        line two\nRequires-Dist: beta\nRequires-Dist: gamma; extra == \"x\"\n\
+             \nLong description\n",
+        )
+        .unwrap();
+        fs::write(alpha.join("INSTALLER"), "uv\n").unwrap();
+        fs::write(alpha.join("REQUESTED"), "").unwrap();
+        fs::write(
+            alpha.join("direct_url.json"),
+            r#"{"url": "file:///src/alpha", "dir_info": {"editable": true}}"#,
+        )
+        .unwrap();
+
+        let venv = Venv::from_path(root.path()).unwrap();
+        let report = pip_inspect(&venv);
+        assert_eq!(report["version"], "1");
+        assert_eq!(report["pip_version"], "24.0");
+
+        let installed = report["installed"].as_array().unwrap();
+        let entry = |name: &str| {
+            installed
+                .iter()
+                .find(|e| e["metadata"]["name"] == name)
+                .unwrap()
+        };
+        let alpha_entry = entry("alpha");
+        let metadata = &alpha_entry["metadata"];
+        assert_eq!(metadata["metadata_version"], "2.1");
+        assert_eq!(metadata["keywords"], json!(["a", "b"]));
+        assert_eq!(metadata["license"], "MIT\nline two");
+        assert_eq!(
+            metadata["requires_dist"],
+            json!(["beta", "gamma; extra == \"x\""])
+        );
+        assert_eq!(metadata["description"], "Long description\n");
+        assert_eq!(alpha_entry["installer"], "uv");
+        assert_eq!(alpha_entry["requested"], true);
+        assert_eq!(alpha_entry["direct_url"]["dir_info"]["editable"], true);
+        assert_eq!(
+            alpha_entry["metadata_location"],
+            alpha.to_string_lossy().as_ref()
+        );
+
+        let pip_entry = entry("pip");
+        assert_eq!(pip_entry["requested"], false);
+        assert!(pip_entry.get("installer").is_none());
+        assert_eq!(
+            pip_entry["metadata_location"],
+            pip.to_string_lossy().as_ref()
+        );
+    }
+
+    #[test]
+    fn test_direct_url_and_requested() {
+        let root = tempdir().unwrap();
+        let site_packages = mock_venv(root.path());
+        let vcs = mock_package(&site_packages, "vcs", "1.0", &[]);
+        fs::write(
+            vcs.join("direct_url.json"),
+            r#"{"url": "https://github.com/o/vcs", "vcs_info": {"vcs": "git", "commit_id": "abc123"}}"#,
+        )
+        .unwrap();
+        fs::write(vcs.join("REQUESTED"), "").unwrap();
+        let archive = mock_package(&site_packages, "archive", "2.0", &[]);
+        fs::write(
+            archive.join("direct_url.json"),
+            r#"{"url": "https://example.com/archive-2.0.tar.gz", "archive_info": {"hashes": {"sha256": "ff"}}}"#,
+        )
+        .unwrap();
+        let broken = mock_package(&site_packages, "broken", "3.0", &[]);
+        fs::write(broken.join("direct_url.json"), "{not json").unwrap();
+        mock_package(&site_packages, "plain", "4.0", &[]);
+
+        let report = pip_inspect(&Venv::from_path(root.path()).unwrap());
+        let installed = report["installed"].as_array().unwrap();
+        let entry = |name: &str| {
+            installed
+                .iter()
+                .find(|e| e["metadata"]["name"] == name)
+                .unwrap()
+        };
+        assert_eq!(
+            entry("vcs")["direct_url"],
+            json!({
+                "url": "https://github.com/o/vcs",
+                "vcs_info": { "vcs": "git", "commit_id": "abc123" },
+            })
+        );
+        assert_eq!(entry("vcs")["requested"], true);
+        assert_eq!(
+            entry("archive")["direct_url"]["archive_info"]["hashes"]["sha256"],
+            "ff"
+        );
+        assert_eq!(entry("archive")["requested"], false);
+        // pip leaves out a direct_url.json it can't read, as it does a missing one
+        for name in ["broken", "plain"] {
+            assert!(entry(name).get("direct_url").is_none(), "{name}");
+            assert_eq!(entry(name)["requested"], false);
+        }
+    }
+
+    #[test]
+    fn test_report_lists_packages_installed_after_caching() {
+        let cache_dir = tempdir().unwrap();
+        let root = tempdir().unwrap();
+        let site_packages = mock_venv(root.path());
+        mock_package(&site_packages, "alpha", "1.0", &[]);
+        mock_package(&site_packages, "beta", "1.0", &[]);
+        let mut vm = VenvManager::with_cache_path(cache_dir.path().to_path_buf());
+        vm.get(root.path()).unwrap();
+        vm.save_cache().unwrap();
+
+        mock_package(&site_packages, "gamma", "1.0", &[]);
+        let mut vm = VenvManager::with_cache_path(cache_dir.path().to_path_buf());
+        vm.load_cache().unwrap();
+        let report = pip_inspect(&vm.get_fresh(root.path()).unwrap());
+        let mut names: Vec<&str> = report["installed"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| e["metadata"]["name"].as_str().unwrap())
+            .collect();
+        names.sort_unstable();
+        assert_eq!(names, ["alpha", "beta", "gamma"]);
+    }
+}
@@ -0,0 +1,29 @@
+//! Machine readable descriptions of a venv for other tools.
+
+pub mod inspect;
+pub mod sbom;
+
+use clap::ValueEnum;
+use serde_json::Value;
+
+use crate::venv::Venv;
+
+/// Formats the `export` command writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    /// CycloneDX 1.5 JSON
+    Cyclonedx,
+    /// SPDX 2.3 JSON
+    Spdx,
+    /// The JSON report of `pip inspect`
+    PipInspect,
+}
+
+/// The document describing `venv` in `format`.
+pub fn export(venv: &Venv, format: ExportFormat) -> Value {
+    match format {
+        ExportFormat::Cyclonedx => sbom::cyclonedx(venv),
+        ExportFormat::Spdx => sbom::spdx(venv),
+        ExportFormat::PipInspect => inspect::pip_inspect(venv),
+    }
+}
@@ -0,0 +1,629 @@
+//! Software bills of materials in CycloneDX and SPDX JSON.
+
+use std::{collections::HashMap, path::Path};
+
+use chrono::{SecondsFormat, Utc};
+use serde_json::{Value, json};
+
+use crate::{
+    analysis::{License, license_of},
+    venv::{
+        Venv,
+        conflicts::lexical_join,
+        model::Package,
+        record::{digest, read_record},
+        utils::canonical_name,
+    },
+};
+
+const TOOL_NAME: &str = "venv-rs";
+const TOOL_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// A package with what both formats need from it.
+struct Component<'a> {
+    pkg: &'a Package,
+    purl: String,
+    license: License,
+    /// Paths relative to the venv with their `RECORD` hashes as (algorithm, hex digest)
+    files: Vec<(String, String, String)>,
+    /// Indices of the installed packages it requires
+    depends_on: Vec<usize>,
+}
+
+/// The package URL of a PyPI distribution, `pkg:pypi/name@version`.
+pub fn purl(name: &str, version: &str) -> String {
+    format!(
+        "pkg:pypi/{}@{}",
+        canonical_name(name),
+        version.replace('+', "%2B")
+    )
+}
+
+/// Reads the `RECORD` of every package and resolves its `Requires-Dist` to the installed
+/// packages. Requirements that aren't installed, like unused extras, are left out.
+fn components(venv: &Venv) -> Vec<Component<'_>> {
+    let index: HashMap<String, usize> = venv
+        .packages
+        .iter()
+        .enumerate()
+        .map(|(i, pkg)| (canonical_name(&pkg.name), i))
+        .collect();
+
+    let site_packages = venv.site_packages().unwrap_or_default();
+    venv.packages
+        .iter()
+        .map(|pkg| {
+            let files = read_record(&pkg.dist_info)
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|entry| {
+                    let hash = entry.hash?;
+                    let hex = hash.hex()?;
+                    // scripts climb out of site-packages with `..`
+                    let path = lexical_join(&site_packages, Path::new(&entry.path));
+                    let path = path.strip_prefix(&venv.path).unwrap_or(&path);
+                    let path = path.to_string_lossy().replace('\\', "/");
+                    Some((path, hash.algorithm, hex))
+                })
+                .collect();
+            let mut depends_on: Vec<usize> = pkg
+                .metadata
+                .dependencies
+                .iter()
+                .flatten()
+                .filter_map(|dep| index.get(&canonical_name(dep)).copied())
+                .collect();
+            depends_on.sort_unstable();
+            depends_on.dedup();
+            Component {
+                pkg,
+                purl: purl(&pkg.name, &pkg.version),
+                license: license_of(&pkg.metadata),
+                files,
+                depends_on,
+            }
+        })
+        .collect()
+}
+
+fn now() -> String {
+    Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// CycloneDX names of the `RECORD` hash algorithms.
+fn cyclonedx_alg(algorithm: &str) -> Option<&'static str> {
+    match algorithm {
+        "sha256" => Some("SHA-256"),
+        "sha384" => Some("SHA-384"),
+        "sha512" => Some("SHA-512"),
+        _ => None,
+    }
+}
+
+fn cyclonedx_license(license: &License) -> Value {
+    match license {
+        License::Spdx(expression) => json!([{ "expression": expression }]),
+        License::Named(name) => json!([{ "license": { "name": name } }]),
+        License::Unknown => json!([]),
+    }
+}
+
+/// A CycloneDX 1.5 BOM with the venv as the described application, a library component per
+/// package holding its files, and the `Requires-Dist` relations as dependencies.
+pub fn cyclonedx(venv: &Venv) -> Value {
+    let components = components(venv);
+
+    let library_components: Vec<Value> = components
+        .iter()
+        .map(|c| {
+            let files: Vec<Value> = c
+                .files
+                .iter()
+                .filter_map(|(path, algorithm, hex)| {
+                    Some(json!({
+                        "type": "file",
+                        "name": path,
+                        "hashes": [{ "alg": cyclonedx_alg(algorithm)?, "content": hex }],
+                    }))
+                })
+                .collect();
+            json!({
+                "type": "library",
+                "bom-ref": c.purl,
+                "name": c.pkg.name,
+                "version": c.pkg.version,
+                "description": c.pkg.metadata.summary,
+                "purl": c.purl,
+                "licenses": cyclonedx_license(&c.license),
+                "components": files,
+            })
+        })
+        .collect();
+
+    let mut dependencies = vec![json!({
+        "ref": venv.name,
+        "dependsOn": components.iter().map(|c| &c.purl).collect::<Vec<_>>(),
+    })];
+    dependencies.extend(components.iter().map(|c| {
+        json!({
+            "ref": c.purl,
+            "dependsOn": c.depends_on.iter().map(|&i| &components[i].purl).collect::<Vec<_>>(),
+        })
+    }));
+
+    json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": "1.5",
+        "version": 1,
+        "metadata": {
+            "timestamp": now(),
+            "tools": {
+                "components": [{ "type": "application", "name": TOOL_NAME, "version": TOOL_VERSION }],
+            },
+            "component": {
+                "type": "application",
+                "bom-ref": venv.name,
+                "name": venv.name,
+                "version": venv.version,
+            },
+        },
+        "components": library_components,
+        "dependencies": dependencies,
+    })
+}
+
+/// SPDX ids may only hold letters, digits, `.` and `-`.
+fn id_safe(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+fn spdx_id(kind: &str, index: usize, name: &str) -> String {
+    format!("SPDXRef-{kind}-{index}-{}", id_safe(name))
+}
+
+/// An SPDX 2.3 document with a package per distribution and `DEPENDS_ON` relationships from
+/// `Requires-Dist`. SPDX wants a SHA-1 for every file, which `RECORD` doesn't have, so the
+/// files are left out and the packages are marked as not analyzed. Licenses without an SPDX
+/// id are declared as `LicenseRef-` licenses with the name as their text.
+pub fn spdx(venv: &Venv) -> Value {
+    let components = components(venv);
+    let created = now();
+    let mut license_refs: Vec<(String, &str)> = Vec::new();
+
+    let package_ids: Vec<String> = components
+        .iter()
+        .enumerate()
+        .map(|(i, c)| spdx_id("Package", i, &c.pkg.name))
+        .collect();
+
+    let mut packages = Vec::new();
+    let mut relationships = vec![json!({
+        "spdxElementId": "SPDXRef-DOCUMENT",
+        "relationshipType": "DESCRIBES",
+        "relatedSpdxElement": "SPDXRef-Venv",
+    })];
+    packages.push(json!({
+        "SPDXID": "SPDXRef-Venv",
+        "name": venv.name,
+        "versionInfo": venv.version,
+        "downloadLocation": "NOASSERTION",
+        "filesAnalyzed": false,
+        "primaryPackagePurpose": "APPLICATION",
+    }));
+
+    for (i, c) in components.iter().enumerate() {
+        let license = match &c.license {
+            License::Spdx(expression) => expression.clone(),
+            License::Named(name) => match license_refs.iter().find(|(_, n)| n == name) {
+                Some((id, _)) => id.clone(),
+                None => {
+                    let id = format!("LicenseRef-{}-{}", license_refs.len(), id_safe(name));
+                    license_refs.push((id.clone(), name));
+                    id
+                }
+            },
+            License::Unknown => "NOASSERTION".to_string(),
+        };
+        packages.push(json!({
+            "SPDXID": package_ids[i],
+            "name": c.pkg.name,
+            "versionInfo": c.pkg.version,
+            "downloadLocation": "NOASSERTION",
+            "filesAnalyzed": false,
+            "licenseConcluded": "NOASSERTION",
+            "licenseDeclared": license,
+            "copyrightText": "NOASSERTION",
+            "summary": c.pkg.metadata.summary,
+            "primaryPackagePurpose": "LIBRARY",
+            "externalRefs": [{
+                "referenceCategory": "PACKAGE-MANAGER",
+                "referenceType": "purl",
+                "referenceLocator": c.purl,
+            }],
+        }));
+
+        relationships.push(json!({
+            "spdxElementId": "SPDXRef-Venv",
+            "relationshipType": "CONTAINS",
+            "relatedSpdxElement": package_ids[i],
+        }));
+        for &dep in &c.depends_on {
+            relationships.push(json!({
+                "spdxElementId": package_ids[i],
+                "relationshipType": "DEPENDS_ON",
+                "relatedSpdxElement": package_ids[dep],
+            }));
+        }
+    }
+    let extracted: Vec<Value> = license_refs
+        .iter()
+        .map(|(id, name)| json!({ "licenseId": id, "name": name, "extractedText": name }))
+        .collect();
+
+    // unique per venv and run, as the namespace has to be
+    let namespace = digest(format!("{}{}", venv.path.display(), created).as_bytes());
+    json!({
+        "spdxVersion": "SPDX-2.3",
+        "dataLicense": "CC0-1.0",
+        "SPDXID": "SPDXRef-DOCUMENT",
+        "name": venv.name,
+        "documentNamespace": format!("https://spdx.org/spdxdocs/{TOOL_NAME}/{}-{namespace}", venv.name),
+        "creationInfo": {
+            "created": created,
+            "creators": [format!("Tool: {TOOL_NAME}-{TOOL_VERSION}")],
+        },
+        "packages": packages,
+        "hasExtractedLicensingInfos": extracted,
+        "relationships": relationships,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::fs::OpenOptions;
+    use std::io::Write;
+
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::venv::test_utils::{mock_package, mock_venv};
+
+    #[test]
+    fn test_sbom() {
+        let root = tempdir().unwrap();
+        let site_packages = mock_venv(root.path());
+        let alpha = mock_package(
+            &site_packages,
+            "Alpha_Pkg",
+            "1.0+cpu",
+            &[("alpha/__init__.py", "a = 1")],
+        );
+        let beta = mock_package(&site_packages, "beta", "2.0", &[("beta/__init__.py", "")]);
+        mock_package(&site_packages, "gamma", "3.0", &[("gamma.py", "")]);
+        let mut metadata = OpenOptions::new()
+            .append(true)
+            .open(beta.join("METADATA"))
+            .unwrap();
+        writeln!(metadata, "License: BSD").unwrap();
+        let mut metadata = OpenOptions::new()
+            .append(true)
+            .open(alpha.join("METADATA"))
+            .unwrap();
+        writeln!(
+            metadata,
+            "License-Expression: MIT\nRequires-Dist: beta>=2\nRequires-Dist: delta"
+        )
+        .unwrap();
+
+        let venv = Venv::from_path(root.path()).unwrap();
+        let alpha_purl = "pkg:pypi/alpha-pkg@1.0%2Bcpu";
+
+        let bom = cyclonedx(&venv);
+        let alpha = bom["components"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|c| c["name"] == "Alpha_Pkg")
+            .unwrap();
+        assert_eq!(alpha["purl"], alpha_purl);
+        assert_eq!(alpha["licenses"][0]["expression"], "MIT");
+        let site_packages_rel = site_packages.strip_prefix(root.path()).unwrap();
+        assert_eq!(
+            alpha["components"][0]["name"],
+            site_packages_rel
+                .join("alpha/__init__.py")
+                .to_str()
+                .unwrap()
+        );
+        assert_eq!(alpha["components"][0]["hashes"][0]["alg"], "SHA-256");
+        assert_eq!(
+            alpha["components"][0]["hashes"][0]["content"]
+                .as_str()
+                .unwrap()
+                .len(),
+            64
+        );
+        let alpha_deps = bom["dependencies"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|d| d["ref"] == alpha_purl)
+            .unwrap();
+        assert_eq!(alpha_deps["dependsOn"], json!(["pkg:pypi/beta@2.0"]));
+        let licenses = |name: &str| {
+            bom["components"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|c| c["name"] == name)
+                .unwrap()["licenses"]
+                .clone()
+        };
+        assert_eq!(licenses("beta"), json!([{ "license": { "name": "BSD" } }]));
+        assert_eq!(licenses("gamma"), json!([]));
+
+        let doc = spdx(&venv);
+        validate_spdx(&doc);
+        let declared = |name: &str| {
+            doc["packages"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|p| p["name"] == name)
+                .unwrap()["licenseDeclared"]
+                .clone()
+        };
+        assert_eq!(declared("Alpha_Pkg"), "MIT");
+        assert_eq!(declared("beta"), "LicenseRef-0-BSD");
+        assert_eq!(declared("gamma"), "NOASSERTION");
+        assert_eq!(
+            doc["hasExtractedLicensingInfos"],
+            json!([{ "licenseId": "LicenseRef-0-BSD", "name": "BSD", "extractedText": "BSD" }])
+        );
+        let alpha_id = doc["packages"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|p| p["name"] == "Alpha_Pkg")
+            .unwrap()["SPDXID"]
+            .clone();
+        let depends_on: Vec<&Value> = doc["relationships"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|r| r["relationshipType"] == "DEPENDS_ON")
+            .collect();
+        assert_eq!(depends_on.len(), 1);
+        assert_eq!(depends_on[0]["spdxElementId"], alpha_id);
+        assert!(doc.get("files").is_none());
+    }
+
+    fn append_metadata(dist_info: &Path, lines: &str) {
+        let mut metadata = OpenOptions::new()
+            .append(true)
+            .open(dist_info.join("METADATA"))
+            .unwrap();
+        writeln!(metadata, "{lines}").unwrap();
+    }
+
+    /// A venv where `app` requires `Lib.Core` by a different spelling and `lib-core` requires
+    /// `app` back, with free text licenses two packages share.
+    fn linked_venv(root: &Path) -> Venv {
+        let site_packages = mock_venv(root);
+        let app = mock_package(&site_packages, "app", "1.0", &[("app.py", "")]);
+        append_metadata(
+            &app,
+            "License: Acme Commercial\nRequires-Dist: Lib.Core[fast]>=1; python_version > \"3\"",
+        );
+        let lib = mock_package(&site_packages, "lib_core", "2.0", &[("lib_core.py", "")]);
+        append_metadata(&lib, "License: Acme Commercial\nRequires-Dist: app");
+        let other = mock_package(&site_packages, "other", "3.0", &[("other.py", "")]);
+        append_metadata(
+            &other,
+            "Classifier: License :: OSI Approved :: MIT License\n\
+             Classifier: License :: OSI Approved :: BSD License",
+        );
+        Venv::from_path(root).unwrap()
+    }
+
+    #[test]
+    fn test_cyclonedx_refs_resolve() {
+        let root = tempdir().unwrap();
+        let venv = linked_venv(root.path());
+        let bom = cyclonedx(&venv);
+
+        let mut refs: HashSet<&str> = bom["components"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|c| c["bom-ref"].as_str().unwrap())
+            .collect();
+        assert_eq!(refs.len(), 3);
+        refs.insert(bom["metadata"]["component"]["bom-ref"].as_str().unwrap());
+
+        let dependencies = bom["dependencies"].as_array().unwrap();
+        let described: HashSet<&str> = dependencies
+            .iter()
+            .map(|d| d["ref"].as_str().unwrap())
+            .collect();
+        assert_eq!(described, refs);
+        for dependency in dependencies {
+            for target in dependency["dependsOn"].as_array().unwrap() {
+                assert!(refs.contains(target.as_str().unwrap()), "{target}");
+            }
+        }
+        let depends_on = |purl: &str| {
+            dependencies.iter().find(|d| d["ref"] == purl).unwrap()["dependsOn"].clone()
+        };
+        assert_eq!(
+            depends_on("pkg:pypi/app@1.0"),
+            json!(["pkg:pypi/lib-core@2.0"])
+        );
+        assert_eq!(
+            depends_on("pkg:pypi/lib-core@2.0"),
+            json!(["pkg:pypi/app@1.0"])
+        );
+        assert_eq!(depends_on("pkg:pypi/other@3.0"), json!([]));
+    }
+
+    #[test]
+    fn test_spdx_relationships_and_license_refs() {
+        let root = tempdir().unwrap();
+        let venv = linked_venv(root.path());
+        let doc = spdx(&venv);
+        validate_spdx(&doc);
+
+        let packages = doc["packages"].as_array().unwrap();
+        let id_of = |name: &str| {
+            packages.iter().find(|p| p["name"] == name).unwrap()["SPDXID"]
+                .as_str()
+                .unwrap()
+                .to_string()
+        };
+        let declared = |name: &str| {
+            packages.iter().find(|p| p["name"] == name).unwrap()["licenseDeclared"].clone()
+        };
+        // one declaration for the license both share
+        assert_eq!(declared("app"), declared("lib_core"));
+        assert_eq!(declared("other"), "LicenseRef-1-MIT-OR-BSD-License");
+        let extracted = doc["hasExtractedLicensingInfos"].as_array().unwrap();
+        assert_eq!(extracted.len(), 2);
+        assert_eq!(extracted[0]["licenseId"], declared("app"));
+        assert_eq!(extracted[0]["extractedText"], "Acme Commercial");
+
+        let relationships: HashSet<(String, &str, String)> = doc["relationships"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| {
+                (
+                    r["spdxElementId"].as_str().unwrap().to_string(),
+                    r["relationshipType"].as_str().unwrap(),
+                    r["relatedSpdxElement"].as_str().unwrap().to_string(),
+                )
+            })
+            .collect();
+        let venv_id = "SPDXRef-Venv".to_string();
+        let mut expected: HashSet<(String, &str, String)> = [
+            ("SPDXRef-DOCUMENT".to_string(), "DESCRIBES", venv_id.clone()),
+            (id_of("app"), "DEPENDS_ON", id_of("lib_core")),
+            (id_of("lib_core"), "DEPENDS_ON", id_of("app")),
+        ]
+        .into_iter()
+        .collect();
+        for name in ["app", "lib_core", "other"] {
+            expected.insert((venv_id.clone(), "CONTAINS", id_of(name)));
+        }
+        assert_eq!(relationships, expected);
+    }
+
+    /// Checks the SPDX 2.3 rules the document can break: ids, analyzed packages and their
+    /// files, license expressions and the `LicenseRef-` licenses they use, and relationships.
+    fn validate_spdx(doc: &Value) {
+        assert_eq!(doc["spdxVersion"], "SPDX-2.3");
+        assert_eq!(doc["dataLicense"], "CC0-1.0");
+        assert_eq!(doc["SPDXID"], "SPDXRef-DOCUMENT");
+        assert!(
+            doc["documentNamespace"]
+                .as_str()
+                .unwrap()
+                .starts_with("https://")
+        );
+        assert!(
+            !doc["creationInfo"]["creators"]
+                .as_array()
+                .unwrap()
+                .is_empty()
+        );
+
+        let is_id = |id: &str, prefix: &str| {
+            id.strip_prefix(prefix).is_some_and(|rest| {
+                !rest.is_empty()
+                    && rest
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-')
+            })
+        };
+        let packages = doc["packages"].as_array().unwrap();
+        let files = doc["files"].as_array().map_or(&[][..], |f| f.as_slice());
+        let mut ids: Vec<&str> = vec!["SPDXRef-DOCUMENT"];
+        ids.extend(
+            packages
+                .iter()
+                .chain(files)
+                .map(|e| e["SPDXID"].as_str().unwrap()),
+        );
+        for id in &ids {
+            assert!(is_id(id, "SPDXRef-"), "{id}");
+        }
+        let unique: HashSet<&str> = ids.iter().copied().collect();
+        assert_eq!(unique.len(), ids.len());
+
+        for file in files {
+            let checksums = file["checksums"].as_array().unwrap();
+            assert!(checksums.iter().any(|c| c["algorithm"] == "SHA1"));
+        }
+        let file_ids: HashSet<&str> = files
+            .iter()
+            .map(|f| f["SPDXID"].as_str().unwrap())
+            .collect();
+        let relationships = doc["relationships"].as_array().unwrap();
+
+        let extracted: HashSet<&str> = doc["hasExtractedLicensingInfos"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|l| {
+                assert!(l["extractedText"].is_string());
+                l["licenseId"].as_str().unwrap()
+            })
+            .collect();
+        for package in packages {
+            let id = package["SPDXID"].as_str().unwrap();
+            assert!(package["name"].is_string() && package["downloadLocation"].is_string());
+            // filesAnalyzed defaults to true
+            if package["filesAnalyzed"].as_bool().unwrap_or(true) {
+                assert!(package["packageVerificationCode"].is_object(), "{id}");
+            } else {
+                assert!(package.get("packageVerificationCode").is_none(), "{id}");
+                assert!(package.get("hasFiles").is_none(), "{id}");
+                assert!(
+                    !relationships.iter().any(|r| r["spdxElementId"] == id
+                        && file_ids.contains(r["relatedSpdxElement"].as_str().unwrap())),
+                    "{id}"
+                );
+            }
+            for field in ["licenseDeclared", "licenseConcluded"] {
+                let Some(license) = package[field].as_str() else {
+                    continue;
+                };
+                if license == "NOASSERTION" || license == "NONE" {
+                    continue;
+                }
+                assert!(spdx::Expression::parse(license).is_ok(), "{license}");
+                for license_ref in license
+                    .split(|c: char| c.is_whitespace() || c == '(' || c == ')')
+                    .filter(|t| t.starts_with("LicenseRef-"))
+                {
+                    assert!(is_id(license_ref, "LicenseRef-"), "{license_ref}");
+                    assert!(extracted.contains(license_ref), "{license_ref}");
+                }
+            }
+        }
+
+        for r in relationships {
+            for end in ["spdxElementId", "relatedSpdxElement"] {
+                assert!(unique.contains(r[end].as_str().unwrap()), "{r}");
+            }
+        }
+    }
+}
@@ -1,7 +1,9 @@
+pub mod analysis;
 pub mod commands;
 pub mod config;
 pub mod core;
 pub mod dir_size;
+pub mod export;
 pub mod platform;
 pub mod shell;
 pub mod tui;
@@ -31,7 +31,7 @@ fn main() -> color_eyre::Result<()> {
 
     // TODO: config to run the TUI in stderr to allow pipes and stuff
     let terminal = ratatui::init();
-    let app = App::new(vm);
+    let app = App::new(vm, &config);
     let result = app.run(terminal);
     ratatui::restore();
 
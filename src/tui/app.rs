@@ -1,19 +1,30 @@
 use std::{
-    path::PathBuf,
+    collections::{BTreeSet, HashMap},
+    path::{Path, PathBuf},
     process::Command,
     sync::{
         Arc, RwLock,
+        atomic::{AtomicBool, Ordering},
         mpsc::{self, Receiver},
     },
-    thread::JoinHandle,
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
 };
 
 use crate::{
-    core::VenvManager,
+    analysis::{
+        AdvisoryDb, Drift, DuplicateGroup, StartupHook, VenvDiff, check_drift, diff_venvs,
+        find_duplicates, find_lock_file, find_startup_hooks,
+    },
+    config::Settings,
+    core::{VenvManager, VenvWatcher},
+    dir_size::{Category, DiskUsage, SizeFormat, SizeMode},
     tui::{AppEvent, Event, EventHandler, SyncMsg},
     venv::{
-        VenvListUi,
+        Venv, VenvListUi,
+        bytecode::BytecodeAction,
         model::{Package, VenvUi},
+        utils::canonical_name,
     },
 };
 use color_eyre::eyre;
@@ -21,14 +32,38 @@ use crossterm::event::KeyEventKind;
 use ratatui::{
     DefaultTerminal,
     crossterm::event::{KeyCode, KeyEvent, KeyModifiers},
+    widgets::TableState,
 };
 
+/// How long the watched venvs have to be quiet before they're refreshed
+const WATCH_DEBOUNCE: Duration = Duration::from_secs(1);
+
+/// Name of the venv at `path` as the venv list shows it.
+pub fn venv_display_name(path: &Path) -> String {
+    path.file_stem()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
 #[derive(Debug)]
 pub enum Panel {
     Venv,
     Packages,
 }
 
+/// Views of the TUI, switched with Tab.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tab {
+    /// Venvs and their packages
+    Venvs,
+    /// Distributions duplicated across the venvs
+    Overview,
+    /// Startup hooks of the selected venv
+    Audit,
+    /// Differences between two venvs
+    Compare,
+}
+
 #[derive(Debug)]
 pub enum Output {
     /// path of the selected venv
@@ -54,22 +89,71 @@ pub struct App {
     pub current_focus: Panel,
     pub show_help: bool,
     pub maybe_error: Option<eyre::Report>,
+    /// A question for the user, the event is sent once they answer `y`
+    pub confirm: Option<(String, AppEvent)>,
     pub syncing: bool,
     pub total_venvs: u16,
     pub venv_sync_progress: u16,
     pub current_syncing_venv: String,
+    /// Paths of the venvs that are being checked right now
+    pub syncing_venvs: Vec<PathBuf>,
+    /// How long each venv took in the last sync, by path
+    pub sync_timings: Vec<(PathBuf, Duration)>,
+    pub sync_errors: Vec<String>,
+    pub show_sync_report: bool,
+    /// Outcome of the last bytecode action, shown in the footer
+    pub status: Option<String>,
+    /// Usage of all venvs together
+    pub disk_usage: DiskUsage,
+    pub size_mode: SizeMode,
+    pub size_format: SizeFormat,
+    pub tab: Tab,
+    pub duplicates: Vec<DuplicateGroup>,
+    pub duplicates_state: TableState,
+    /// `.pth` files and customize modules of the selected venv
+    pub startup_hooks: Vec<StartupHook>,
+    pub audit_state: TableState,
+    /// Ids of the advisories affecting each package, by canonical name and version
+    pub vulnerable: HashMap<(String, String), Vec<String>>,
+    /// Drift of the venvs that have a lock file next to them, by venv path
+    pub drift: HashMap<PathBuf, Drift>,
+    /// Venv marked with `d`, compared with the next one `d` is pressed on
+    pub compare_base: Option<PathBuf>,
+    /// Paths of the last compared venvs
+    pub compared: Option<(PathBuf, PathBuf)>,
+    pub venv_diff: Option<VenvDiff>,
+    pub compare_state: TableState,
+    advisory_db: Option<AdvisoryDb>,
+    advisory_load: Option<JoinHandle<eyre::Result<AdvisoryDb>>>,
+    /// Lock files are read and compared off the UI thread, see [`App::update_drift`]
+    drift_load: Option<JoinHandle<HashMap<PathBuf, Drift>>>,
+    sync_cancel: Arc<AtomicBool>,
     sync_handle: Option<JoinHandle<()>>,
+    watcher: Option<VenvWatcher>,
+    /// Venvs changed on disk that haven't been refreshed yet
+    pending_changes: BTreeSet<PathBuf>,
+    last_change: Instant,
     sync_rx: Option<Receiver<SyncMsg>>,
     output: Output,
 }
 
 impl App {
     /// Constructs a new instance of [`App`].
-    pub fn new(vm: VenvManager) -> Self {
+    pub fn new(vm: VenvManager, config: &Settings) -> Self {
         let venvs = vm.get_venvs();
+        let disk_usage = vm.disk_usage();
         let uh = Arc::new(RwLock::new(vm));
 
-        Self {
+        let mut sync_errors = Vec::new();
+        let watcher = if config.extra.watch {
+            VenvWatcher::new()
+                .inspect_err(|e| sync_errors.push(format!("Could not watch venvs: {e}")))
+                .ok()
+        } else {
+            None
+        };
+
+        let mut app = Self {
             running: true,
             total_venvs: venvs.len() as u16,
             venv_sync_progress: 0,
@@ -83,10 +167,44 @@ impl App {
             show_help: false,
             syncing: false,
             maybe_error: None,
+            confirm: None,
             sync_handle: None,
             sync_rx: None,
             current_syncing_venv: "".to_string(),
-        }
+            syncing_venvs: Vec::new(),
+            sync_timings: Vec::new(),
+            sync_errors,
+            show_sync_report: false,
+            status: None,
+            disk_usage,
+            size_mode: config.size_mode,
+            size_format: config.size_units,
+            tab: Tab::Venvs,
+            duplicates: Vec::new(),
+            duplicates_state: TableState::default(),
+            startup_hooks: Vec::new(),
+            audit_state: TableState::default(),
+            vulnerable: HashMap::new(),
+            drift: HashMap::new(),
+            compare_base: None,
+            compared: None,
+            venv_diff: None,
+            compare_state: TableState::default(),
+            advisory_db: None,
+            // large databases take a moment to parse, so the venvs show up first
+            advisory_load: config
+                .advisory_db
+                .clone()
+                .map(|path| thread::spawn(move || AdvisoryDb::load(std::path::Path::new(&path)))),
+            drift_load: None,
+            sync_cancel: Arc::new(AtomicBool::new(false)),
+            watcher,
+            pending_changes: BTreeSet::new(),
+            last_change: Instant::now(),
+        };
+        app.update_watches();
+        app.update_drift();
+        app
     }
 
     // Run the application's main loop.
@@ -100,52 +218,173 @@ impl App {
             self.handle_events()?;
         }
 
-        // join sync thread on exit
-        if let Some(h) = self.sync_handle.take() {
+        // stop the sync thread on exit, an unfinished one is left behind instead of blocking
+        self.sync_cancel.store(true, Ordering::Relaxed);
+        if let Some(h) = self.sync_handle.take()
+            && h.is_finished()
+        {
             let _ = h.join();
         }
         Ok(self.output)
     }
 
     pub fn handle_sync_messages(&mut self) {
-        if let Ok(msg) = self.sync_rx.as_mut().unwrap().try_recv() {
+        let Some(rx) = &self.sync_rx else {
+            return;
+        };
+        let msgs: Vec<SyncMsg> = rx.try_iter().collect();
+
+        for msg in msgs {
             match msg {
                 SyncMsg::Started => {}
-                SyncMsg::Progress { venv: _ } => {}
-                SyncMsg::VenvUpdated(venv) => {
+                SyncMsg::Progress { path } => {
+                    self.syncing_venvs.push(path);
+                }
+                SyncMsg::VenvUpdated { path, elapsed } => {
+                    self.syncing_venvs.retain(|p| *p != path);
+                    self.sync_timings.push((path, elapsed));
                     self.venv_sync_progress += 1;
-                    self.current_syncing_venv = venv
+                }
+                SyncMsg::VenvMissing(path) => {
+                    self.syncing_venvs.retain(|p| *p != path);
+                    self.venv_sync_progress += 1;
+                }
+                SyncMsg::VenvMoved { from, to: _ } => {
+                    self.syncing_venvs.retain(|p| *p != from);
+                    self.venv_sync_progress += 1;
+                }
+                SyncMsg::BytecodeCleaned { venv, cleanup } => {
+                    self.status = Some(format!(
+                        "Removed {} __pycache__ dirs and {} .pyc files from {}, freed {}",
+                        cleanup.dirs,
+                        cleanup.files,
+                        venv,
+                        self.size_format.format(cleanup.freed)
+                    ));
+                }
+                SyncMsg::BytecodeCompiled { venv, size } => {
+                    self.status = Some(format!(
+                        "Compiled {}, bytecode takes {}",
+                        venv,
+                        self.size_format.format(size)
+                    ));
                 }
                 SyncMsg::Finished => {
                     self.syncing = false;
-                    // TODO: better error handling here
-                    {
-                        let vm_r = self.vm.read().expect("rwlock poisoned");
-                        vm_r.save_cache().expect("Could not save cache");
-                        let venvs = vm_r.get_venvs();
-                        self.venv_list = VenvListUi::new(venvs);
-                        self.venv_list.list_state.select(Some(self.venv_index));
+                    self.syncing_venvs.clear();
+                    if let Err(e) = self.vm.read().expect("rwlock poisoned").save_cache() {
+                        self.sync_errors.push(format!("Failed to save cache: {e}"));
                     }
-                    self.update_venv_index();
+                    self.refresh_venv_list();
                 }
-                SyncMsg::Error(err) => self.maybe_error = Some(eyre::eyre!(err)),
+                SyncMsg::Error(err) => self.sync_errors.push(err),
             }
         }
+        self.current_syncing_venv = self
+            .syncing_venvs
+            .iter()
+            .map(|path| venv_display_name(path))
+            .collect::<Vec<_>>()
+            .join(", ");
     }
 
     pub fn start_sync(&mut self) {
         if self.syncing {
             return;
         }
-        self.syncing = true;
+        let (tx, rx) = mpsc::channel::<SyncMsg>();
+
+        let vm_arc = Arc::clone(&self.vm);
 
+        self.begin_sync(rx, self.venv_list.venvs.len());
+        let handle = VenvManager::threaded_sync(vm_arc, tx, Arc::clone(&self.sync_cancel));
+        self.sync_handle = Some(handle);
+    }
+
+    /// Reloads the venvs at `paths` in the background, whether they're stale or not.
+    pub fn start_reload(&mut self, paths: Vec<PathBuf>) {
+        if self.syncing {
+            return;
+        }
         let (tx, rx) = mpsc::channel::<SyncMsg>();
 
         let vm_arc = Arc::clone(&self.vm);
+        let total = paths.len();
 
-        let handle = VenvManager::threaded_sync(vm_arc, tx.clone());
+        self.begin_sync(rx, total);
+        let handle = VenvManager::threaded_reload(vm_arc, tx, paths, Arc::clone(&self.sync_cancel));
         self.sync_handle = Some(handle);
+    }
+
+    /// Incrementally re-parses the venvs at `paths` in the background.
+    pub fn start_refresh(&mut self, paths: Vec<PathBuf>) {
+        if self.syncing {
+            return;
+        }
+        let (tx, rx) = mpsc::channel::<SyncMsg>();
+
+        let vm_arc = Arc::clone(&self.vm);
+        let total = paths.len();
+
+        self.begin_sync(rx, total);
+        let handle =
+            VenvManager::threaded_refresh(vm_arc, tx, paths, Arc::clone(&self.sync_cancel));
+        self.sync_handle = Some(handle);
+    }
+
+    /// Collects the venvs that changed on disk and refreshes them once the changes settle down,
+    /// so a `pip install` is picked up once instead of for every file it touches.
+    pub fn handle_watcher(&mut self) {
+        let Some(watcher) = &self.watcher else {
+            return;
+        };
+        let changed = watcher.changed();
+        if !changed.is_empty() {
+            self.pending_changes.extend(changed);
+            self.last_change = Instant::now();
+        }
+
+        if self.syncing
+            || self.pending_changes.is_empty()
+            || self.last_change.elapsed() < WATCH_DEBOUNCE
+        {
+            return;
+        }
+        let paths = std::mem::take(&mut self.pending_changes)
+            .into_iter()
+            .collect();
+        self.start_refresh(paths);
+    }
+
+    /// Watches the venvs that are currently listed.
+    fn update_watches(&mut self) {
+        let Some(watcher) = &mut self.watcher else {
+            return;
+        };
+        let venvs: Vec<&Venv> = self
+            .venv_list
+            .venvs
+            .iter()
+            .filter(|vui| !vui.missing)
+            .map(|vui| vui.venv.as_ref())
+            .collect();
+
+        for e in watcher.watch_venvs(&venvs) {
+            self.sync_errors.push(format!("Could not watch venv: {e}"));
+        }
+    }
+
+    fn begin_sync(&mut self, rx: Receiver<SyncMsg>, total: usize) {
+        self.syncing = true;
         self.sync_rx = Some(rx);
+        self.sync_cancel = Arc::new(AtomicBool::new(false));
+        self.total_venvs = total as u16;
+        self.venv_sync_progress = 0;
+        self.current_syncing_venv.clear();
+        self.syncing_venvs.clear();
+        self.sync_timings.clear();
+        self.sync_errors.clear();
+        self.status = None;
     }
 
     pub fn sync_after_sync(&mut self) {}
@@ -169,7 +408,13 @@ impl App {
                     AppEvent::HalfPageDown => self.select_some_down(),
                     AppEvent::SwitchLeft => self.switch_left(),
                     AppEvent::SwitchRight => self.switch_right(),
-                    AppEvent::UpdateVenvCache => {}
+                    AppEvent::UpdateVenvCache => self.update_venv_cache(),
+                    AppEvent::SyncAll => self.sync_all(),
+                    AppEvent::ForgetVenv => self.forget_venv(),
+                    AppEvent::LocateVenv => self.locate_venv(),
+                    AppEvent::CleanBytecode => self.update_bytecode(BytecodeAction::Clean),
+                    AppEvent::CompileBytecode => self.update_bytecode(BytecodeAction::Compile),
+                    AppEvent::CompareVenv => self.compare_venv(),
                     AppEvent::SelectVenv => {
                         let v = self.get_selected_venv_ui_ref();
                         let venv_path = v.venv.activation_path();
@@ -206,12 +451,32 @@ impl App {
             return Ok(());
         }
 
+        // any key dismisses the error popup
+        if self.maybe_error.take().is_some() {
+            return Ok(());
+        }
+
+        // anything but `y` cancels
+        if let Some((_, event)) = self.confirm.take() {
+            if matches!(key_event.code, KeyCode::Char('y' | 'Y')) {
+                self.events.send(event);
+            }
+            return Ok(());
+        }
+
         match key_event.code {
             KeyCode::Esc | KeyCode::Char('q') => self.events.send(AppEvent::Quit),
             KeyCode::Char('?') => self.show_help = !self.show_help,
+            KeyCode::Char('e') => self.show_sync_report = !self.show_sync_report,
             KeyCode::Char('c' | 'C') if key_event.modifiers == KeyModifiers::CONTROL => {
                 self.events.send(AppEvent::Quit)
             }
+            KeyCode::Tab => self.switch_tab(),
+            _ if self.tab == Tab::Overview => self.handle_overview_key(key_event),
+            _ if self.tab == Tab::Audit => self.handle_audit_key(key_event),
+            _ if self.tab == Tab::Compare => self.handle_compare_key(key_event),
+            // the rest acts on the selected venv
+            _ if self.venv_list.venvs.is_empty() => {}
             KeyCode::Up if key_event.modifiers == KeyModifiers::CONTROL => {
                 self.events.send(AppEvent::SelectFirst)
             }
@@ -233,29 +498,265 @@ impl App {
             KeyCode::Char('a') => self.events.send(AppEvent::SelectVenv),
             KeyCode::Char('r') => self.events.send(AppEvent::Requirements),
             KeyCode::Char('u') => self.events.send(AppEvent::UpdateVenvCache),
+            KeyCode::Char('U') => self.events.send(AppEvent::SyncAll),
+            KeyCode::Char('f') => self.events.send(AppEvent::ForgetVenv),
+            KeyCode::Char('m') => self.events.send(AppEvent::LocateVenv),
+            KeyCode::Char('x') => self.confirm_clean_bytecode(),
+            KeyCode::Char('c') => self.events.send(AppEvent::CompileBytecode),
+            KeyCode::Char('d') => self.events.send(AppEvent::CompareVenv),
             // Other handlers you could add here.
             _ => {}
         }
         Ok(())
     }
 
-    // WARN: This is no longer important so I'll deal with this in later versions
-    // pub fn update_venv_cache(&mut self) {
-    //     let selected_venv_path = self.get_selected_venv_ui().venv.path.clone();
-    //     // reloading removes the old value so we have to recalculate everything
-    //     match self.vm.write().unwrap().reload_venv(&selected_venv_path) {
-    //         Ok(_) => {
-    //             let venvs = self.vm.read().unwrap().get_venvs();
-    //             self.venv_list = VenvListUi::new(venvs);
-    //             self.maybe_error = None;
-    //             self.venv_list.list_state.select(Some(self.venv_index));
-    //             self.update_venv_index();
-    //         }
-    //         Err(e) => {
-    //             self.maybe_error = Some(e);
-    //         }
-    //     }
-    // }
+    /// Keys of the overview tab, only moving around the duplicates for now.
+    fn handle_overview_key(&mut self, key_event: KeyEvent) {
+        match key_event.code {
+            KeyCode::Up | KeyCode::Char('k') => self.duplicates_state.select_previous(),
+            KeyCode::Down | KeyCode::Char('j') => self.duplicates_state.select_next(),
+            KeyCode::Char('K') => self.duplicates_state.select_first(),
+            KeyCode::Char('J') => self.duplicates_state.select_last(),
+            _ => {}
+        }
+    }
+
+    /// Keys of the audit tab.
+    fn handle_audit_key(&mut self, key_event: KeyEvent) {
+        match key_event.code {
+            KeyCode::Up | KeyCode::Char('k') => self.audit_state.select_previous(),
+            KeyCode::Down | KeyCode::Char('j') => self.audit_state.select_next(),
+            KeyCode::Char('K') => self.audit_state.select_first(),
+            KeyCode::Char('J') => self.audit_state.select_last(),
+            _ => {}
+        }
+    }
+
+    /// Keys of the compare tab.
+    fn handle_compare_key(&mut self, key_event: KeyEvent) {
+        match key_event.code {
+            KeyCode::Up | KeyCode::Char('k') => self.compare_state.select_previous(),
+            KeyCode::Down | KeyCode::Char('j') => self.compare_state.select_next(),
+            KeyCode::Char('K') => self.compare_state.select_first(),
+            KeyCode::Char('J') => self.compare_state.select_last(),
+            _ => {}
+        }
+    }
+
+    pub fn switch_tab(&mut self) {
+        self.tab = match self.tab {
+            Tab::Venvs => Tab::Overview,
+            Tab::Overview => Tab::Audit,
+            Tab::Audit => Tab::Compare,
+            Tab::Compare => Tab::Venvs,
+        };
+        match self.tab {
+            Tab::Overview => self.update_overview(),
+            Tab::Audit => self.update_audit(),
+            Tab::Compare => self.update_compare(),
+            Tab::Venvs => {}
+        }
+    }
+
+    /// Marks the selected venv for comparison. With a venv already marked, compares the two
+    /// and switches to the compare tab.
+    pub fn compare_venv(&mut self) {
+        let vui = self.get_selected_venv_ui_ref();
+        if vui.missing {
+            return;
+        }
+        let (path, name) = (vui.venv.path.clone(), vui.venv.name.clone());
+        match self.compare_base.take() {
+            Some(base) if base != path => {
+                self.compared = Some((base, path));
+                self.status = None;
+                self.tab = Tab::Compare;
+                self.compare_state.select(Some(0));
+                self.update_compare();
+            }
+            _ => {
+                self.status = Some(format!("Comparing with {name}, press d on another venv"));
+                self.compare_base = Some(path);
+            }
+        }
+    }
+
+    /// Compares the venvs of the last comparison again with their cached state.
+    pub fn update_compare(&mut self) {
+        let Some((a, b)) = &self.compared else {
+            return;
+        };
+        let find = |path: &PathBuf| {
+            self.venv_list
+                .venvs
+                .iter()
+                .find(|vui| vui.venv.path == *path)
+                .map(|vui| Arc::clone(&vui.venv))
+        };
+        match (find(a), find(b)) {
+            (Some(a), Some(b)) => {
+                let diff = diff_venvs(&a, &b, self.size_mode);
+                let selected = self.compare_state.selected().unwrap_or(0);
+                self.compare_state.select(
+                    (!diff.changes.is_empty()).then(|| selected.min(diff.changes.len() - 1)),
+                );
+                self.venv_diff = Some(diff);
+            }
+            // one of them was forgotten
+            _ => {
+                self.compared = None;
+                self.venv_diff = None;
+                self.compare_state.select(None);
+            }
+        }
+    }
+
+    /// Looks for the startup hooks of the selected venv.
+    pub fn update_audit(&mut self) {
+        if self.venv_list.venvs.is_empty() {
+            self.startup_hooks.clear();
+            self.audit_state.select(None);
+            return;
+        }
+        let vui = self.get_selected_venv_ui_ref();
+        if vui.missing {
+            self.startup_hooks.clear();
+        } else {
+            let venv = Arc::clone(&vui.venv);
+            match find_startup_hooks(&venv) {
+                Ok(hooks) => self.startup_hooks = hooks,
+                Err(e) => {
+                    self.startup_hooks.clear();
+                    self.maybe_error = Some(e);
+                }
+            }
+        }
+
+        let selected = self.audit_state.selected().unwrap_or(0);
+        self.audit_state.select(
+            (!self.startup_hooks.is_empty()).then(|| selected.min(self.startup_hooks.len() - 1)),
+        );
+    }
+
+    /// Groups the distributions that are installed in several venvs.
+    pub fn update_overview(&mut self) {
+        let venvs: Vec<Arc<Venv>> = self
+            .venv_list
+            .venvs
+            .iter()
+            .map(|vui| Arc::clone(&vui.venv))
+            .collect();
+        self.duplicates = find_duplicates(&venvs, false);
+
+        let selected = self.duplicates_state.selected().unwrap_or(0);
+        self.duplicates_state
+            .select((!self.duplicates.is_empty()).then(|| selected.min(self.duplicates.len() - 1)));
+    }
+
+    /// Reloads the selected venv in the background.
+    pub fn update_venv_cache(&mut self) {
+        let selected_venv_path = self.get_selected_venv_ui_ref().venv.path.clone();
+        self.start_reload(vec![selected_venv_path]);
+    }
+
+    /// Re-parses every cached venv in the background.
+    pub fn sync_all(&mut self) {
+        let paths = self
+            .venv_list
+            .venvs
+            .iter()
+            .map(|vui| vui.venv.path.clone())
+            .collect();
+        self.start_reload(paths);
+    }
+
+    /// Removes the selected venv from the cache.
+    pub fn forget_venv(&mut self) {
+        // a running sync could put the venv right back into the cache
+        if self.syncing {
+            self.maybe_error = Some(eyre::eyre!("Can't forget a venv while syncing"));
+            return;
+        }
+        let path = self.get_selected_venv_ui_ref().venv.path.clone();
+        let res = self.vm.write().expect("rwlock poisoned").forget(&path);
+        match res {
+            Ok(_) => self.refresh_venv_list(),
+            Err(e) => self.maybe_error = Some(e),
+        }
+    }
+
+    /// Searches for the new location of the selected venv if it's missing.
+    pub fn locate_venv(&mut self) {
+        if self.syncing || !self.get_selected_venv_ui_ref().missing {
+            return;
+        }
+        let (tx, rx) = mpsc::channel::<SyncMsg>();
+
+        let vm_arc = Arc::clone(&self.vm);
+        let path = self.get_selected_venv_ui_ref().venv.path.clone();
+
+        self.begin_sync(rx, 1);
+        let handle = VenvManager::threaded_locate(vm_arc, tx, path);
+        self.sync_handle = Some(handle);
+    }
+
+    /// Removes or compiles the bytecode of the selected venv in the background.
+    /// Asks before removing the bytecode of the selected venv.
+    pub fn confirm_clean_bytecode(&mut self) {
+        let vui = self.get_selected_venv_ui_ref();
+        let (venv, missing) = (Arc::clone(&vui.venv), vui.missing);
+        if missing || self.syncing {
+            return;
+        }
+        let question = format!(
+            "Remove the bytecode of {} ({})? [y/N]",
+            venv.name,
+            self.size_format
+                .format(venv.breakdown.get(Category::Bytecode))
+        );
+        self.confirm = Some((question, AppEvent::CleanBytecode));
+    }
+
+    pub fn update_bytecode(&mut self, action: BytecodeAction) {
+        if self.syncing || self.get_selected_venv_ui_ref().missing {
+            return;
+        }
+        let venv = Arc::clone(&self.get_selected_venv_ui_ref().venv);
+        let (tx, rx) = mpsc::channel::<SyncMsg>();
+
+        let vm_arc = Arc::clone(&self.vm);
+
+        self.begin_sync(rx, 1);
+        let handle = VenvManager::threaded_bytecode(vm_arc, tx, venv, action);
+        self.sync_handle = Some(handle);
+    }
+
+    /// Picks up the venvs from the cache, keeping the current selection and scroll positions.
+    pub fn refresh_venv_list(&mut self) {
+        {
+            let vm_r = self.vm.read().expect("rwlock poisoned");
+            self.venv_list.update(vm_r.get_venvs());
+            for vui in self.venv_list.venvs.iter_mut() {
+                vui.missing = vm_r.is_missing(&vui.venv.path);
+            }
+            self.disk_usage = vm_r.disk_usage();
+        }
+
+        self.venv_index = self
+            .venv_index
+            .min(self.venv_list.venvs.len().saturating_sub(1));
+        self.venv_list.list_state.select(Some(self.venv_index));
+        self.update_package_index();
+        self.update_watches();
+        self.update_vulnerable();
+        self.update_drift();
+        match self.tab {
+            Tab::Overview => self.update_overview(),
+            Tab::Audit => self.update_audit(),
+            Tab::Compare => self.update_compare(),
+            Tab::Venvs => {}
+        }
+    }
 
     /// Handles the tick event of the terminal.
     ///
@@ -264,6 +765,111 @@ impl App {
     pub fn tick(&mut self) {
         // sync message has a loading animation so we update it here
         self.handle_sync_messages();
+        self.handle_watcher();
+        self.handle_advisory_load();
+        self.handle_drift_load();
+    }
+
+    /// Picks up the advisory database once its thread is done.
+    fn handle_advisory_load(&mut self) {
+        if !self
+            .advisory_load
+            .as_ref()
+            .is_some_and(JoinHandle::is_finished)
+        {
+            return;
+        }
+        let Some(handle) = self.advisory_load.take() else {
+            return;
+        };
+        match handle.join() {
+            Ok(Ok(db)) => {
+                self.status = Some(if db.skipped > 0 {
+                    format!(
+                        "Loaded {} advisories, {} files couldn't be read",
+                        db.len(),
+                        db.skipped
+                    )
+                } else {
+                    format!("Loaded {} advisories", db.len())
+                });
+                self.advisory_db = Some(db);
+                self.update_vulnerable();
+            }
+            Ok(Err(e)) => self.maybe_error = Some(e.wrap_err("Failed to load advisory database")),
+            Err(_) => self.maybe_error = Some(eyre::eyre!("Advisory database loader panicked")),
+        }
+    }
+
+    /// Matches the packages of every venv against the advisory database, if one is loaded.
+    pub fn update_vulnerable(&mut self) {
+        let Some(db) = &self.advisory_db else {
+            return;
+        };
+        self.vulnerable.clear();
+        for vui in &self.venv_list.venvs {
+            for pkg in &vui.venv.packages {
+                let key = (canonical_name(&pkg.name), pkg.version.clone());
+                if self.vulnerable.contains_key(&key) {
+                    continue;
+                }
+                let ids: Vec<String> = db
+                    .affecting(&pkg.name, &pkg.version)
+                    .iter()
+                    .map(|a| a.id.clone())
+                    .collect();
+                if !ids.is_empty() {
+                    self.vulnerable.insert(key, ids);
+                }
+            }
+        }
+    }
+
+    /// Compares each venv to the lock file next to it in the background. A lock file that can't
+    /// be read is left out rather than shown as an error on every refresh. A comparison that's
+    /// still running is superseded, only the latest one is picked up.
+    pub fn update_drift(&mut self) {
+        let venvs: Vec<Arc<Venv>> = self
+            .venv_list
+            .venvs
+            .iter()
+            .map(|vui| Arc::clone(&vui.venv))
+            .collect();
+        self.drift_load = Some(thread::spawn(move || {
+            venvs
+                .iter()
+                .filter_map(|venv| {
+                    let lock = find_lock_file(venv)?;
+                    let drift = check_drift(venv, &lock).ok()?;
+                    Some((venv.path.clone(), drift))
+                })
+                .collect()
+        }));
+    }
+
+    /// Picks up the drift of the venvs once its thread is done.
+    fn handle_drift_load(&mut self) {
+        if !self
+            .drift_load
+            .as_ref()
+            .is_some_and(JoinHandle::is_finished)
+        {
+            return;
+        }
+        let Some(handle) = self.drift_load.take() else {
+            return;
+        };
+        match handle.join() {
+            Ok(drift) => self.drift = drift,
+            Err(_) => self.maybe_error = Some(eyre::eyre!("Lock file comparison panicked")),
+        }
+    }
+
+    /// Advisory ids affecting `pkg`, empty without a database.
+    pub fn advisories_of(&self, pkg: &Package) -> &[String] {
+        self.vulnerable
+            .get(&(canonical_name(&pkg.name), pkg.version.clone()))
+            .map_or(&[], Vec::as_slice)
     }
 
     /// Set running to false to quit the application.
@@ -417,6 +1023,9 @@ impl App {
         }
     }
     pub fn update_package_index(&mut self) {
+        if self.venv_list.venvs.is_empty() {
+            return;
+        }
         let current_venv = self.get_selected_venv_ui_ref();
         if let Some(i) = current_venv.list_state.selected() {
             if i >= current_venv.venv.packages.len() {
@@ -1,6 +1,8 @@
+use crate::venv::bytecode::BytecodeCleanup;
 use color_eyre::eyre::WrapErr;
 use ratatui::crossterm::event::{self, Event as CrosstermEvent};
 use std::{
+    path::PathBuf,
     sync::mpsc,
     thread,
     time::{Duration, Instant},
@@ -45,14 +47,51 @@ pub enum AppEvent {
     SwitchRight,
     SelectVenv,
     Requirements,
+    /// Re-parse the selected venv.
     UpdateVenvCache,
+    /// Re-parse every cached venv.
+    SyncAll,
+    /// Remove the selected venv from the cache.
+    ForgetVenv,
+    /// Search for a missing venv that has been moved.
+    LocateVenv,
+    /// Remove the bytecode of the selected venv.
+    CleanBytecode,
+    /// Compile the bytecode of the selected venv.
+    CompileBytecode,
+    /// Mark the selected venv for comparison, or compare it with the marked one.
+    CompareVenv,
 }
 
 #[derive(Debug)]
 pub enum SyncMsg {
     Started,
-    Progress { venv: String },
-    VenvUpdated(String),
+    /// Started checking the venv at `path`.
+    Progress {
+        path: PathBuf,
+    },
+    /// Done checking a venv, whether it had to be re-parsed or not.
+    VenvUpdated {
+        path: PathBuf,
+        elapsed: Duration,
+    },
+    /// A cached venv is no longer on disk.
+    VenvMissing(PathBuf),
+    /// A missing venv was found at a new path.
+    VenvMoved {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    /// The bytecode of a venv was removed.
+    BytecodeCleaned {
+        venv: String,
+        cleanup: BytecodeCleanup,
+    },
+    /// The bytecode of a venv was compiled, `size` is its bytecode afterwards.
+    BytecodeCompiled {
+        venv: String,
+        size: u64,
+    },
     Finished,
     Error(String),
 }
@@ -1,4 +1,3 @@
-use crate::dir_size::{Chonk, ParallelReader};
 use chrono::{DateTime, Local};
 use ratatui::{
     buffer::Buffer,
@@ -6,15 +5,26 @@ use ratatui::{
     style::{Color, Modifier, Style, Stylize},
     text::{Line, Span, Text},
     widgets::{
-        Block, Borders, Clear, HighlightSpacing, LineGauge, List, ListItem, Padding, Paragraph,
-        Scrollbar, ScrollbarOrientation, StatefulWidget, Widget, Wrap,
+        Block, Borders, Cell, Clear, HighlightSpacing, LineGauge, List, ListItem, Padding,
+        Paragraph, Row, Scrollbar, ScrollbarOrientation, StatefulWidget, Table, Widget, Wrap,
     },
 };
 
-use crate::tui::App;
+use crate::{
+    analysis::{ChangeKind, DuplicateGroup, LinkState, license_of},
+    dir_size::{Category, SizeBreakdown, SizeFormat},
+    tui::{
+        App,
+        app::{Tab, venv_display_name},
+    },
+    venv::{conflicts::Conflict, model::Package, utils::canonical_name, wheel::Portability},
+};
 
 const PANEL_STYLE: Style = Style::new().fg(Color::White);
 const FOCUSED_PANEL_STYLE: Style = Style::new().fg(Color::Green);
+const MISSING_STYLE: Style = Style::new()
+    .fg(Color::DarkGray)
+    .add_modifier(Modifier::CROSSED_OUT);
 const SELECTED_STYLE: Style = Style::new()
     .fg(Color::Black)
     .bg(Color::Blue)
@@ -57,16 +67,27 @@ impl Widget for &mut App {
 
         let [venv_layout, venv_details_layout] = Layout::default()
             .direction(Direction::Vertical)
-            .constraints([Constraint::Percentage(70), Constraint::Percentage(30)])
+            .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
             .areas(left);
 
         let footer_block = Block::new()
             .borders(Borders::empty())
             .padding(Padding::left(1));
 
-        let footer_text = String::from(
-            "Exit: q | Movement: hjkl or ↓ ↑ ← → | Activate: a | Requirements: r | Update: u | Help: ?",
-        );
+        let mut footer_text = String::from(match self.tab {
+            Tab::Venvs => {
+                "Exit: q | Movement: hjkl or ↓ ↑ ← → | Activate: a | Requirements: r | Reload: u/U | Overview: Tab | Help: ?"
+            }
+            Tab::Overview => "Exit: q | Movement: jk or ↓ ↑ | Audit: Tab | Help: ?",
+            Tab::Audit => "Exit: q | Movement: jk or ↓ ↑ | Compare: Tab | Help: ?",
+            Tab::Compare => "Exit: q | Movement: jk or ↓ ↑ | Venvs: Tab | Help: ?",
+        });
+        if let Some(status) = &self.status {
+            footer_text.push_str(&format!(" | {status}"));
+        }
+        if !self.sync_errors.is_empty() {
+            footer_text.push_str(&format!(" | Sync errors: {} (e)", self.sync_errors.len()));
+        }
 
         let footer = Paragraph::new(footer_text)
             .block(footer_block)
@@ -75,20 +96,42 @@ impl Widget for &mut App {
 
         footer.render(footer_chunk, buf);
 
-        self.render_venvs(venv_layout, buf);
-        self.render_packages(packages_layout, buf);
-        self.render_package_details(pkg_details, buf);
-        self.render_package_dependencies(pkg_dependencies, buf);
-        self.render_venv_details(venv_details_layout, buf);
+        match self.tab {
+            Tab::Venvs if self.venv_list.venvs.is_empty() => {
+                self.render_venvs(left, buf);
+                Paragraph::new("No venvs cached. Add one with `venv-rs venv <path>`.")
+                    .block(Block::new().borders(Borders::ALL).border_style(PANEL_STYLE))
+                    .centered()
+                    .render(right, buf);
+            }
+            Tab::Venvs => {
+                self.render_venvs(venv_layout, buf);
+                self.render_packages(packages_layout, buf);
+                self.render_package_details(pkg_details, buf);
+                self.render_package_dependencies(pkg_dependencies, buf);
+                self.render_venv_details(venv_details_layout, buf);
+
+                if self.syncing {
+                    self.render_sync_text(progress, buf);
+                }
+            }
+            Tab::Overview => self.render_overview(main, buf),
+            Tab::Audit => self.render_audit(main, buf),
+            Tab::Compare => self.render_compare(main, buf),
+        }
 
-        if self.syncing {
-            self.render_sync_text(progress, buf);
+        if self.show_sync_report {
+            self.render_sync_report(area, buf);
         }
 
         if self.show_help {
             self.render_help(area, buf);
         }
 
+        if self.confirm.is_some() {
+            self.render_confirm(area, buf);
+        }
+
         if self.maybe_error.is_some() {
             self.render_error(area, buf);
         }
@@ -107,7 +150,15 @@ impl Widget for &mut App {
 impl App {
     fn render_venvs(&mut self, area: Rect, buf: &mut Buffer) {
         let block = Block::new()
-            .title(Line::raw("Virtual Environments").centered())
+            .title(
+                Line::raw(format!(
+                    "Virtual Environments ({}, {} on disk)",
+                    self.size_format.format(self.disk_usage.apparent),
+                    self.size_format
+                        .format(self.disk_usage.on_disk(self.size_mode))
+                ))
+                .centered(),
+            )
             .borders(Borders::ALL)
             .border_style(match self.current_focus {
                 super::app::Panel::Venv => FOCUSED_PANEL_STYLE,
@@ -118,7 +169,15 @@ impl App {
             .venv_list
             .venvs
             .iter()
-            .map(|vui| ListItem::from(vui.venv.name.clone()))
+            .map(|vui| {
+                if vui.missing {
+                    ListItem::from(format!("{} (missing)", vui.venv.name)).style(MISSING_STYLE)
+                } else if self.compare_base.as_ref() == Some(&vui.venv.path) {
+                    ListItem::from(format!("{} ⇄", vui.venv.name)).style(Style::new().cyan())
+                } else {
+                    ListItem::from(vui.venv.name.clone())
+                }
+            })
             .collect();
 
         let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
@@ -159,12 +218,26 @@ impl App {
         let mut v = self.get_selected_venv_ui();
         let style = Style::default();
         let no_dependency_style = Style::default().magenta().italic();
+        let conflict_style = Style::default().red();
+        let vulnerable_style = Style::default().red().bold();
+        let drift_style = Style::default().yellow();
+        let drift = self.drift.get(&v.venv.path);
 
         let items: Vec<ListItem> = v
             .venv
             .packages
             .iter()
             .map(|pack| {
+                if !self.advisories_of(pack).is_empty() {
+                    return ListItem::from(format!("{} ✗", pack.name)).style(vulnerable_style);
+                }
+                // files shared with another distribution break uninstalls
+                if v.venv.conflicts_of(pack).next().is_some() {
+                    return ListItem::from(format!("{} ⚠", pack.name)).style(conflict_style);
+                }
+                if drift.is_some_and(|d| d.drifted(&pack.name)) {
+                    return ListItem::from(format!("{} ~", pack.name)).style(drift_style);
+                }
                 let mut item = ListItem::from(pack.name.clone());
                 if pack.metadata.dependencies.is_none() {
                     item = item.style(no_dependency_style);
@@ -213,7 +286,7 @@ impl App {
         let datetime: DateTime<Local> = package.last_modified.into();
         let fmt_date = datetime.format("%Y-%m-%d %H:%M");
 
-        let details = vec![
+        let mut details = vec![
             Line::from(Span::styled(format!("Name:     {}", package.name), style)),
             Line::from(Span::styled(
                 format!("Version:  {}", package.version),
@@ -224,22 +297,76 @@ impl App {
                 style,
             )),
             Line::from(Span::styled(
-                format!("Size: {}", ParallelReader::formatted_size(package.size)),
+                format!(
+                    "Size: {}",
+                    self.size_format.format(package.usage.size(self.size_mode))
+                ),
+                style,
+            )),
+            Line::from(Span::styled(
+                format!("License:  {}", license_of(&package.metadata)),
+                style,
+            )),
+            Line::from(Span::styled(
+                format!("Wheel:    {}", wheel_summary(&package)),
                 style,
             )),
             Line::from(Span::styled(format!("Last Modified: {fmt_date}"), style)),
-            if package.metadata.dependencies.is_some() {
+            if let Some(dependencies) = &package.metadata.dependencies {
                 Line::from(Span::styled(
-                    format!(
-                        "Num Dependencies: {}",
-                        package.metadata.dependencies.unwrap().len()
-                    ),
+                    format!("Num Dependencies: {}", dependencies.len()),
                     style,
                 ))
             } else {
                 Line::from("")
             },
         ];
+        let venv = self.get_selected_venv_ui().venv;
+        let conflicts: Vec<&Conflict> = venv.conflicts_of(&package).collect();
+        if !conflicts.is_empty() {
+            let mut others: Vec<&str> = conflicts
+                .iter()
+                .flat_map(|c| c.owner_names())
+                .filter(|name| canonical_name(name) != canonical_name(&package.name))
+                .collect();
+            others.sort_unstable();
+            others.dedup();
+            details.push(Line::from(Span::styled(
+                format!(
+                    "Conflicts: {} files also owned by {}",
+                    conflicts.len(),
+                    others.join(", ")
+                ),
+                Style::new().red().bold(),
+            )));
+        }
+        if let Some(drift) = self.drift.get(&venv.path) {
+            let lock = drift
+                .file
+                .file_name()
+                .map(|n| n.to_string_lossy())
+                .unwrap_or_default();
+            let text = match drift.mismatch_of(&package.name) {
+                Some(mismatch) => Some(format!("Drift:    {} expects {}", lock, mismatch.expected)),
+                None if drift.drifted(&package.name) => Some(format!("Drift:    not in {lock}")),
+                None => None,
+            };
+            if let Some(text) = text {
+                details.push(Line::from(Span::styled(text, Style::new().yellow().bold())));
+            }
+        }
+        let advisories = self.advisories_of(&package);
+        if !advisories.is_empty() {
+            details.push(Line::from(Span::styled(
+                format!("Vulnerable: {}", advisories.join(", ")),
+                Style::new().red().bold(),
+            )));
+        }
+        details.extend(breakdown_lines(
+            &package.breakdown,
+            area.width.saturating_sub(2),
+            self.size_format,
+        ));
 
         let p = Paragraph::new(details)
             .block(block)
@@ -289,7 +416,7 @@ impl App {
         let datetime = self.get_selected_venv_ui_ref().last_modified;
         let fmt_date = datetime.format("%Y-%m-%d %H:%M");
 
-        let details = vec![
+        let mut details = vec![
             Line::from(Span::styled(
                 format!("Name:           {}", venv.name),
                 style,
@@ -306,23 +433,446 @@ impl App {
                 format!("# of Pkg:       {}", venv.num_dist_info_packages),
                 style,
             )),
+            Line::from(Span::styled(
+                format!("Size:           {}", self.size_format.format(venv.size)),
+                style,
+            )),
+            Line::from(Span::styled(
+                format!(
+                    "Allocated Size: {}",
+                    self.size_format.format(venv.usage.allocated)
+                ),
+                style,
+            )),
             Line::from(Span::styled(
                 format!(
-                    "Size:           {}",
-                    ParallelReader::formatted_size(venv.size)
+                    "Unique Size:    {} ({} hardlinked)",
+                    self.size_format.format(venv.usage.unique),
+                    self.size_format.format(venv.linked_size())
                 ),
                 style,
             )),
             Line::from(Span::styled(format!("Last Modified:  {fmt_date}"), style)),
         ];
 
+        if !venv.orphans.is_empty() {
+            details.push(Line::from(Span::styled(
+                format!(
+                    "Orphaned:       {} ({} not owned by any package)",
+                    self.size_format.format(venv.orphaned_size()),
+                    venv.orphans.len()
+                ),
+                Style::new().yellow().italic(),
+            )));
+        }
+
+        if let Some(first) = venv.skipped.first() {
+            details.push(Line::from(Span::styled(
+                format!(
+                    "Unreadable:     {} left out of the size, e.g. {}",
+                    venv.skipped.len(),
+                    first.path.display()
+                ),
+                Style::new().yellow().italic(),
+            )));
+        }
+
+        if let Some(drift) = self.drift.get(&venv.path)
+            && !drift.is_clean()
+        {
+            details.push(Line::from(Span::styled(
+                format!(
+                    "Drift:          {} missing, {} extra, {} mismatched ({})",
+                    drift.missing.len(),
+                    drift.extra.len(),
+                    drift.mismatched.len(),
+                    drift
+                        .file
+                        .file_name()
+                        .map(|n| n.to_string_lossy())
+                        .unwrap_or_default()
+                ),
+                Style::new().yellow().italic(),
+            )));
+        }
+
+        let platform_specific: Vec<&str> = venv
+            .packages
+            .iter()
+            .filter(|p| p.portability() == Portability::PlatformSpecific)
+            .map(|p| p.name.as_str())
+            .collect();
+        if !platform_specific.is_empty() {
+            details.push(Line::from(Span::styled(
+                format!(
+                    "Platform:       {} platform-specific ({})",
+                    platform_specific.len(),
+                    platform_specific.join(", ")
+                ),
+                style,
+            )));
+        }
+
+        if self.get_selected_venv_ui_ref().missing {
+            details.push(Line::from(Span::styled(
+                "Missing on disk! Forget: f | Locate: m",
+                Style::new().red().bold(),
+            )));
+        }
+
+        details.extend(breakdown_lines(
+            &venv.breakdown,
+            area.width.saturating_sub(2),
+            self.size_format,
+        ));
+
         let p = Paragraph::new(details)
             .block(block)
+            .wrap(Wrap { trim: false })
             .alignment(Alignment::Left);
 
         p.render(area, buf);
     }
 
+    fn render_overview(&mut self, area: Rect, buf: &mut Buffer) {
+        let copies = self
+            .duplicates_state
+            .selected()
+            .and_then(|i| self.duplicates.get(i))
+            .map_or(0, |group| group.copies.len());
+        let [table_area, copies_area] = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(5), Constraint::Length(copies as u16 + 2)])
+            .areas(area);
+
+        let reclaimable: u64 = self
+            .duplicates
+            .iter()
+            .map(DuplicateGroup::reclaimable)
+            .sum();
+        let block = Block::new()
+            .title(
+                Line::raw(format!(
+                    "Duplicate Distributions ({}, {} reclaimable)",
+                    self.duplicates.len(),
+                    self.size_format.format(reclaimable)
+                ))
+                .centered(),
+            )
+            .borders(Borders::ALL)
+            .border_style(FOCUSED_PANEL_STYLE);
+
+        let header = Row::new([
+            "Package",
+            "Version",
+            "Copies",
+            "Total",
+            "On Disk",
+            "Reclaimable",
+            "Hardlinked",
+        ])
+        .style(Style::new().bold().light_blue());
+
+        let rows: Vec<Row> = self
+            .duplicates
+            .iter()
+            .map(|group| {
+                let linked = match group.link_state {
+                    LinkState::Full => Span::styled("yes", Style::new().green()),
+                    LinkState::Partial => Span::styled("partly", Style::new().yellow()),
+                    LinkState::None => Span::styled("no", Style::new().red()),
+                };
+                Row::new([
+                    Cell::from(group.name.clone()),
+                    Cell::from(group.version.clone()),
+                    Cell::from(group.copies.len().to_string()),
+                    Cell::from(self.size_format.format(group.size)),
+                    Cell::from(self.size_format.format(group.on_disk)),
+                    Cell::from(self.size_format.format(group.reclaimable())),
+                    Cell::from(linked),
+                ])
+            })
+            .collect();
+
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Fill(3),
+                Constraint::Fill(2),
+                Constraint::Length(6),
+                Constraint::Length(11),
+                Constraint::Length(11),
+                Constraint::Length(11),
+                Constraint::Length(10),
+            ],
+        )
+        .header(header)
+        .block(block)
+        .row_highlight_style(SELECTED_STYLE)
+        .highlight_symbol(">")
+        .highlight_spacing(HighlightSpacing::Always);
+
+        StatefulWidget::render(table, table_area, buf, &mut self.duplicates_state);
+
+        let copies: Vec<Line> = self
+            .duplicates_state
+            .selected()
+            .and_then(|i| self.duplicates.get(i))
+            .map(|group| {
+                group
+                    .copies
+                    .iter()
+                    .map(|copy| {
+                        Line::from(vec![
+                            Span::styled(format!("{:<20} ", copy.venv), Style::new().yellow()),
+                            Span::raw(format!("{:>10}  ", self.size_format.format(copy.size))),
+                            Span::styled(
+                                copy.venv_path.to_string_lossy().into_owned(),
+                                Style::new().dark_gray(),
+                            ),
+                        ])
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Paragraph::new(copies)
+            .block(
+                Block::new()
+                    .title(Line::raw("Copies").centered())
+                    .borders(Borders::ALL)
+                    .border_style(PANEL_STYLE),
+            )
+            .render(copies_area, buf);
+    }
+
+    fn render_audit(&mut self, area: Rect, buf: &mut Buffer) {
+        let [table_area, lines_area] = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+            .areas(area);
+
+        let venv_name = self
+            .venv_list
+            .venvs
+            .get(self.venv_index)
+            .map(|vui| vui.venv.name.clone())
+            .unwrap_or_default();
+        let executing = self.startup_hooks.iter().filter(|h| h.executes()).count();
+        let block = Block::new()
+            .title(
+                Line::raw(format!(
+                    "Startup Hooks of {} ({}, {} run code)",
+                    venv_name,
+                    self.startup_hooks.len(),
+                    executing
+                ))
+                .centered(),
+            )
+            .borders(Borders::ALL)
+            .border_style(FOCUSED_PANEL_STYLE);
+
+        let header = Row::new(["File", "Kind", "Owner", "Runs Code"])
+            .style(Style::new().bold().light_blue());
+
+        let rows: Vec<Row> = self
+            .startup_hooks
+            .iter()
+            .map(|hook| {
+                let file = hook
+                    .path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                let owner = match &hook.owner {
+                    Some(owner) => Span::raw(owner.clone()),
+                    None => Span::styled("none", Style::new().yellow()),
+                };
+                let executes = if hook.executes() {
+                    Span::styled("yes", Style::new().red())
+                } else {
+                    Span::styled("no", Style::new().green())
+                };
+                Row::new([
+                    Cell::from(file),
+                    Cell::from(hook.kind.name()),
+                    Cell::from(owner),
+                    Cell::from(executes),
+                ])
+            })
+            .collect();
+
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Fill(3),
+                Constraint::Length(14),
+                Constraint::Fill(2),
+                Constraint::Length(10),
+            ],
+        )
+        .header(header)
+        .block(block)
+        .row_highlight_style(SELECTED_STYLE)
+        .highlight_symbol(">")
+        .highlight_spacing(HighlightSpacing::Always);
+
+        StatefulWidget::render(table, table_area, buf, &mut self.audit_state);
+
+        let selected = self
+            .audit_state
+            .selected()
+            .and_then(|i| self.startup_hooks.get(i));
+        let lines: Vec<Line> = selected
+            .map(|hook| {
+                hook.lines
+                    .iter()
+                    .map(|line| {
+                        let style = if line.executes {
+                            Style::new().red().bold()
+                        } else {
+                            Style::new()
+                        };
+                        Line::from(vec![
+                            Span::styled(format!("{:>4} ", line.number), Style::new().dark_gray()),
+                            Span::styled(line.text.clone(), style),
+                        ])
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let title = selected
+            .map(|hook| hook.path.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        Paragraph::new(lines)
+            .block(
+                Block::new()
+                    .title(Line::raw(title).centered())
+                    .borders(Borders::ALL)
+                    .border_style(PANEL_STYLE),
+            )
+            .wrap(Wrap { trim: false })
+            .render(lines_area, buf);
+    }
+
+    fn render_compare(&mut self, area: Rect, buf: &mut Buffer) {
+        let Some(diff) = &self.venv_diff else {
+            Paragraph::new("Press d on two venvs to compare them")
+                .centered()
+                .block(
+                    Block::new()
+                        .title(Line::raw("Compare").centered())
+                        .borders(Borders::ALL)
+                        .border_style(FOCUSED_PANEL_STYLE),
+                )
+                .render(area, buf);
+            return;
+        };
+        let [summary_area, table_area] = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(5), Constraint::Min(5)])
+            .areas(area);
+
+        let name = |path: &std::path::Path| {
+            path.file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        };
+        let (python_a, python_b) = &diff.python;
+        let python = if python_a == python_b {
+            Line::from(format!("Python: {python_a}"))
+        } else {
+            Line::from(vec![
+                Span::raw("Python: "),
+                Span::styled(python_a.clone(), Style::new().red()),
+                Span::raw(" → "),
+                Span::styled(python_b.clone(), Style::new().green()),
+            ])
+        };
+        let count = |kind| diff.of_kind(kind).count();
+        let summary = vec![
+            Line::from(format!(
+                "{} → {}",
+                diff.a.to_string_lossy(),
+                diff.b.to_string_lossy()
+            )),
+            python,
+            Line::from(format!(
+                "Size: {} → {} ({})",
+                self.size_format.format(diff.size.0),
+                self.size_format.format(diff.size.1),
+                self.size_format.format_delta(diff.size_delta())
+            )),
+        ];
+        Paragraph::new(summary)
+            .block(
+                Block::new()
+                    .title(Line::raw(format!("{} → {}", name(&diff.a), name(&diff.b))).centered())
+                    .borders(Borders::ALL)
+                    .border_style(PANEL_STYLE),
+            )
+            .render(summary_area, buf);
+
+        let block = Block::new()
+            .title(
+                Line::raw(format!(
+                    "Changes ({} added, {} removed, {} upgraded, {} downgraded, {} changed, {} unchanged)",
+                    count(ChangeKind::Added),
+                    count(ChangeKind::Removed),
+                    count(ChangeKind::Upgraded),
+                    count(ChangeKind::Downgraded),
+ count(ChangeKind::Changed),
+                    diff.unchanged
+                ))
+                .centered(),
+            )
+            .borders(Borders::ALL)
+            .border_style(FOCUSED_PANEL_STYLE);
+
+        let header = Row::new(["", "Package", "Old", "New", "Size Δ"])
+            .style(Style::new().bold().light_blue());
+
+        let rows: Vec<Row> = diff
+            .changes
+            .iter()
+            .map(|change| {
+                let (symbol, style) = match change.kind {
+                    ChangeKind::Added => ("+", Style::new().green()),
+                    ChangeKind::Removed => ("-", Style::new().red()),
+                    ChangeKind::Upgraded => ("↑", Style::new().cyan()),
+                    ChangeKind::Downgraded => ("↓", Style::new().yellow()),
+                    ChangeKind::Changed => ("~", Style::new().yellow()),
+                };
+                Row::new([
+                    Cell::from(symbol).style(style),
+                    Cell::from(change.name.clone()).style(style),
+                    Cell::from(change.old.clone().unwrap_or_default()),
+                    Cell::from(change.new.clone().unwrap_or_default()),
+                    Cell::from(self.size_format.format_delta(change.size_delta())),
+                ])
+            })
+            .collect();
+
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(1),
+                Constraint::Fill(3),
+                Constraint::Fill(1),
+                Constraint::Fill(1),
+                Constraint::Length(12),
+            ],
+        )
+        .header(header)
+        .block(block)
+        .row_highlight_style(SELECTED_STYLE)
+        .highlight_symbol(">")
+        .highlight_spacing(HighlightSpacing::Always);
+
+        StatefulWidget::render(table, table_area, buf, &mut self.compare_state);
+    }
+
     fn render_help(&mut self, area: Rect, buf: &mut Buffer) {
         // Create centered rect: 60% width, 70% height
         let popup_area = Layout::default()
@@ -358,7 +908,14 @@ impl App {
             ("q", "Exit"),
             ("a", "Activate selected venv"),
             ("r", "Print requirements and exit"),
-            ("u", "Parse the venv and update cache"),
+            ("u", "Reload the selected venv"),
+            ("U", "Reload all venvs"),
+            ("f", "Forget the selected venv"),
+            ("m", "Locate a missing venv that was moved"),
+            ("x", "Remove the bytecode of the selected venv, asks first"),
+            ("c", "Compile the bytecode of the selected venv"),
+            ("d", "Mark the selected venv, then compare it with another"),
+            ("e", "Toggle the sync report"),
             ("?", "Toggle keybinds"),
         ];
 
@@ -371,6 +928,7 @@ impl App {
             ("Ctrl+u / PgUp", "Half page up"),
             ("J / Ctrl+↓", "Scroll last"),
             ("K / Ctrl+↑", "Scroll first"),
+            ("Tab", "Switch between venvs, overview, audit and compare"),
         ];
 
         /* layout kinda looks like this
@@ -405,9 +963,9 @@ impl App {
             .direction(Direction::Vertical)
             .constraints([
                 Constraint::Length(3),
-                Constraint::Length(5),
+                Constraint::Length(actions.len() as u16),
                 Constraint::Length(3),
-                Constraint::Length(10),
+                Constraint::Length(navigations.len() as u16),
                 Constraint::Fill(1),
             ])
             .areas(popup_area.inner(Margin {
@@ -542,6 +1100,101 @@ impl App {
         }
     }
 
+    fn render_confirm(&mut self, area: Rect, buf: &mut Buffer) {
+        let Some((question, _)) = &self.confirm else {
+            return;
+        };
+        // a single line, centered and as wide as the error popup
+        let popup_area = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Fill(1),
+                Constraint::Length(3),
+                Constraint::Fill(1),
+            ])
+            .split(area)[1];
+
+        let popup_area = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Percentage(20),
+                Constraint::Percentage(60),
+                Constraint::Percentage(20),
+            ])
+            .split(popup_area)[1];
+
+        Clear.render(popup_area, buf);
+        let block = Block::new()
+            .title(Line::styled("Confirm", Style::new().bold().yellow()))
+            .borders(Borders::ALL)
+            .border_style(FOCUSED_PANEL_STYLE);
+        Paragraph::new(question.as_str())
+            .block(block)
+            .centered()
+            .render(popup_area, buf);
+    }
+
+    fn render_sync_report(&mut self, area: Rect, buf: &mut Buffer) {
+        let popup_area = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Percentage(15),
+                Constraint::Percentage(70),
+                Constraint::Percentage(15),
+            ])
+            .split(area)[1];
+
+        let popup_area = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Percentage(20),
+                Constraint::Percentage(60),
+                Constraint::Percentage(20),
+            ])
+            .split(popup_area)[1];
+
+        Clear.render(popup_area, buf);
+
+        let block = Block::new()
+            .title(Line::styled(" Sync Report ", Style::new().bold().yellow()))
+            .borders(Borders::ALL)
+            .border_style(FOCUSED_PANEL_STYLE)
+            .padding(Padding::horizontal(1));
+
+        let mut lines: Vec<Line> = self
+            .sync_errors
+            .iter()
+            .map(|e| Line::from(Span::styled(e.clone(), Style::new().red())))
+            .collect();
+
+        if !lines.is_empty() {
+            lines.push(Line::from(""));
+        }
+
+        // slowest venvs first
+        let mut timings = self.sync_timings.clone();
+        timings.sort_by(|(_, a), (_, b)| b.cmp(a));
+        lines.extend(timings.iter().map(|(path, elapsed)| {
+            Line::from(vec![
+                Span::styled(format!("{:>8.2?} ", elapsed), Style::new().cyan()),
+                Span::raw(venv_display_name(path)),
+                Span::styled(format!("  {}", path.display()), Style::new().dark_gray()),
+            ])
+        }));
+
+        if lines.is_empty() {
+            lines.push(Line::from(Span::styled(
+                "Nothing synced yet",
+                Style::new().magenta().italic(),
+            )));
+        }
+
+        Paragraph::new(lines)
+            .block(block)
+            .wrap(Wrap { trim: true })
+            .render(popup_area, buf);
+    }
+
     fn render_sync_text(&mut self, area: Rect, buf: &mut Buffer) {
         let block = Block::new()
             .title("Syncing")
@@ -579,3 +1232,89 @@ impl App {
             .render(area, buf);
     }
 }
+
+const fn category_color(category: Category) -> Color {
+    match category {
+        Category::Sources => Color::Green,
+        Category::Bytecode => Color::Yellow,
+        Category::Native => Color::Red,
+        Category::Libs => Color::Magenta,
+        Category::Data => Color::Blue,
+        Category::Tests => Color::Cyan,
+        Category::DistInfo => Color::Gray,
+    }
+}
+
+/// A bar of `width` cells split between the categories by their size, followed by a legend.
+fn breakdown_lines(
+    breakdown: &SizeBreakdown,
+    width: u16,
+    format: SizeFormat,
+) -> Vec<Line<'static>> {
+    let total = breakdown.total();
+    if total == 0 || width == 0 {
+        return Vec::new();
+    }
+
+    // floor every share, then hand the leftover cells to the largest remainders
+    let shares: Vec<(Category, u64)> = breakdown.iter().collect();
+    let mut cells: Vec<u64> = shares
+        .iter()
+        .map(|(_, size)| size * width as u64 / total)
+        .collect();
+    let mut leftover = width as u64 - cells.iter().sum::<u64>();
+    let mut by_remainder: Vec<usize> = (0..shares.len()).collect();
+    by_remainder.sort_by_key(|&i| std::cmp::Reverse(shares[i].1 * width as u64 % total));
+    for i in by_remainder {
+        if leftover == 0 {
+            break;
+        }
+        if shares[i].1 > 0 {
+            cells[i] += 1;
+            leftover -= 1;
+        }
+    }
+
+    let bar: Vec<Span> = shares
+        .iter()
+        .zip(&cells)
+        .filter(|(_, n)| **n > 0)
+        .map(|((category, _), n)| {
+            Span::styled(
+                "█".repeat(*n as usize),
+                Style::new().fg(category_color(*category)),
+            )
+        })
+        .collect();
+
+    let legend: Vec<Span> = shares
+        .iter()
+        .filter(|(_, size)| *size > 0)
+        .map(|(category, size)| {
+            Span::styled(
+                format!("■ {} {}  ", category.name(), format.format(*size)),
+                Style::new().fg(category_color(*category)),
+            )
+        })
+        .collect();
+
+    vec![Line::from(bar), Line::from(legend)]
+}
+
+/// Tags of the wheel a package was installed from and whether it's portable.
+fn wheel_summary(package: &Package) -> String {
+    let tags = package
+        .wheel
+        .as_ref()
+        .filter(|w| !w.tags.is_empty())
+        .map_or("none".to_string(), |w| w.tags.join(", "));
+    if package.native_files.is_empty() {
+        format!("{tags} ({})", package.portability().name())
+    } else {
+        format!(
+            "{tags} ({}, {} native files)",
+            package.portability().name(),
+            package.native_files.len()
+        )
+    }
+}
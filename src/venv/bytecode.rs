@@ -0,0 +1,225 @@
+//! Removing and generating the bytecode of a venv.
+
+use std::{fs, path::Path, process::Command};
+
+use color_eyre::{
+    Result,
+    eyre::{WrapErr, eyre},
+};
+use walkdir::WalkDir;
+
+use super::Venv;
+use crate::dir_size::HardlinkReader;
+
+/// What to do with the bytecode of a venv.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BytecodeAction {
+    Clean,
+    Compile,
+}
+
+/// What [`clean_bytecode`] removed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BytecodeCleanup {
+    /// Removed `__pycache__` directories
+    pub dirs: usize,
+    /// Removed `.pyc` files outside of `__pycache__`, next to their source
+    pub files: usize,
+    /// Space freed on disk, a file with several links under the removed paths is counted once
+    pub freed: u64,
+}
+
+/// Removes every `__pycache__` directory and stray `.pyc` file under `dir`. Python writes them
+/// again on the next import, or ahead of time with [`compile_bytecode`]. A `.pyc` outside of
+/// `__pycache__` without a `.py` next to it is a sourceless module, the only copy of its code,
+/// and is kept.
+pub fn clean_bytecode(dir: &Path) -> Result<BytecodeCleanup> {
+    let mut pycache_dirs = Vec::new();
+    let mut pyc_files = Vec::new();
+    let mut entries = WalkDir::new(dir).follow_links(false).into_iter();
+    while let Some(entry) = entries.next() {
+        let entry = entry?;
+        if entry.file_type().is_dir() && entry.file_name() == "__pycache__" {
+            entries.skip_current_dir();
+            pycache_dirs.push(entry.into_path());
+        } else if entry.file_type().is_file()
+            && entry.path().extension().is_some_and(|ext| ext == "pyc")
+            && entry.path().with_extension("py").is_file()
+        {
+            pyc_files.push(entry.into_path());
+        }
+    }
+
+    // everything is sized before anything is removed, a removed link would make the other
+    // links of a file look unique
+    let reader = HardlinkReader::new();
+    let mut freed = 0;
+    for path in pycache_dirs.iter().chain(&pyc_files) {
+        freed += reader.get_dir_usage(path)?.unique;
+    }
+
+    for path in &pycache_dirs {
+        fs::remove_dir_all(path).with_context(|| format!("Failed to remove {}", path.display()))?;
+    }
+    for path in &pyc_files {
+        fs::remove_file(path).with_context(|| format!("Failed to remove {}", path.display()))?;
+    }
+    Ok(BytecodeCleanup {
+        dirs: pycache_dirs.len(),
+        files: pyc_files.len(),
+        freed,
+    })
+}
+
+/// Writes the bytecode of everything in site-packages with the venv's own interpreter, so it
+/// matches the python version and doesn't have to be written on the first import.
+pub fn compile_bytecode(venv: &Venv) -> Result<()> {
+    let site_packages = venv.site_packages()?;
+    let interpreter = venv.interpreter();
+    let output = Command::new(&interpreter)
+        .args(["-m", "compileall", "-q", "-j", "0"])
+        .arg(&site_packages)
+        .output()
+        .with_context(|| format!("Failed to run {}", interpreter.display()))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        return Err(eyre!(
+            "compileall failed with {}: {}",
+            output.status,
+            if stderr.trim().is_empty() {
+                stdout.trim()
+            } else {
+                stderr.trim()
+            }
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::venv::test_utils::{mock_package, mock_venv};
+
+    #[test]
+    fn test_clean_bytecode() {
+        let root = tempdir().unwrap();
+        let site_packages = mock_venv(root.path());
+        mock_package(
+            &site_packages,
+            "alpha",
+            "1.0",
+            &[
+                ("alpha/__init__.py", "a = 1"),
+                ("alpha/__pycache__/__init__.cpython-313.pyc", "0123456789"),
+                ("alpha/sub/__pycache__/mod.cpython-313.pyc", "01234"),
+                ("alpha/old.py", "o = 0"),
+                ("alpha/old.pyc", "012"),
+            ],
+        );
+
+        let cleanup = clean_bytecode(root.path()).unwrap();
+        assert_eq!(
+            cleanup,
+            BytecodeCleanup {
+                dirs: 2,
+                files: 1,
+                freed: 18
+            }
+        );
+        assert!(site_packages.join("alpha/__init__.py").exists());
+        assert!(!site_packages.join("alpha/__pycache__").exists());
+        assert!(!site_packages.join("alpha/old.pyc").exists());
+        assert_eq!(
+            clean_bytecode(root.path()).unwrap(),
+            BytecodeCleanup::default()
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_clean_linked_bytecode() {
+        let root = tempdir().unwrap();
+        let site_packages = mock_venv(root.path());
+        mock_package(
+            &site_packages,
+            "alpha",
+            "1.0",
+            &[
+                ("alpha/__pycache__/__init__.cpython-313.pyc", "0123456789"),
+                ("alpha/init.py", "i = 0"),
+                ("alpha/old.py", "o = 0"),
+                ("alpha/older.py", "o = -1"),
+                ("alpha/old.pyc", "012"),
+            ],
+        );
+        let pycache = site_packages.join("alpha/__pycache__");
+        fs::hard_link(
+            pycache.join("__init__.cpython-313.pyc"),
+            site_packages.join("alpha/init.pyc"),
+        )
+        .unwrap();
+        fs::hard_link(
+            site_packages.join("alpha/old.pyc"),
+            site_packages.join("alpha/older.pyc"),
+        )
+        .unwrap();
+
+        // every file is counted once, whether it's in __pycache__ or not
+        let cleanup = clean_bytecode(root.path()).unwrap();
+        assert_eq!(
+            cleanup,
+            BytecodeCleanup {
+                dirs: 1,
+                files: 3,
+                freed: 13
+            }
+        );
+    }
+
+    #[test]
+    fn test_sourceless_modules_are_kept() {
+        let root = tempdir().unwrap();
+        let site_packages = mock_venv(root.path());
+        mock_package(
+            &site_packages,
+            "alpha",
+            "1.0",
+            &[
+                ("alpha/__init__.pyc", "0123456789"),
+                ("alpha/core.pyc", "012"),
+                ("alpha/__pycache__/stale.cpython-313.pyc", "01234"),
+            ],
+        );
+
+        let cleanup = clean_bytecode(root.path()).unwrap();
+        assert_eq!(
+            cleanup,
+            BytecodeCleanup {
+                dirs: 1,
+                files: 0,
+                freed: 5
+            }
+        );
+        assert!(site_packages.join("alpha/__init__.pyc").exists());
+        assert!(site_packages.join("alpha/core.pyc").exists());
+    }
+
+    #[test]
+    fn test_compile_without_interpreter() {
+        let root = tempdir().unwrap();
+        let site_packages = mock_venv(root.path());
+        mock_package(
+            &site_packages,
+            "alpha",
+            "1.0",
+            &[("alpha/__init__.py", "a = 1")],
+        );
+        let venv = Venv::from_path(root.path()).unwrap();
+        assert!(compile_bytecode(&venv).is_err());
+    }
+}
@@ -0,0 +1,165 @@
+//! Finding the files that more than one distribution claims in its `RECORD`.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use bincode::{Decode, Encode};
+use color_eyre::{Result, eyre::WrapErr};
+
+use super::record::Records;
+
+/// A file listed in the `RECORD` of several distributions. Uninstalling one of them removes the
+/// file from under the others.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct Conflict {
+    /// Path relative to the venv, so scripts in `bin` are shown as such
+    pub path: PathBuf,
+    /// The dist-info directories of the distributions that claim the file
+    pub owners: Vec<PathBuf>,
+}
+
+impl Conflict {
+    /// Names of the distributions that claim the file, from their dist-info directories.
+    pub fn owner_names(&self) -> impl Iterator<Item = &str> {
+        self.owners.iter().filter_map(|d| {
+            let name = d.file_name()?.to_str()?;
+            Some(name.split_once('-').map_or(name, |(name, _)| name))
+        })
+    }
+}
+
+/// Goes through the `RECORD` of every distribution in `site_packages` and returns the files
+/// claimed more than once. Distributions without a readable `RECORD` can't be checked and are
+/// skipped.
+pub fn find_conflicts(
+    venv_dir: &Path,
+    site_packages: &Path,
+    records: &mut Records,
+) -> Result<Vec<Conflict>> {
+    let mut claims: BTreeMap<PathBuf, Vec<PathBuf>> = BTreeMap::new();
+    let entries = fs::read_dir(site_packages)
+        .with_context(|| format!("Failed to read {}", site_packages.display()))?;
+
+    for entry in entries.filter_map(Result::ok) {
+        let dist_info = entry.path();
+        if dist_info.extension().is_none_or(|ext| ext != "dist-info") {
+            continue;
+        }
+        let Some(record) = records.read(&dist_info) else {
+            continue;
+        };
+        for e in record.iter().filter(|e| !e.is_dist_info()) {
+            let path = lexical_join(site_packages, Path::new(&e.path));
+            let owners = claims.entry(path).or_default();
+            // a RECORD listing the same file twice isn't a conflict
+            if owners.last() != Some(&dist_info) {
+                owners.push(dist_info.clone());
+            }
+        }
+    }
+
+    Ok(claims
+        .into_iter()
+        .filter(|(_, owners)| owners.len() > 1)
+        .map(|(path, mut owners)| {
+            owners.sort();
+            let path = path
+                .strip_prefix(venv_dir)
+                .map(Path::to_path_buf)
+                .unwrap_or(path);
+            Conflict { path, owners }
+        })
+        .collect())
+}
+
+/// Joins `path` to `base` and resolves the `..` without touching the file system, the file may
+/// not exist anymore.
+pub(crate) fn lexical_join(base: &Path, path: &Path) -> PathBuf {
+    let mut joined = base.to_path_buf();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                joined.pop();
+            }
+            Component::CurDir => {}
+            c => joined.push(c),
+        }
+    }
+    joined
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::venv::{
+        orphans::find_orphans,
+        test_utils::{mock_package, mock_venv},
+    };
+
+    #[test]
+    fn test_find_conflicts() {
+        let root = tempdir().unwrap();
+        let site_packages = mock_venv(root.path());
+        mock_package(
+            &site_packages,
+            "alpha",
+            "1.0",
+            &[("shared/__init__.py", "a = 1"), ("alpha/__init__.py", "")],
+        );
+        mock_package(
+            &site_packages,
+            "beta",
+            "2.0",
+            &[("shared/__init__.py", "b = 2"), ("beta/__init__.py", "")],
+        );
+        // both write the same script
+        for dist in ["alpha-1.0.dist-info", "beta-2.0.dist-info"] {
+            let record_path = site_packages.join(dist).join("RECORD");
+            let mut record = fs::read_to_string(&record_path).unwrap();
+            record.push_str("../../../bin/foo,sha256=abc,3\n");
+            fs::write(record_path, record).unwrap();
+        }
+
+        let conflicts =
+            find_conflicts(root.path(), &site_packages, &mut Records::default()).unwrap();
+        let paths: Vec<&Path> = conflicts.iter().map(|c| c.path.as_path()).collect();
+        let site_packages_rel = site_packages.strip_prefix(root.path()).unwrap();
+        assert_eq!(
+            paths,
+            [
+                Path::new("bin/foo"),
+                &site_packages_rel.join("shared/__init__.py")
+            ]
+        );
+        let owners: Vec<&str> = conflicts[0].owner_names().collect();
+        assert_eq!(owners, ["alpha", "beta"]);
+    }
+
+    #[test]
+    fn test_records_are_read_once() {
+        let root = tempdir().unwrap();
+        let site_packages = mock_venv(root.path());
+        for name in ["alpha", "beta"] {
+            mock_package(&site_packages, name, "1.0", &[("shared.py", name)]);
+        }
+
+        let mut records = Records::default();
+        find_orphans(&site_packages, &mut records).unwrap();
+        for dist in ["alpha-1.0.dist-info", "beta-1.0.dist-info"] {
+            fs::remove_file(site_packages.join(dist).join("RECORD")).unwrap();
+        }
+
+        let conflicts = find_conflicts(root.path(), &site_packages, &mut records).unwrap();
+        assert_eq!(conflicts.len(), 1);
+        assert!(
+            find_conflicts(root.path(), &site_packages, &mut Records::default())
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
@@ -8,6 +8,9 @@ pub enum MetadataTokens {
     Version(String),
     Summary(String),
     Dependency(String),
+    License(String),
+    LicenseExpression(String),
+    Classifier(String),
 }
 
 #[derive(Debug, Clone, Encode, Decode)]
@@ -16,6 +19,12 @@ pub struct Metadata {
     pub version: String,
     pub summary: String,
     pub dependencies: Option<HashSet<String>>,
+    /// Free text `License` field, often the whole license text in older distributions
+    pub license: Option<String>,
+    /// SPDX `License-Expression` field from metadata 2.4
+    pub license_expression: Option<String>,
+    /// `License ::` classifiers
+    pub license_classifiers: Vec<String>,
 }
 
 #[derive(Default)]
@@ -24,6 +33,9 @@ pub struct MetadataBuilder {
     pub version: Option<String>,
     pub summary: Option<String>,
     pub dependencies: Option<HashSet<String>>,
+    pub license: Option<String>,
+    pub license_expression: Option<String>,
+    pub license_classifiers: Vec<String>,
 }
 
 impl MetadataBuilder {
@@ -33,6 +45,9 @@ impl MetadataBuilder {
             version: None,
             summary: None,
             dependencies: None,
+            license: None,
+            license_expression: None,
+            license_classifiers: Vec::new(),
         }
     }
     pub fn name(&mut self, name: String) -> &mut Self {
@@ -51,12 +66,27 @@ impl MetadataBuilder {
         self.dependencies = Some(dependencies);
         self
     }
+    pub fn license(&mut self, license: String) -> &mut Self {
+        self.license = Some(license);
+        self
+    }
+    pub fn license_expression(&mut self, expression: String) -> &mut Self {
+        self.license_expression = Some(expression);
+        self
+    }
+    pub fn add_license_classifier(&mut self, classifier: String) -> &mut Self {
+        self.license_classifiers.push(classifier);
+        self
+    }
     pub fn build(&mut self) -> Metadata {
         Metadata {
             name: self.name.clone().unwrap_or_default(),
             version: self.version.clone().unwrap_or_default(),
             summary: self.summary.clone().unwrap_or_default(),
             dependencies: self.dependencies.clone(),
+            license: self.license.clone(),
+            license_expression: self.license_expression.clone(),
+            license_classifiers: self.license_classifiers.clone(),
         }
     }
 }
@@ -74,6 +104,15 @@ impl Metadata {
                     let dep_name = Self::parse_dependency(dep);
                     let _ = dependencies.insert(dep_name);
                 }
+                MetadataTokens::License(license) => builder = builder.license(license),
+                MetadataTokens::LicenseExpression(expression) => {
+                    builder = builder.license_expression(expression)
+                }
+                MetadataTokens::Classifier(classifier) => {
+                    if let Some(license) = classifier.strip_prefix("License :: ") {
+                        builder = builder.add_license_classifier(license.to_string());
+                    }
+                }
             }
         }
         if !dependencies.is_empty() {
@@ -102,7 +141,7 @@ impl Metadata {
         let mut split_index = 0;
         for (i, c) in dep.char_indices() {
             match c {
-                '>' | '=' | ' ' | '\n' | ';' | '!' | '<' => {
+                '>' | '=' | ' ' | '\n' | ';' | '!' | '<' | '~' | '[' | '(' | '@' => {
                     split_index = i;
                     break;
                 }
@@ -164,6 +203,14 @@ mod tests {
             "requests"
         );
         assert_eq!(Metadata::parse_dependency("numpy".to_string()), "numpy");
+        assert_eq!(
+            Metadata::parse_dependency("Lib.Core[fast]>=1".to_string()),
+            "Lib.Core"
+        );
+        assert_eq!(
+            Metadata::parse_dependency("pkg~=1.4; extra == \"x\"".to_string()),
+            "pkg"
+        );
     }
 
     #[test]
@@ -174,6 +221,9 @@ mod tests {
             MetadataTokens::Summary("This is a test.".to_string()),
             MetadataTokens::Dependency("requests>=2.0".to_string()),
             MetadataTokens::Dependency("click".to_string()),
+            MetadataTokens::LicenseExpression("MIT".to_string()),
+            MetadataTokens::Classifier("License :: OSI Approved :: MIT License".to_string()),
+            MetadataTokens::Classifier("Programming Language :: Python".to_string()),
         ];
 
         let metadata = Metadata::parse_tokens(tokens).unwrap();
@@ -187,6 +237,11 @@ mod tests {
             .cloned()
             .collect();
         assert_eq!(metadata.dependencies, Some(expected_deps));
+        assert_eq!(metadata.license_expression.as_deref(), Some("MIT"));
+        assert_eq!(
+            metadata.license_classifiers,
+            ["OSI Approved :: MIT License"]
+        );
     }
 
     #[test]
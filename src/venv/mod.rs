@@ -1,6 +1,15 @@
+pub mod bytecode;
+pub mod conflicts;
 pub mod metadata;
 pub mod model;
+pub mod orphans;
 pub mod parser;
+pub mod record;
 pub mod utils;
+pub mod version;
+pub mod wheel;
+
+#[cfg(test)]
+pub(crate) mod test_utils;
 
 pub use model::{Venv, VenvListUi};
@@ -1,4 +1,6 @@
 use std::{
+    collections::{HashMap, HashSet},
+    ffi::OsString,
     fs::{self},
     path::{Path, PathBuf},
     str::FromStr,
@@ -6,14 +8,25 @@ use std::{
     time::SystemTime,
 };
 
-use bincode::{Decode, Encode, config};
+use bincode::{
+    BorrowDecode, Decode, Encode, config,
+    de::{BorrowDecoder, Decoder},
+    enc::Encoder,
+    error::{DecodeError, EncodeError},
+};
 use chrono::{DateTime, Local};
 use color_eyre::Result;
 use color_eyre::eyre;
 use dirs::cache_dir;
 use ratatui::widgets::{ListState, ScrollbarState};
 
-use crate::venv::metadata::Metadata;
+use crate::dir_size::{DirMemo, DiskUsage, LinkedFile, SizeBreakdown, SkippedEntry, combine_usage};
+use crate::venv::{
+    conflicts::Conflict,
+    metadata::Metadata,
+    orphans::Orphan,
+    wheel::{Portability, WheelInfo, portability},
+};
 
 use super::parser::VenvParser;
 
@@ -26,6 +39,21 @@ pub struct Venv {
     pub num_dist_info_packages: i32,
     pub binaries: PathBuf,
     pub path: PathBuf,
+    /// Contents of `pyvenv.cfg`, used to recognize the venv after it's moved.
+    pub pyvenv_cfg: String,
+    /// Apparent, unique and allocated size of the whole venv
+    pub usage: DiskUsage,
+    pub breakdown: SizeBreakdown,
+    /// Directory sizes, so that only the changed directories are walked on the next refresh.
+    /// They're saved to a side file by the `VenvManager` and read back when the cache is loaded,
+    /// the cache file would mostly be made of them otherwise.
+    pub dir_memo: Uncached<DirMemo>,
+    /// Files in site-packages that no distribution owns
+    pub orphans: Vec<Orphan>,
+    /// Files that more than one distribution owns
+    pub conflicts: Vec<Conflict>,
+    /// Entries that couldn't be read and are left out of the sizes
+    pub skipped: Vec<SkippedEntry>,
 }
 
 #[derive(Debug, Clone)]
@@ -34,6 +62,32 @@ pub struct VenvUi {
     pub list_state: ListState,
     pub scroll_state: ScrollbarState,
     pub last_modified: DateTime<Local>,
+    /// The venv is cached but no longer on disk
+    pub missing: bool,
+}
+
+/// A field that isn't written to the cache file, it's decoded as the default value.
+#[derive(Debug, Clone, Default)]
+pub struct Uncached<T>(pub T);
+
+impl<T> Encode for Uncached<T> {
+    fn encode<E: Encoder>(&self, _encoder: &mut E) -> Result<(), EncodeError> {
+        Ok(())
+    }
+}
+
+impl<Context, T: Default> Decode<Context> for Uncached<T> {
+    fn decode<D: Decoder<Context = Context>>(_decoder: &mut D) -> Result<Self, DecodeError> {
+        Ok(Self::default())
+    }
+}
+
+impl<'de, Context, T: Default> BorrowDecode<'de, Context> for Uncached<T> {
+    fn borrow_decode<D: BorrowDecoder<'de, Context = Context>>(
+        _decoder: &mut D,
+    ) -> Result<Self, DecodeError> {
+        Ok(Self::default())
+    }
 }
 
 #[derive(Debug, Clone, Encode, Decode)]
@@ -43,6 +97,21 @@ pub struct Package {
     pub size: u64,
     pub metadata: Metadata,
     pub last_modified: SystemTime,
+    pub dist_info: PathBuf,
+    /// The package directory next to the dist-info, if one was found
+    pub package_dir: Option<PathBuf>,
+    /// Inode of the dist-info directory, it stays the same when the venv is moved
+    pub dist_info_ino: u64,
+    pub usage: DiskUsage,
+    /// Files of the package that have other hardlinks, possibly in other venvs. They're in the
+    /// directory records already, so they're restored from those when the venv is loaded, see
+    /// [`Venv::restore_linked`].
+    pub linked: Uncached<Vec<LinkedFile>>,
+    pub breakdown: SizeBreakdown,
+    /// Contents of `WHEEL`, if it was installed from a wheel
+    pub wheel: Option<WheelInfo>,
+    /// Extension modules and shared libraries, relative to site-packages
+    pub native_files: Vec<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -59,6 +128,7 @@ impl Package {
         size: u64,
         metadata: Metadata,
         last_modified: SystemTime,
+        dist_info: PathBuf,
     ) -> Self {
         Self {
             name: name.to_string(),
@@ -66,8 +136,30 @@ impl Package {
             size,
             metadata,
             last_modified,
+            dist_info,
+            package_dir: None,
+            dist_info_ino: 0,
+            usage: DiskUsage::default(),
+            linked: Uncached::default(),
+            breakdown: SizeBreakdown::default(),
+            wheel: None,
+            native_files: Vec::new(),
         }
     }
+
+    pub fn portability(&self) -> Portability {
+        portability(self.wheel.as_ref(), &self.native_files)
+    }
+}
+
+/// Usage of `packages` together. Each package is sized with a reader of its own, so the files
+/// they share through hardlinks are counted once here.
+pub fn packages_usage(packages: &[Package]) -> DiskUsage {
+    combine_usage(
+        packages
+            .iter()
+            .map(|pkg| (pkg.usage, pkg.linked.0.as_slice())),
+    )
 }
 
 impl Venv {
@@ -88,6 +180,56 @@ impl Venv {
             num_dist_info_packages,
             binaries,
             path,
+            pyvenv_cfg: String::new(),
+            usage: DiskUsage::default(),
+            breakdown: SizeBreakdown::default(),
+            dir_memo: Uncached::default(),
+            orphans: Vec::new(),
+            conflicts: Vec::new(),
+            skipped: Vec::new(),
+        }
+    }
+
+    /// Files that `pkg` shares with other distributions.
+    pub fn conflicts_of<'a>(&'a self, pkg: &'a Package) -> impl Iterator<Item = &'a Conflict> {
+        self.conflicts
+            .iter()
+            .filter(|c| c.owners.contains(&pkg.dist_info))
+    }
+
+    /// Total size of the files in site-packages that no distribution owns.
+    pub fn orphaned_size(&self) -> u64 {
+        self.orphans.iter().map(|o| o.size).sum()
+    }
+
+    /// Usage of each package along with its hardlinked files, to be combined with
+    /// [`combine_usage`](crate::dir_size::combine_usage). Files outside of the packages
+    /// (binaries, the interpreter, caches) are counted as unique.
+    pub fn usage_parts(&self) -> impl Iterator<Item = (DiskUsage, &[LinkedFile])> {
+        let rest = self.usage - packages_usage(&self.packages);
+        self.packages
+            .iter()
+            .map(|pkg| (pkg.usage, pkg.linked.0.as_slice()))
+            .chain(std::iter::once((rest, &[] as &[LinkedFile])))
+    }
+
+    /// Bytes of the venv that are in files with other hardlinks.
+    pub fn linked_size(&self) -> u64 {
+        let mut seen = HashSet::new();
+        self.packages
+            .iter()
+            .flat_map(|pkg| &pkg.linked.0)
+            .filter(|f| seen.insert((f.dev, f.ino)))
+            .map(|f| f.size)
+            .sum()
+    }
+
+    /// Gives the packages back the hardlinked files that aren't in the cache file, from the
+    /// directory records of their package directory and dist-info.
+    pub fn restore_linked(&mut self) {
+        for pkg in &mut self.packages {
+            let roots = pkg.package_dir.iter().chain([&pkg.dist_info]);
+            pkg.linked.0 = self.dir_memo.0.linked_files(roots.map(PathBuf::as_path));
         }
     }
 
@@ -95,6 +237,11 @@ impl Venv {
         VenvParser::parse_from_dir(path.to_path_buf())
     }
 
+    /// Re-parses the venv, reusing the packages that haven't changed since it was parsed.
+    pub fn refresh(&self) -> Result<Self> {
+        VenvParser::parse_incremental(self)
+    }
+
     pub fn from_venvs_dir(path: &Path) -> Result<Vec<Self>> {
         if !path.is_dir() {
             return Err(eyre::eyre!("{} is not a directory", path.display()));
@@ -120,6 +267,7 @@ impl Venv {
         fs::read_dir(path)?
             .filter_map(Result::ok)
             .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "bin"))
             .map(|path| Venv::load_cache(&path))
             .collect()
     }
@@ -155,10 +303,79 @@ impl Venv {
         Ok(decoded)
     }
 
+    pub fn site_packages(&self) -> Result<PathBuf> {
+        VenvParser::new(self.path.clone()).site_packages_path()
+    }
+
+    /// Most recent modification time among the packages of the venv.
+    pub fn last_modified(&self) -> SystemTime {
+        self.packages
+            .iter()
+            .map(|pkg| pkg.last_modified)
+            .max()
+            .unwrap_or(SystemTime::UNIX_EPOCH)
+    }
+
+    /// Tells whether `dir` holds this same venv, i.e. it has an identical `pyvenv.cfg` and the
+    /// same set of installed distributions. Used to recognize venvs that have been moved.
+    pub fn is_same_venv_at(&self, dir: &Path) -> bool {
+        if self.pyvenv_cfg.is_empty() {
+            return false;
+        }
+        match fs::read_to_string(dir.join("pyvenv.cfg")) {
+            Ok(cfg) if cfg == self.pyvenv_cfg => {}
+            _ => return false,
+        }
+        let Ok(parser) = VenvParser::new(dir.to_path_buf()).discover_packages() else {
+            return false;
+        };
+        let on_disk: HashSet<OsString> = parser
+            .dist_info_packages
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|d| d.file_name().map(|n| n.to_os_string()))
+            .collect();
+
+        on_disk == self.dist_info_names()
+    }
+
+    /// Tells whether `other` is this venv at a different path. A venv that was created again
+    /// with the same packages has the same `pyvenv.cfg`, but its dist-info directories are new
+    /// ones with other inodes.
+    pub fn is_same_venv(&self, other: &Venv) -> bool {
+        let dist_info_ids = |venv: &Venv| -> HashSet<(OsString, u64)> {
+            venv.packages
+                .iter()
+                .filter_map(|pkg| {
+                    Some((pkg.dist_info.file_name()?.to_os_string(), pkg.dist_info_ino))
+                })
+                .collect()
+        };
+        !self.pyvenv_cfg.is_empty()
+            && self.pyvenv_cfg == other.pyvenv_cfg
+            && dist_info_ids(self) == dist_info_ids(other)
+    }
+
+    fn dist_info_names(&self) -> HashSet<OsString> {
+        self.packages
+            .iter()
+            .filter_map(|pkg| pkg.dist_info.file_name().map(|n| n.to_os_string()))
+            .collect()
+    }
+
     pub fn activation_path(&self) -> PathBuf {
         self.binaries.clone()
     }
 
+    /// The python executable of the venv.
+    pub fn interpreter(&self) -> PathBuf {
+        if cfg!(windows) {
+            self.binaries.join("python.exe")
+        } else {
+            self.binaries.join("python")
+        }
+    }
+
     pub fn requirements(&self) -> PathBuf {
         PathBuf::from_str(&self.name)
             .unwrap()
@@ -173,30 +390,108 @@ impl VenvUi {
             scroll_state: ScrollbarState::new(venv.packages.len()),
             list_state: ListState::default().with_selected(Some(0)),
             last_modified,
+            missing: false,
             venv,
         }
     }
+
+    pub fn from_venv(venv: Arc<Venv>) -> Self {
+        let date: DateTime<Local> = venv.last_modified().into();
+        Self::new(venv, date)
+    }
+
+    /// Swaps in a re-parsed venv while keeping the selected package in view.
+    pub fn set_venv(&mut self, venv: Arc<Venv>) {
+        let num_packages = venv.packages.len();
+        self.last_modified = venv.last_modified().into();
+        self.scroll_state = self.scroll_state.content_length(num_packages);
+        if let Some(i) = self.list_state.selected() {
+            self.list_state
+                .select(Some(i.min(num_packages.saturating_sub(1))));
+        }
+        self.venv = venv;
+    }
 }
 
 impl VenvListUi {
     pub fn new(venvs: Vec<Arc<Venv>>) -> Self {
-        let venvs_ui: Vec<VenvUi> = venvs
-            .into_iter()
-            .map(|v| {
-                let last_modified = v
-                    .packages
-                    .iter()
-                    .map(|pkg| pkg.last_modified)
-                    .max()
-                    .unwrap_or(SystemTime::UNIX_EPOCH);
-                let date: DateTime<Local> = last_modified.into();
-                VenvUi::new(v, date)
-            })
-            .collect();
+        let venvs_ui: Vec<VenvUi> = venvs.into_iter().map(VenvUi::from_venv).collect();
         Self {
             list_state: ListState::default().with_selected(Some(0)),
             scroll_state: ScrollbarState::new(venvs_ui.len()),
             venvs: venvs_ui,
         }
     }
+
+    /// Replaces the listed venvs with `venvs`. Venvs that were already listed keep their
+    /// selection and scroll state.
+    pub fn update(&mut self, venvs: Vec<Arc<Venv>>) {
+        let mut old: HashMap<PathBuf, VenvUi> = self
+            .venvs
+            .drain(..)
+            .map(|vui| (vui.venv.path.clone(), vui))
+            .collect();
+
+        self.venvs = venvs
+            .into_iter()
+            .map(|v| match old.remove(&v.path) {
+                Some(mut vui) => {
+                    vui.set_venv(v);
+                    vui
+                }
+                None => VenvUi::from_venv(v),
+            })
+            .collect();
+        self.scroll_state = self.scroll_state.content_length(self.venvs.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::venv::metadata::MetadataBuilder;
+
+    fn venv_with_packages(name: &str, num_packages: usize) -> Arc<Venv> {
+        let packages = (0..num_packages)
+            .map(|i| {
+                Package::new(
+                    &format!("pkg{i}"),
+                    "1.0",
+                    0,
+                    MetadataBuilder::default().build(),
+                    SystemTime::UNIX_EPOCH,
+                    PathBuf::from(format!("pkg{i}-1.0.dist-info")),
+                )
+            })
+            .collect();
+        Arc::new(Venv::new(
+            name,
+            "3.13.2".to_string(),
+            0,
+            packages,
+            num_packages as i32,
+            PathBuf::from(name).join("bin"),
+            PathBuf::from(name),
+        ))
+    }
+
+    #[test]
+    fn test_update_keeps_package_selection() {
+        let mut list =
+            VenvListUi::new(vec![venv_with_packages("a", 5), venv_with_packages("b", 5)]);
+        list.venvs[0].list_state.select(Some(3));
+        list.venvs[1].list_state.select(Some(4));
+
+        // "b" lost two packages, "c" is new
+        list.update(vec![
+            venv_with_packages("a", 5),
+            venv_with_packages("b", 3),
+            venv_with_packages("c", 1),
+        ]);
+
+        assert_eq!(list.venvs.len(), 3);
+        assert_eq!(list.venvs[0].list_state.selected(), Some(3));
+        assert_eq!(list.venvs[1].list_state.selected(), Some(2));
+        assert_eq!(list.venvs[2].list_state.selected(), Some(0));
+    }
 }
@@ -0,0 +1,266 @@
+//! Finding the files in site-packages that no installed distribution owns.
+
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use bincode::{Decode, Encode};
+use color_eyre::{Result, eyre::WrapErr};
+
+use super::record::Records;
+use crate::dir_size::HardlinkReader;
+
+/// A file or directory in site-packages that isn't listed by any distribution. Usually left
+/// behind by a failed uninstall, copied in by hand or written by `setup.py develop`.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct Orphan {
+    /// Path relative to site-packages
+    pub path: PathBuf,
+    pub is_dir: bool,
+    pub size: u64,
+}
+
+/// Paths the installed distributions claim.
+#[derive(Debug, Default)]
+struct Owned {
+    files: HashSet<PathBuf>,
+    /// Ancestors of the owned files, listed to find orphans inside them
+    dirs: HashSet<PathBuf>,
+    /// Directories owned with everything in them
+    trees: HashSet<PathBuf>,
+}
+
+impl Owned {
+    fn add_file(&mut self, path: PathBuf) {
+        self.dirs
+            .extend(path.ancestors().skip(1).map(Path::to_path_buf));
+        self.files.insert(path);
+    }
+
+    fn add_tree(&mut self, path: PathBuf) {
+        self.dirs
+            .extend(path.ancestors().skip(1).map(Path::to_path_buf));
+        self.trees.insert(path);
+    }
+
+    /// Bytecode in `__pycache__` is often written after install and left out of `RECORD`, it
+    /// belongs to whoever owns its source.
+    fn owns_bytecode(&self, path: &Path) -> bool {
+        let Some(cache_dir) = path.parent() else {
+            return false;
+        };
+        if cache_dir.file_name().is_none_or(|n| n != "__pycache__") {
+            return false;
+        }
+        let Some(module) = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .and_then(|n| n.split('.').next())
+        else {
+            return false;
+        };
+        let source_dir = cache_dir.parent().unwrap_or(Path::new(""));
+        self.files
+            .contains(&source_dir.join(format!("{module}.py")))
+    }
+}
+
+/// Lists everything in `site_packages` that isn't in the `RECORD` of an installed distribution.
+/// Whole directories are reported once instead of file by file. Legacy `.egg-info` installs
+/// are matched with `installed-files.txt`, and distributions without either file own the
+/// names in their `top_level.txt`. Directories that can't be read are left out, the size walk
+/// reports them already.
+pub fn find_orphans(site_packages: &Path, records: &mut Records) -> Result<Vec<Orphan>> {
+    let owned = owned_paths(site_packages, records)?;
+    let reader = HardlinkReader::new();
+    let mut orphans = Vec::new();
+    collect_orphans(site_packages, Path::new(""), &owned, &reader, &mut orphans)
+        .with_context(|| format!("Failed to read {}", site_packages.display()))?;
+    orphans.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(orphans)
+}
+
+fn owned_paths(site_packages: &Path, records: &mut Records) -> Result<Owned> {
+    let mut owned = Owned::default();
+    let entries = fs::read_dir(site_packages)
+        .with_context(|| format!("Failed to read {}", site_packages.display()))?;
+
+    for entry in entries.filter_map(Result::ok) {
+        let name = entry.file_name();
+        let Some(name) = name.to_str() else { continue };
+        let meta_dir = entry.path();
+        if name.ends_with(".dist-info") {
+            owned.add_tree(PathBuf::from(name));
+            match records.read(&meta_dir) {
+                Some(record) => record
+                    .iter()
+                    .filter_map(|e| normalize(Path::new(&e.path)))
+                    .for_each(|p| owned.add_file(p)),
+                None => add_top_level(&mut owned, &meta_dir),
+            }
+        } else if name.ends_with(".egg-info") {
+            owned.add_tree(PathBuf::from(name));
+            match fs::read_to_string(meta_dir.join("installed-files.txt")) {
+                Ok(files) => files
+                    .lines()
+                    .filter_map(|line| normalize(&Path::new(name).join(line.trim())))
+                    .for_each(|p| owned.add_file(p)),
+                Err(_) => add_top_level(&mut owned, &meta_dir),
+            }
+        }
+    }
+    Ok(owned)
+}
+
+fn add_top_level(owned: &mut Owned, meta_dir: &Path) {
+    let Ok(top_level) = fs::read_to_string(meta_dir.join("top_level.txt")) else {
+        return;
+    };
+    for name in top_level.lines().map(str::trim).filter(|n| !n.is_empty()) {
+        owned.add_tree(PathBuf::from(name));
+        owned.add_file(PathBuf::from(format!("{name}.py")));
+    }
+}
+
+/// Resolves `..` in a path relative to site-packages. Paths that end up outside of it, like
+/// scripts in `bin`, give `None`.
+fn normalize(path: &Path) -> Option<PathBuf> {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(c) => normalized.push(c),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    return None;
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    (!normalized.as_os_str().is_empty()).then_some(normalized)
+}
+
+/// Only fails if site-packages itself can't be listed, everything below it is best effort.
+fn collect_orphans(
+    site_packages: &Path,
+    relative: &Path,
+    owned: &Owned,
+    reader: &HardlinkReader,
+    orphans: &mut Vec<Orphan>,
+) -> std::io::Result<()> {
+    let dir = site_packages.join(relative);
+    for entry in fs::read_dir(&dir)?.filter_map(Result::ok) {
+        let path = relative.join(entry.file_name());
+        if owned.trees.contains(&path) || owned.files.contains(&path) {
+            continue;
+        }
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        let is_cache = entry.file_name() == "__pycache__";
+        if file_type.is_dir() && (owned.dirs.contains(&path) || is_cache) {
+            let _ = collect_orphans(site_packages, &path, owned, reader, orphans);
+        } else if file_type.is_dir() {
+            orphans.push(Orphan {
+                size: reader
+                    .get_dir_usage(&entry.path())
+                    .map_or(0, |usage| usage.apparent),
+                path,
+                is_dir: true,
+            });
+        } else if !owned.owns_bytecode(&path) {
+            orphans.push(Orphan {
+                size: entry.metadata().map_or(0, |m| m.len()),
+                path,
+                is_dir: false,
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::venv::test_utils::{mock_package, mock_venv};
+
+    #[test]
+    fn test_find_orphans() {
+        let root = tempdir().unwrap();
+        let site_packages = mock_venv(root.path());
+        mock_package(
+            &site_packages,
+            "alpha",
+            "1.0",
+            &[("alpha/__init__.py", "a = 1"), ("alpha/core.py", "b = 2")],
+        );
+        let write = |path: &str, contents: &str| {
+            let path = site_packages.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        };
+        // bytecode of owned sources isn't an orphan
+        write("alpha/__pycache__/core.cpython-313.pyc", "0123");
+        write("alpha/__pycache__/gone.cpython-313.pyc", "01234");
+        write("alpha/patched.py", "c = 3");
+        write("leftover/__init__.py", "0123456789");
+        write("leftover/sub/mod.py", "01234");
+        write("easy-install.pth", "/src/project");
+
+        let orphans = find_orphans(&site_packages, &mut Records::default()).unwrap();
+        let found: Vec<(&str, bool, u64)> = orphans
+            .iter()
+            .map(|o| (o.path.to_str().unwrap(), o.is_dir, o.size))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("alpha/__pycache__/gone.cpython-313.pyc", false, 5),
+                ("alpha/patched.py", false, 5),
+                ("easy-install.pth", false, 12),
+                ("leftover", true, 15),
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unreadable_dir_is_left_out() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = tempdir().unwrap();
+        let site_packages = mock_venv(root.path());
+        mock_package(
+            &site_packages,
+            "alpha",
+            "1.0",
+            &[("alpha/__init__.py", "a = 1"), ("alpha/data/blob.bin", "0")],
+        );
+        fs::write(site_packages.join("alpha/patched.py"), "c = 3").unwrap();
+        let locked = site_packages.join("alpha/data");
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+        // root can read anything, there's nothing to leave out then
+        if fs::read_dir(&locked).is_ok() {
+            return;
+        }
+
+        let orphans = find_orphans(&site_packages, &mut Records::default()).unwrap();
+        let paths: Vec<&Path> = orphans.iter().map(|o| o.path.as_path()).collect();
+        assert_eq!(paths, [Path::new("alpha/patched.py")]);
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(
+            normalize(Path::new("a.egg-info/../a/__init__.py")),
+            Some(PathBuf::from("a/__init__.py"))
+        );
+        assert_eq!(normalize(Path::new("../../../bin/tool")), None);
+    }
+}
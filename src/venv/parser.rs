@@ -1,11 +1,13 @@
 use std::{
+    collections::HashMap,
     fs::{self, File},
-    io::{BufRead, BufReader},
-    path::PathBuf,
+    io::{self, BufRead, BufReader},
+    path::{Path, PathBuf},
+    sync::Arc,
     time::SystemTime,
 };
 
-use crate::dir_size::{self, Chonk};
+use crate::dir_size::{self, DirMemo, DiskUsage, SizeBreakdown, SkippedEntry};
 use color_eyre::{
     eyre::{self, Result, WrapErr},
     owo_colors::OwoColorize,
@@ -13,9 +15,13 @@ use color_eyre::{
 
 use crate::venv::{
     Venv,
+    conflicts::find_conflicts,
     metadata::{Metadata, MetadataBuilder, MetadataTokens},
-    model::Package,
+    model::{Package, Uncached, packages_usage},
+    orphans::find_orphans,
+    record::Records,
     utils::get_python_dir,
+    wheel::{find_native_files, read_wheel},
 };
 
 use super::utils::{get_packages, package_pairs};
@@ -118,18 +124,151 @@ impl VenvParser {
         let venv_name = self.venv_name();
         let version = self.version.clone().unwrap();
         let binaries = self.binaries_path();
+        let site_packages = self.site_packages_path()?;
 
+        let mut memo = DirMemo::default();
+        let mut records = Records::default();
         let pairs = package_pairs(self.dist_info_packages.unwrap(), self.package_dirs.unwrap());
-        let (packages, num_pkg) =
-            parse_package_pairs(pairs).context("Error while parsing pairs")?;
+        // the walk of the whole venv below runs into the same unreadable entries
+        let (packages, num_pkg) = parse_package_pairs(
+            pairs,
+            &Arc::default(),
+            &mut memo,
+            &mut Vec::new(),
+            &mut records,
+        )
+        .context("Error while parsing pairs")?;
+        let orphans = best_effort(
+            "Could not look for orphaned files",
+            find_orphans(&site_packages, &mut records),
+        );
+        let conflicts = best_effort(
+            "Could not look for conflicting files",
+            find_conflicts(&self.dir, &site_packages, &mut records),
+        );
 
-        let venv_size = dir_size::ParallelReader
-            .get_dir_size(&self.dir)
+        let reader = dir_size::HardlinkReader::new();
+        let (usage, breakdown) = reader
+            .get_dir_breakdown(&self.dir, &self.dir)
             .context("Could not get venv size")?;
+        memo.update(reader.take_visited());
+        let skipped = reader.take_skipped();
+        let venv_size = usage.apparent;
 
-        let v = Venv::new(
+        let mut v = Venv::new(
             &venv_name, version, venv_size, packages, num_pkg, binaries, self.dir,
         );
+        v.pyvenv_cfg = self.cfg.unwrap_or_default();
+        v.usage = usage;
+        v.breakdown = breakdown;
+        v.dir_memo = Uncached(memo);
+        v.orphans = orphans;
+        v.conflicts = conflicts;
+        v.skipped = skipped;
+        Ok(v)
+    }
+
+    /// Re-parses only the packages that were added or changed since `cached` was parsed.
+    /// Packages whose `.dist-info` directory still has the same modification time are carried
+    /// over from `cached`, and the venv size is adjusted by the difference instead of walking
+    /// the whole venv again.
+    pub fn parse_incremental(cached: &Venv) -> Result<Venv> {
+        let parser = VenvParser::new(cached.path.clone())
+            .read_config()?
+            .parse_version()?
+            .discover_packages()?;
+
+        let venv_name = parser.venv_name();
+        let version = parser.version.clone().unwrap();
+        let binaries = parser.binaries_path();
+        let site_packages = parser.site_packages_path();
+
+        let cached_packages: HashMap<&Path, &Package> = cached
+            .packages
+            .iter()
+            .map(|pkg| (pkg.dist_info.as_path(), pkg))
+            .collect();
+
+        let pairs = package_pairs(
+            parser.dist_info_packages.unwrap(),
+            parser.package_dirs.unwrap(),
+        );
+
+        let mut packages = Vec::with_capacity(pairs.len());
+        let cached_memo = Arc::new(cached.dir_memo.0.clone());
+        let mut memo = cached.dir_memo.0.clone();
+        let mut records = Records::default();
+        let mut skipped: Vec<SkippedEntry> = cached
+            .skipped
+            .iter()
+            .filter(|entry| still_unreadable(entry))
+            .cloned()
+            .collect();
+        let mut kept_breakdown = SizeBreakdown::default();
+        let mut new_breakdown = SizeBreakdown::default();
+
+        for (pkg, dist_info) in &pairs {
+            let Some(d) = dist_info else { continue };
+
+            let unchanged = cached_packages.get(d.as_path()).filter(|cached_pkg| {
+                fs::metadata(d)
+                    .and_then(|m| m.modified())
+                    .is_ok_and(|modified| modified == cached_pkg.last_modified)
+            });
+
+            if let Some(cached_pkg) = unchanged {
+                kept_breakdown += cached_pkg.breakdown;
+                packages.push((*cached_pkg).clone());
+            } else if let Some(package) = parse_package_pair(
+                pkg,
+                dist_info,
+                &cached_memo,
+                &mut memo,
+                &mut skipped,
+                &mut records,
+            )? {
+                new_breakdown += package.breakdown;
+                packages.push(package);
+            }
+        }
+
+        // removed packages aren't walked, so their records have to be dropped separately
+        let site_packages = site_packages?;
+        memo.prune_missing_children(&site_packages);
+        // the RECORDs of the kept packages are read here, the others were read while parsing
+        let orphans = best_effort(
+            "Could not look for orphaned files",
+            find_orphans(&site_packages, &mut records),
+        );
+        let conflicts = best_effort(
+            "Could not look for conflicting files",
+            find_conflicts(&parser.dir, &site_packages, &mut records),
+        );
+
+        // the files outside of the packages are as they were, the packages are combined again
+        // since the removed ones might have shared files with the kept ones
+        let usage = cached.usage - packages_usage(&cached.packages) + packages_usage(&packages);
+        // everything that isn't kept was either removed or re-parsed
+        let cached_breakdown = cached
+            .packages
+            .iter()
+            .fold(SizeBreakdown::default(), |acc, pkg| acc + pkg.breakdown);
+        let breakdown = cached.breakdown + new_breakdown - (cached_breakdown - kept_breakdown);
+        let venv_size = usage.apparent;
+        let num_pkg = packages.len() as i32;
+
+        let mut v = Venv::new(
+            &venv_name, version, venv_size, packages, num_pkg, binaries, parser.dir,
+        );
+        v.pyvenv_cfg = parser.cfg.unwrap_or_default();
+        v.usage = usage;
+        v.breakdown = breakdown;
+        v.dir_memo = Uncached(memo);
+        v.orphans = orphans;
+        v.conflicts = conflicts;
+        skipped.sort_by(|a, b| a.path.cmp(&b.path));
+        skipped.dedup_by(|a, b| a.path == b.path);
+        v.skipped = skipped;
         Ok(v)
     }
 
@@ -154,7 +293,7 @@ impl VenvParser {
         }
     }
 
-    fn site_packages_path(&self) -> Result<PathBuf> {
+    pub fn site_packages_path(&self) -> Result<PathBuf> {
         let lib_dir = self.lib_path();
         if cfg!(windows) {
             Ok(lib_dir.join("site-packages"))
@@ -178,10 +317,25 @@ pub fn parse_metadata(dist_info_path: PathBuf) -> Result<Metadata> {
     let reader = BufReader::new(file);
 
     let mut tokens = Vec::new();
+    let mut folding = false;
 
     for line in reader.lines() {
         let line = line?;
         let line = line.trim_end_matches(&['\r', '\n'][..]); // normalize newlines
+        // the headers end at the first blank line, the description follows
+        if line.is_empty() {
+            break;
+        }
+        // a folded header goes on in lines that start with whitespace, setuptools writes long
+        // `License` fields like that
+        if line.starts_with([' ', '\t']) {
+            if let Some(MetadataTokens::License(license)) = tokens.last_mut().filter(|_| folding) {
+                license.push('\n');
+                license.push_str(line.trim());
+            }
+            continue;
+        }
+        folding = line.starts_with("License: ");
 
         if let Some((key, value)) = line.split_once(": ") {
             match key {
@@ -189,6 +343,11 @@ pub fn parse_metadata(dist_info_path: PathBuf) -> Result<Metadata> {
                 "Version" => tokens.push(MetadataTokens::Version(value.to_string())),
                 "Summary" => tokens.push(MetadataTokens::Summary(value.to_string())),
                 "Requires-Dist" => tokens.push(MetadataTokens::Dependency(value.to_string())),
+                "License" => tokens.push(MetadataTokens::License(value.to_string())),
+                "License-Expression" => {
+                    tokens.push(MetadataTokens::LicenseExpression(value.to_string()))
+                }
+                "Classifier" => tokens.push(MetadataTokens::Classifier(value.to_string())),
                 _ => {}
             }
         }
@@ -200,50 +359,87 @@ pub fn parse_metadata(dist_info_path: PathBuf) -> Result<Metadata> {
 
 fn parse_package_pairs(
     pairs: Vec<(Option<PathBuf>, Option<PathBuf>)>,
+    cached_memo: &Arc<DirMemo>,
+    memo: &mut DirMemo,
+    skipped: &mut Vec<SkippedEntry>,
+    records: &mut Records,
 ) -> Result<(Vec<Package>, i32)> {
     let mut packages: Vec<Package> = Vec::new();
     let mut num_pkg = 0;
 
     for (pkg, dist_info) in &pairs {
-        // println!("Pkg: {}", pkg.to_str().unwrap());
-        let metadata = if let Some(d) = get_metadata(dist_info) {
+        if let Some(package) =
+            parse_package_pair(pkg, dist_info, cached_memo, memo, skipped, records)?
+        {
             num_pkg += 1;
-            d
-        } else {
-            continue;
-        };
-
-        let package_size = get_package_size(pkg);
-
-        let dist_info_size = if let Some(d) = dist_info {
-            dir_size::ParallelReader
-                .get_dir_size(d)
-                .context("Could not get dist-info size")?
-        } else {
-            0
-        };
-
-        let last_modified = if let Some(d) = dist_info {
-            fs::metadata(d)?.modified()?
-        } else {
-            SystemTime::now()
-        };
-
-        let package = Package::new(
-            &metadata.name,
-            &metadata.version,
-            package_size + dist_info_size,
-            metadata.clone(),
-            last_modified,
-        );
-        // println!("pck: {:?}", package);
-
-        packages.push(package);
+            packages.push(package);
+        }
     }
     // TODO: after all packages are done, go through them again and insert their dependencies
     Ok((packages, num_pkg))
 }
 
+/// Parses a single package and dist-info pair. Pairs without a dist-info are skipped and
+/// return `None`. Directories unchanged since `cached_memo` was made aren't listed again, and
+/// the records of the walked ones are added to `memo`. Entries that can't be read are added to
+/// `skipped`, and the `RECORD` of the pair stays in `records` for the checks of the whole venv.
+fn parse_package_pair(
+    pkg: &Option<PathBuf>,
+    dist_info: &Option<PathBuf>,
+    cached_memo: &Arc<DirMemo>,
+    memo: &mut DirMemo,
+    skipped: &mut Vec<SkippedEntry>,
+    records: &mut Records,
+) -> Result<Option<Package>> {
+    let (metadata, d) = match (get_metadata(dist_info), dist_info) {
+        (Some(m), Some(d)) => (m, d),
+        _ => return Ok(None),
+    };
+
+    // one reader for both so files linked between them are counted once
+    let reader = dir_size::HardlinkReader::with_memo(Arc::clone(cached_memo));
+    let site_packages = d.parent().unwrap_or(d);
+    let (package_usage, package_breakdown) =
+        get_package_usage(&reader, pkg, site_packages, skipped);
+
+    let (dist_info_usage, dist_info_breakdown) = reader
+        .get_dir_breakdown(d, site_packages)
+        .context("Could not get dist-info size")?;
+    let usage = package_usage + dist_info_usage;
+    memo.update(reader.take_visited());
+    skipped.extend(reader.take_skipped());
+
+    let dist_info_meta = fs::metadata(d)?;
+    let last_modified = dist_info_meta.modified()?;
+
+    let mut package = Package::new(
+        &metadata.name,
+        &metadata.version,
+        usage.apparent,
+        metadata.clone(),
+        last_modified,
+        d.to_path_buf(),
+    );
+    package.package_dir = pkg.clone();
+    package.dist_info_ino = dir_size::dir_ino(&dist_info_meta);
+    package.usage = usage;
+    package.linked.0 = reader.linked_files();
+    package.breakdown = package_breakdown + dist_info_breakdown;
+    package.wheel = read_wheel(d);
+    package.native_files = find_native_files(records.read(d), d, pkg.as_ref());
+
+    Ok(Some(package))
+}
+
+/// Findings of a check that the venv can do without. A failed check is printed and finds
+/// nothing instead of failing the whole parse.
+fn best_effort<T: Default>(what: &str, res: Result<T>) -> T {
+    res.unwrap_or_else(|err| {
+        eprintln!("{}: {:#}", what.red().bold(), err.red().italic());
+        T::default()
+    })
+}
+
 fn get_metadata(dist_info: &Option<PathBuf>) -> Option<Metadata> {
     if let Some(d) = dist_info {
         match parse_metadata(d.to_path_buf())
@@ -265,22 +461,35 @@ fn get_metadata(dist_info: &Option<PathBuf>) -> Option<Metadata> {
     }
 }
 
-fn get_package_size(pkg: &Option<PathBuf>) -> u64 {
+/// Whether an entry skipped by an earlier parse still can't be read. Entries that no longer
+/// exist were removed along with their package.
+fn still_unreadable(entry: &SkippedEntry) -> bool {
+    match fs::symlink_metadata(&entry.path) {
+        Ok(meta) if meta.is_dir() => fs::read_dir(&entry.path).is_err(),
+        Ok(_) => false,
+        Err(e) => e.kind() != io::ErrorKind::NotFound,
+    }
+}
+
+fn get_package_usage(
+    reader: &dir_size::HardlinkReader,
+    pkg: &Option<PathBuf>,
+    site_packages: &Path,
+    skipped: &mut Vec<SkippedEntry>,
+) -> (DiskUsage, SizeBreakdown) {
     if let Some(p) = pkg {
-        match dir_size::ParallelReader.get_dir_size(p) {
-            Ok(sz) => sz,
+        match reader.get_dir_breakdown(p, site_packages) {
+            Ok(usage) => usage,
             Err(err) => {
-                eprintln!(
-                    "{} {}: {:#}",
-                    "Error while calculating size: ".red().bold(),
-                    p.display().yellow(),
-                    err.red().italic()
-                );
-                0
+                skipped.push(SkippedEntry {
+                    path: p.clone(),
+                    reason: err.root_cause().to_string(),
+                });
+                Default::default()
             }
         }
     } else {
-        0
+        Default::default()
     }
 }
 
@@ -290,6 +499,8 @@ mod tests {
     use tempfile::tempdir;
 
     use super::*;
+    use crate::dir_size::Category;
+    use crate::venv::test_utils::{mock_package, mock_venv};
     use std::{collections::HashSet, fs::File};
 
     #[test]
@@ -370,6 +581,24 @@ Dynamic: license-file"
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_parse_metadata_folded_license() -> Result<()> {
+        let dir = tempdir().unwrap();
+        let dist_info_path = dir.path().join("legacy-1.0.dist-info");
+        fs::create_dir(&dist_info_path).unwrap();
+        let contents = "Metadata-Version: 2.1\nName: legacy\nVersion: 1.0\n\
+            License: GNU GENERAL PUBLIC LICENSE\n        Version 3, 29 June 2007\n        \n\
+            Platform: UNKNOWN\n\nDescription\n";
+        fs::write(dist_info_path.join("METADATA"), contents).unwrap();
+
+        let metadata = parse_metadata(dist_info_path)?;
+        assert_eq!(
+            metadata.license.as_deref(),
+            Some("GNU GENERAL PUBLIC LICENSE\nVersion 3, 29 June 2007\n")
+        );
+        Ok(())
+    }
+
     // TODO: proper tests
     //     #[test]
     //     fn test_parse_from_dir() {
@@ -457,6 +686,153 @@ Dynamic: license-file"
     //         assert_eq!(venv.path, venv_dir.path().to_path_buf());
     //     }
 
+    #[test]
+    fn test_parse_incremental() -> Result<()> {
+        let dir = tempdir().unwrap();
+        let site_packages = mock_venv(dir.path());
+        mock_package(
+            &site_packages,
+            "alpha",
+            "1.0",
+            &[("alpha/__init__.py", "a = 1")],
+        );
+        let beta = mock_package(
+            &site_packages,
+            "beta",
+            "2.0",
+            &[("beta/__init__.py", "b = 2")],
+        );
+
+        let mut cached = VenvParser::parse_from_dir(dir.path().to_path_buf())?;
+        assert_eq!(cached.num_dist_info_packages, 2);
+
+        // an unchanged package must be carried over instead of being parsed again
+        let alpha = cached
+            .packages
+            .iter_mut()
+            .find(|p| p.name == "alpha")
+            .unwrap();
+        alpha.metadata.summary = "cached".to_string();
+
+        fs::remove_dir_all(&beta)?;
+        fs::remove_dir_all(site_packages.join("beta"))?;
+        mock_package(
+            &site_packages,
+            "gamma",
+            "3.0",
+            &[("gamma/__init__.py", "c = 3")],
+        );
+
+        let refreshed = VenvParser::parse_incremental(&cached)?;
+        let names: HashSet<&str> = refreshed.packages.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, HashSet::from(["alpha", "gamma"]));
+        assert_eq!(refreshed.num_dist_info_packages, 2);
+
+        let alpha = refreshed
+            .packages
+            .iter()
+            .find(|p| p.name == "alpha")
+            .unwrap();
+        assert_eq!(alpha.metadata.summary, "cached");
+        Ok(())
+    }
+
+    #[test]
+    fn test_incremental_with_shared_inode() -> Result<()> {
+        let dir = tempdir().unwrap();
+        let site_packages = mock_venv(dir.path());
+        let blob = "x".repeat(10_000);
+        mock_package(
+            &site_packages,
+            "alpha",
+            "1.0",
+            &[("alpha/data.bin", blob.as_str())],
+        );
+        let beta = mock_package(
+            &site_packages,
+            "beta",
+            "1.0",
+            &[("beta/__init__.py", "b = 1")],
+        );
+        // both packages link the same file, each of them counts it as its own
+        fs::hard_link(
+            site_packages.join("alpha/data.bin"),
+            site_packages.join("beta/data.bin"),
+        )?;
+        let cached = VenvParser::parse_from_dir(dir.path().to_path_buf())?;
+        assert!(cached.packages.iter().all(|p| p.usage.unique >= 10_000));
+
+        fs::remove_dir_all(&beta)?;
+        fs::remove_dir_all(site_packages.join("beta"))?;
+        let refreshed = VenvParser::parse_incremental(&cached)?;
+        let parsed = VenvParser::parse_from_dir(dir.path().to_path_buf())?;
+        assert_eq!(refreshed.usage, parsed.usage);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unreadable_entries_are_kept() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempdir().unwrap();
+        let site_packages = mock_venv(dir.path());
+        mock_package(&site_packages, "alpha", "1.0", &[("alpha.py", "a = 1")]);
+        let locked = dir.path().join("share/data");
+        fs::create_dir_all(&locked)?;
+        fs::write(locked.join("blob.bin"), "0123")?;
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o000))?;
+        // root can read anything, there's nothing to skip then
+        if fs::read_dir(&locked).is_ok() {
+            return Ok(());
+        }
+
+        let venv = VenvParser::parse_from_dir(dir.path().to_path_buf())?;
+        let skipped: Vec<&Path> = venv.skipped.iter().map(|s| s.path.as_path()).collect();
+        assert_eq!(skipped, [locked.as_path()]);
+
+        // still unreadable after a refresh that re-parses another package
+        mock_package(&site_packages, "beta", "2.0", &[("beta.py", "b = 2")]);
+        let refreshed = VenvParser::parse_incremental(&venv)?;
+        assert_eq!(refreshed.skipped, venv.skipped);
+
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o755))?;
+        let refreshed = VenvParser::parse_incremental(&refreshed)?;
+        assert!(refreshed.skipped.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_size_breakdown() -> Result<()> {
+        let dir = tempdir().unwrap();
+        let site_packages = mock_venv(dir.path());
+        mock_package(
+            &site_packages,
+            "alpha",
+            "1.0",
+            &[
+                ("alpha/__init__.py", "a = 1"),
+                ("alpha/__pycache__/__init__.cpython-313.pyc", "0123456789"),
+                ("alpha/tests/test_alpha.py", "def test(): ..."),
+                ("alpha/_speedups.cpython-313-x86_64-linux-gnu.so", "ELF"),
+            ],
+        );
+
+        let venv = VenvParser::parse_from_dir(dir.path().to_path_buf())?;
+        let alpha = &venv.packages[0];
+        assert_eq!(alpha.breakdown.get(Category::Sources), 5);
+        assert_eq!(alpha.breakdown.get(Category::Bytecode), 10);
+        assert_eq!(alpha.breakdown.get(Category::Tests), 15);
+        assert_eq!(alpha.breakdown.get(Category::Native), 3);
+        assert!(alpha.breakdown.get(Category::DistInfo) > 0);
+        assert_eq!(alpha.breakdown.total(), alpha.size);
+
+        // pyvenv.cfg is outside of the packages
+        assert_eq!(venv.breakdown.total(), venv.size);
+        assert!(venv.breakdown.get(Category::Data) > 0);
+        Ok(())
+    }
+
     #[test]
     fn test_metadata_builder_build() {
         let mut builder = MetadataBuilder::new();
@@ -0,0 +1,213 @@
+//! Reading the `RECORD` file of installed distributions.
+
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    fs::{self, File},
+    io,
+    path::Path,
+};
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use color_eyre::{Result, eyre::WrapErr};
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+/// Hash of a file as written in `RECORD`, e.g. `sha256=<urlsafe base64 digest>`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RecordHash {
+    pub algorithm: String,
+    pub digest: String,
+}
+
+impl RecordHash {
+    /// Hashes the file at `path` with the algorithm of this hash and compares the digests.
+    /// Fails for algorithms other than sha256, sha384 and sha512.
+    pub fn matches_file(&self, path: &Path) -> io::Result<bool> {
+        let digest = match self.algorithm.as_str() {
+            "sha256" => hash_file::<Sha256>(path)?,
+            "sha384" => hash_file::<Sha384>(path)?,
+            "sha512" => hash_file::<Sha512>(path)?,
+            other => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    format!("unsupported hash algorithm {other}"),
+                ));
+            }
+        };
+        Ok(digest == self.digest)
+    }
+
+    /// The digest as lowercase hex, the encoding SBOM formats use. `None` if it isn't valid
+    /// base64.
+    pub fn hex(&self) -> Option<String> {
+        let bytes = URL_SAFE_NO_PAD
+            .decode(self.digest.trim_end_matches('='))
+            .ok()?;
+        Some(bytes.iter().map(|b| format!("{b:02x}")).collect())
+    }
+}
+
+/// A line of `RECORD`. Hash and size are empty for the `RECORD` itself and usually for
+/// bytecode that was compiled after install.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordEntry {
+    /// Path relative to site-packages with `/` separators, can climb out with `..` for scripts
+    pub path: String,
+    pub hash: Option<RecordHash>,
+    pub size: Option<u64>,
+}
+
+impl RecordEntry {
+    /// The entry is a file of the dist-info directory.
+    pub fn is_dist_info(&self) -> bool {
+        self.path
+            .split('/')
+            .next()
+            .is_some_and(|first| first.ends_with(".dist-info"))
+    }
+}
+
+/// The `RECORD` of each distribution of a site-packages directory, read at most once. Everything
+/// a parse needs from the `RECORD` files goes through one of these.
+#[derive(Debug, Default)]
+pub struct Records {
+    /// By the name of the dist-info directory, `None` without a readable `RECORD`
+    by_name: HashMap<OsString, Option<Vec<RecordEntry>>>,
+}
+
+impl Records {
+    /// The `RECORD` of `dist_info`, read unless it was read already.
+    pub fn read(&mut self, dist_info: &Path) -> Option<&[RecordEntry]> {
+        let name = dist_info.file_name().unwrap_or_default().to_os_string();
+        self.by_name
+            .entry(name)
+            .or_insert_with(|| read_record(dist_info).ok())
+            .as_deref()
+    }
+}
+
+pub fn read_record(dist_info: &Path) -> Result<Vec<RecordEntry>> {
+    let record_path = dist_info.join("RECORD");
+    let contents = fs::read_to_string(&record_path)
+        .with_context(|| format!("Failed to read {}", record_path.display()))?;
+    Ok(parse_record(&contents))
+}
+
+pub fn parse_record(contents: &str) -> Vec<RecordEntry> {
+    contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| {
+            let fields = split_csv_line(line);
+            let path = fields.first()?.clone();
+            let hash = fields
+                .get(1)
+                .and_then(|h| h.split_once('='))
+                .map(|(algorithm, digest)| RecordHash {
+                    algorithm: algorithm.to_string(),
+                    digest: digest.to_string(),
+                });
+            let size = fields.get(2).and_then(|s| s.parse().ok());
+            Some(RecordEntry { path, hash, size })
+        })
+        .collect()
+}
+
+/// The sha256 digest of a file in the encoding `RECORD` uses.
+pub fn file_digest(path: &Path) -> io::Result<String> {
+    hash_file::<Sha256>(path)
+}
+
+fn hash_file<D: Digest + io::Write>(path: &Path) -> io::Result<String> {
+    let mut hasher = D::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(URL_SAFE_NO_PAD.encode(hasher.finalize()))
+}
+
+/// The sha256 digest of `bytes` in the encoding `RECORD` uses.
+pub fn digest(bytes: &[u8]) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(bytes))
+}
+
+/// RECORD is a CSV file, paths with commas or quotes in them are quoted.
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.trim_end_matches('\r').chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_record() {
+        let record = "\
+numpy/__init__.py,sha256=LtD0jLbS0rYPkv-yNkRlnIa0QWlOe6HFDM7FIHQGjt0,22285
+\"odd,name.py\",sha256=abc,3
+numpy-2.2.4.dist-info/RECORD,,
+../../../bin/f2py,sha256=def,236
+";
+        let entries = parse_record(record);
+        assert_eq!(entries.len(), 4);
+        assert_eq!(
+            entries[0].hash.as_ref().unwrap().hex().unwrap(),
+            "2ed0f48cb6d2d2b60f92ffb23644659c86b441694e7ba1c50ccec52074068edd"
+        );
+        assert_eq!(entries[0].path, "numpy/__init__.py");
+        assert_eq!(
+            entries[0].hash,
+            Some(RecordHash {
+                algorithm: "sha256".to_string(),
+                digest: "LtD0jLbS0rYPkv-yNkRlnIa0QWlOe6HFDM7FIHQGjt0".to_string(),
+            })
+        );
+        assert_eq!(entries[0].size, Some(22285));
+        assert_eq!(entries[1].path, "odd,name.py");
+        assert_eq!(entries[2].hash, None);
+        assert_eq!(entries[2].size, None);
+        assert!(entries[2].is_dist_info());
+        assert!(!entries[3].is_dist_info());
+    }
+
+    #[test]
+    fn test_digest() {
+        // hash of an empty `py.typed` as pip writes it
+        assert_eq!(digest(b""), "47DEQpj8HBSa-_TImW-5JCeuQeRkm5NMpJWZG3hSuFU");
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.py");
+        fs::write(&path, "a = 1\n").unwrap();
+        assert_eq!(file_digest(&path).unwrap(), digest(b"a = 1\n"));
+
+        let hash = |algorithm: &str, digest: &str| RecordHash {
+            algorithm: algorithm.to_string(),
+            digest: digest.to_string(),
+        };
+        assert!(
+            hash("sha256", &digest(b"a = 1\n"))
+                .matches_file(&path)
+                .unwrap()
+        );
+        assert!(
+            !hash("sha256", &digest(b"a = 2\n"))
+                .matches_file(&path)
+                .unwrap()
+        );
+        assert!(hash("md5", "abc").matches_file(&path).is_err());
+    }
+}
@@ -0,0 +1,67 @@
+//! Helpers for building fake virtual environments in tests.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use super::record::digest;
+
+/// Creates a minimal virtual environment with a `pyvenv.cfg` and an empty site-packages under
+/// `root`. Returns the path of the site-packages directory.
+pub fn mock_venv(root: &Path) -> PathBuf {
+    let pyvenv_cfg = "home = /usr/local/bin
+include-system-site-packages = false
+version = 3.13.2
+executable = /usr/local/bin/python3.13
+";
+    fs::write(root.join("pyvenv.cfg"), pyvenv_cfg).unwrap();
+
+    let site_packages = site_packages(root);
+    fs::create_dir_all(&site_packages).unwrap();
+    site_packages
+}
+
+/// Path of the site-packages directory of a venv created with [`mock_venv`].
+pub fn site_packages(root: &Path) -> PathBuf {
+    if cfg!(windows) {
+        root.join("Lib").join("site-packages")
+    } else {
+        root.join("lib").join("python3.13").join("site-packages")
+    }
+}
+
+/// Installs a fake package into `site_packages`. `files` are paths relative to site-packages
+/// with their contents. A `METADATA` and a `RECORD` listing the files are written to the
+/// dist-info directory. Returns the dist-info path.
+pub fn mock_package(
+    site_packages: &Path,
+    name: &str,
+    version: &str,
+    files: &[(&str, &str)],
+) -> PathBuf {
+    let dist_info = site_packages.join(format!("{name}-{version}.dist-info"));
+    fs::create_dir_all(&dist_info).unwrap();
+
+    let metadata = format!(
+        "Metadata-Version: 2.4\nName: {name}\nVersion: {version}\nSummary: {name} package\n"
+    );
+    fs::write(dist_info.join("METADATA"), metadata).unwrap();
+
+    let mut record = String::new();
+    for (file, contents) in files {
+        let path = site_packages.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, contents).unwrap();
+        record.push_str(&format!(
+            "{file},sha256={},{}\n",
+            digest(contents.as_bytes()),
+            contents.len()
+        ));
+    }
+    record.push_str(&format!("{name}-{version}.dist-info/METADATA,,\n"));
+    record.push_str(&format!("{name}-{version}.dist-info/RECORD,,\n"));
+    fs::write(dist_info.join("RECORD"), record).unwrap();
+
+    dist_info
+}
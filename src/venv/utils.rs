@@ -11,6 +11,25 @@ pub fn get_python_dir(lib_dir: PathBuf) -> io::Result<Option<PathBuf>> {
     Ok(entries.next().map(|e| e.path()))
 }
 
+/// Normalizes a distribution name as in PEP 503, so `Foo.Bar`, `foo_bar` and `foo-bar` are the
+/// same distribution.
+pub fn canonical_name(name: &str) -> String {
+    let mut canonical = String::with_capacity(name.len());
+    let mut separator = false;
+    for c in name.chars() {
+        if matches!(c, '-' | '_' | '.') {
+            separator = true;
+        } else {
+            if separator && !canonical.is_empty() {
+                canonical.push('-');
+            }
+            separator = false;
+            canonical.push(c.to_ascii_lowercase());
+        }
+    }
+    canonical
+}
+
 pub fn get_dist_info_packages(site_packages: PathBuf) -> Result<Vec<PathBuf>> {
     let (dist_info_dirs, _) = get_packages(site_packages)?;
 
@@ -84,11 +103,33 @@ pub fn get_packages(site_packages: PathBuf) -> Result<(Vec<PathBuf>, Vec<PathBuf
 }
 
 pub fn search_venvs(path: PathBuf) -> Vec<PathBuf> {
+    search_venvs_within(path, usize::MAX)
+}
+
+/// Like [`search_venvs`], but doesn't descend more than `max_depth` directories.
+pub fn search_venvs_within(path: PathBuf, max_depth: usize) -> Vec<PathBuf> {
     let mut venv_paths = Vec::new();
-    for entry in WalkDir::new(path).into_iter().filter_map(|e| e.ok()) {
+    for entry in WalkDir::new(path)
+        .max_depth(max_depth)
+        .into_iter()
+        .filter_map(|e| e.ok())
+    {
         if entry.file_name().to_str().unwrap_or_default() == "pyvenv.cfg" {
             venv_paths.push(entry.path().parent().unwrap().to_path_buf());
         }
     }
     venv_paths
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canonical_name() {
+        assert_eq!(canonical_name("Foo.Bar"), "foo-bar");
+        assert_eq!(canonical_name("foo__bar"), "foo-bar");
+        assert_eq!(canonical_name("scikit-learn"), "scikit-learn");
+        assert_eq!(canonical_name("zope.interface"), "zope-interface");
+    }
+}
@@ -0,0 +1,464 @@
+//! PEP 440 versions, so installed versions can be compared with advisories and lock files.
+
+use std::{cmp::Ordering, fmt, str::FromStr};
+
+use color_eyre::eyre::{self, eyre};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PreKind {
+    Alpha,
+    Beta,
+    Rc,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum LocalSegment {
+    Number(u64),
+    Text(String),
+}
+
+impl Ord for LocalSegment {
+    /// Numbers sort after text, numbers by value and text lexicographically.
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (LocalSegment::Number(a), LocalSegment::Number(b)) => a.cmp(b),
+            (LocalSegment::Text(a), LocalSegment::Text(b)) => a.cmp(b),
+            (LocalSegment::Number(_), LocalSegment::Text(_)) => Ordering::Greater,
+            (LocalSegment::Text(_), LocalSegment::Number(_)) => Ordering::Less,
+        }
+    }
+}
+
+impl PartialOrd for LocalSegment {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Pre-release rank, pre-release, post-release and dev-release, compared in that order.
+type SuffixKey = (u8, Option<(PreKind, u64)>, Option<u64>, (u8, u64));
+
+/// A version like `1!2.0.0rc1.post2.dev3+local.1`. Alternative spellings such as `2.0-alpha`
+/// or `2.0-1` are accepted and normalized.
+#[derive(Debug, Clone)]
+pub struct Version {
+    epoch: u64,
+    release: Vec<u64>,
+    pre: Option<(PreKind, u64)>,
+    post: Option<u64>,
+    dev: Option<u64>,
+    local: Vec<LocalSegment>,
+}
+
+impl Version {
+    pub fn release(&self) -> &[u64] {
+        &self.release
+    }
+
+    pub fn is_prerelease(&self) -> bool {
+        self.pre.is_some() || self.dev.is_some()
+    }
+
+    /// The version without its local label, `1.0+cpu` becomes `1.0`.
+    pub fn without_local(&self) -> Version {
+        Version {
+            local: Vec::new(),
+            ..self.clone()
+        }
+    }
+
+    /// Whether the version satisfies every comma separated specifier in `specifiers`, like
+    /// `>=1.0,!=1.3.*,<2`. Pre-releases are matched like any other version.
+    pub fn satisfies(&self, specifiers: &str) -> eyre::Result<bool> {
+        for spec in specifiers
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+        {
+            if !self.satisfies_one(spec)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    fn satisfies_one(&self, spec: &str) -> eyre::Result<bool> {
+        let op_len = spec
+            .find(|c: char| !matches!(c, '=' | '!' | '<' | '>' | '~'))
+            .ok_or_else(|| eyre!("Invalid specifier: {spec}"))?;
+        let (op, version) = (&spec[..op_len], spec[op_len..].trim());
+        if op == "===" {
+            return Ok(self.to_string() == version);
+        }
+        if let Some(prefix) = version.strip_suffix(".*") {
+            let prefix: Version = prefix.parse()?;
+            // `1` is `1.0` here, the release is padded with zeros to the prefix
+            let padded =
+                (0..prefix.release.len()).map(|i| self.release.get(i).copied().unwrap_or(0));
+            let matches = self.epoch == prefix.epoch && padded.eq(prefix.release.iter().copied());
+            return match op {
+                "==" => Ok(matches),
+                "!=" => Ok(!matches),
+                _ => Err(eyre!("Invalid specifier: {spec}")),
+            };
+        }
+        let other: Version = version.parse()?;
+        // a specifier without a local label ignores the local label of the version
+        let this = if other.local.is_empty() {
+            self.without_local()
+        } else {
+            self.clone()
+        };
+        Ok(match op {
+            "==" => this == other,
+            "!=" => this != other,
+            ">=" => this >= other,
+            "<=" => this <= other,
+            // `>1.0` doesn't allow `1.0.post1`, unless it names a post-release itself
+            ">" => this > other && !(other.post.is_none() && this.is_post_release_of(&other)),
+            // `<2.0` doesn't allow `2.0rc1`, unless it names a pre-release itself
+            "<" => {
+                this < other
+                    && !(!other.is_prerelease()
+                        && this.is_prerelease()
+                        && this.same_release(&other))
+            }
+            "~=" => {
+                // `~=1.4.5` is `>=1.4.5,==1.4.*`
+                let len = other.release.len().saturating_sub(1).max(1);
+                this >= other
+                    && this.epoch == other.epoch
+                    && this.release.len() >= len
+                    && this.release[..len] == other.release[..len]
+            }
+            _ => return Err(eyre!("Invalid specifier: {spec}")),
+        })
+    }
+
+    fn same_release(&self, other: &Version) -> bool {
+        self.epoch == other.epoch && self.trimmed_release() == other.trimmed_release()
+    }
+
+    /// Whether this is `other` with a post-release added.
+    fn is_post_release_of(&self, other: &Version) -> bool {
+        self.post.is_some() && self.same_release(other) && self.pre == other.pre
+    }
+
+    /// Release numbers without the trailing zeros, `1.0.0` and `1` are the same release.
+    fn trimmed_release(&self) -> &[u64] {
+        let len = self
+            .release
+            .iter()
+            .rposition(|&n| n != 0)
+            .map_or(0, |i| i + 1);
+        &self.release[..len]
+    }
+
+    /// Key of the pre-release, dev, post part in the order PEP 440 gives them.
+    fn suffix_key(&self) -> SuffixKey {
+        // `1.0.dev1` comes before `1.0a1`, `1.0` after every pre-release
+        let pre_rank = match (self.pre, self.post, self.dev) {
+            (Some(_), _, _) => 1,
+            (None, None, Some(_)) => 0,
+            _ => 2,
+        };
+        let dev = self.dev.map_or((1, 0), |n| (0, n));
+        (pre_rank, self.pre, self.post, dev)
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.epoch
+            .cmp(&other.epoch)
+            .then_with(|| self.trimmed_release().cmp(other.trimmed_release()))
+            .then_with(|| self.suffix_key().cmp(&other.suffix_key()))
+            .then_with(|| self.local.cmp(&other.local))
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Version {}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.epoch != 0 {
+            write!(f, "{}!", self.epoch)?;
+        }
+        let release: Vec<String> = self.release.iter().map(u64::to_string).collect();
+        write!(f, "{}", release.join("."))?;
+        if let Some((kind, n)) = self.pre {
+            let kind = match kind {
+                PreKind::Alpha => "a",
+                PreKind::Beta => "b",
+                PreKind::Rc => "rc",
+            };
+            write!(f, "{kind}{n}")?;
+        }
+        if let Some(n) = self.post {
+            write!(f, ".post{n}")?;
+        }
+        if let Some(n) = self.dev {
+            write!(f, ".dev{n}")?;
+        }
+        if !self.local.is_empty() {
+            let local: Vec<String> = self
+                .local
+                .iter()
+                .map(|s| match s {
+                    LocalSegment::Number(n) => n.to_string(),
+                    LocalSegment::Text(t) => t.clone(),
+                })
+                .collect();
+            write!(f, "+{}", local.join("."))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Version {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lower = s.trim().to_ascii_lowercase();
+        let invalid = || eyre!("Invalid version: {s}");
+
+        let (public, local) = match lower.split_once('+') {
+            Some((public, local)) => (public, Some(local)),
+            None => (lower.as_str(), None),
+        };
+        let public = public.strip_prefix('v').unwrap_or(public);
+        let (epoch, public) = match public.split_once('!') {
+            Some((epoch, rest)) => (epoch.parse().map_err(|_| invalid())?, rest),
+            None => (0, public),
+        };
+
+        let mut cursor = Cursor { rest: public };
+        let mut release = vec![cursor.number().ok_or_else(invalid)?];
+        while cursor.rest.starts_with('.')
+            && cursor.rest[1..].starts_with(|c: char| c.is_ascii_digit())
+        {
+            cursor.rest = &cursor.rest[1..];
+            release.push(cursor.number().ok_or_else(invalid)?);
+        }
+
+        let pre = cursor.pre();
+        let post = cursor.post();
+        let dev = cursor.dev();
+        if !cursor.rest.is_empty() {
+            return Err(invalid());
+        }
+
+        let local = match local {
+            Some(local) => {
+                let segments: Vec<LocalSegment> = local
+                    .split(['.', '-', '_'])
+                    .map(|seg| match seg.parse() {
+                        Ok(n) => LocalSegment::Number(n),
+                        Err(_) => LocalSegment::Text(seg.to_string()),
+                    })
+                    .collect();
+                let valid = segments.iter().all(|seg| match seg {
+                    LocalSegment::Text(t) => {
+                        !t.is_empty() && t.chars().all(|c| c.is_ascii_alphanumeric())
+                    }
+                    LocalSegment::Number(_) => true,
+                });
+                if !valid {
+                    return Err(invalid());
+                }
+                segments
+            }
+            None => Vec::new(),
+        };
+
+        Ok(Version {
+            epoch,
+            release,
+            pre,
+            post,
+            dev,
+            local,
+        })
+    }
+}
+
+/// Reads the optional parts after the release numbers.
+struct Cursor<'a> {
+    rest: &'a str,
+}
+
+impl Cursor<'_> {
+    fn number(&mut self) -> Option<u64> {
+        let end = self
+            .rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(self.rest.len());
+        let n = self.rest[..end].parse().ok()?;
+        self.rest = &self.rest[end..];
+        Some(n)
+    }
+
+    fn skip_separator(&mut self) {
+        if self.rest.starts_with(['.', '-', '_']) {
+            self.rest = &self.rest[1..];
+        }
+    }
+
+    /// Takes one of `words` after an optional separator, leaves the cursor alone otherwise.
+    fn word(&mut self, words: &[&str]) -> Option<String> {
+        let start = self.rest;
+        self.skip_separator();
+        // longer spellings first so `rc` isn't read as `r`
+        let mut sorted = words.to_vec();
+        sorted.sort_by_key(|w| std::cmp::Reverse(w.len()));
+        match sorted.iter().find(|w| self.rest.starts_with(**w)) {
+            Some(w) => {
+                self.rest = &self.rest[w.len()..];
+                Some(w.to_string())
+            }
+            None => {
+                self.rest = start;
+                None
+            }
+        }
+    }
+
+    /// Number after a word, `1.0a` is `1.0a0`.
+    fn word_number(&mut self) -> u64 {
+        let start = self.rest;
+        self.skip_separator();
+        self.number().unwrap_or_else(|| {
+            self.rest = start;
+            0
+        })
+    }
+
+    fn pre(&mut self) -> Option<(PreKind, u64)> {
+        let word = self.word(&["alpha", "beta", "preview", "pre", "rc", "a", "b", "c"])?;
+        let kind = match word.as_str() {
+            "a" | "alpha" => PreKind::Alpha,
+            "b" | "beta" => PreKind::Beta,
+            _ => PreKind::Rc,
+        };
+        Some((kind, self.word_number()))
+    }
+
+    fn post(&mut self) -> Option<u64> {
+        // `1.0-1` is an implicit post release
+        if let Some(rest) = self.rest.strip_prefix('-')
+            && rest.starts_with(|c: char| c.is_ascii_digit())
+        {
+            self.rest = rest;
+            return self.number();
+        }
+        self.word(&["post", "rev", "r"])?;
+        Some(self.word_number())
+    }
+
+    fn dev(&mut self) -> Option<u64> {
+        self.word(&["dev"])?;
+        Some(self.word_number())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(s: &str) -> Version {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse_and_normalize() {
+        assert_eq!(v("1.0").to_string(), "1.0");
+        assert_eq!(v("v1.0-Alpha.2").to_string(), "1.0a2");
+        assert_eq!(v("1.0c1").to_string(), "1.0rc1");
+        assert_eq!(v("1.0-1").to_string(), "1.0.post1");
+        assert_eq!(v("1.0.rev").to_string(), "1.0.post0");
+        assert_eq!(v("2!1.0.dev3+ubuntu-1").to_string(), "2!1.0.dev3+ubuntu.1");
+        assert!("1.0.foo".parse::<Version>().is_err());
+        assert!("".parse::<Version>().is_err());
+        assert!("1.0+".parse::<Version>().is_err());
+    }
+
+    #[test]
+    fn test_ordering() {
+        let ordered = [
+            "1.0.dev1",
+            "1.0a1.dev1",
+            "1.0a1",
+            "1.0a1.post1",
+            "1.0b1",
+            "1.0rc1",
+            "1.0",
+            "1.0+local",
+            "1.0.post1.dev1",
+            "1.0.post1",
+            "1.1",
+            "1.10",
+            "1!0.1",
+        ];
+        for pair in ordered.windows(2) {
+            assert!(v(pair[0]) < v(pair[1]), "{} < {}", pair[0], pair[1]);
+        }
+        assert_eq!(v("1.0"), v("1.0.0"));
+        assert!(v("1.0+abc") < v("1.0+1"));
+    }
+
+    #[test]
+    fn test_satisfies() {
+        let satisfies = |version: &str, spec: &str| v(version).satisfies(spec).unwrap();
+        assert!(satisfies("1.4.5", ">=1.0,<2"));
+        assert!(!satisfies("2.0", ">=1.0, <2"));
+        assert!(satisfies("1.4.5", "~=1.4.2"));
+        assert!(!satisfies("1.5.0", "~=1.4.2"));
+        assert!(satisfies("1.9", "~=1.4"));
+        assert!(satisfies("1.3.2", "==1.3.*"));
+        assert!(!satisfies("1.3.2", "!=1.3.*"));
+        assert!(satisfies("1.0+cpu", "==1.0"));
+        assert!(!satisfies("1.0", "==1.0+cpu"));
+        assert!(satisfies("1.0", "===1.0"));
+        assert!(satisfies("1.0", ""));
+        assert!(v("1.0").satisfies("=>1.0").is_err());
+    }
+
+    #[test]
+    fn test_exclusive_comparisons() {
+        let satisfies = |version: &str, spec: &str| v(version).satisfies(spec).unwrap();
+        // pre-releases of the version itself are left out of `<`
+        assert!(!satisfies("2.0rc1", "<2.0"));
+        assert!(!satisfies("2.0.dev1", "<2.0"));
+        assert!(!satisfies("2.0.0a1", "<2"));
+        assert!(satisfies("1.9rc1", "<2.0"));
+        assert!(satisfies("2.0a1", "<2.0rc1"));
+        // and post-releases from `>`
+        assert!(!satisfies("1.0.post1", ">1.0"));
+        assert!(!satisfies("1.0+local", ">1.0"));
+        assert!(satisfies("1.0.1", ">1.0"));
+        assert!(satisfies("1.0.post2", ">1.0.post1"));
+        assert!(satisfies("1.0.post1", ">1.0rc1"));
+    }
+
+    #[test]
+    fn test_prefix_match_pads_release() {
+        let satisfies = |version: &str, spec: &str| v(version).satisfies(spec).unwrap();
+        assert!(satisfies("1", "==1.0.*"));
+        assert!(satisfies("1.0", "==1.0.0.*"));
+        assert!(!satisfies("2", "!=2.0.*"));
+        assert!(!satisfies("1.1", "==1.0.*"));
+        assert!(satisfies("1.0rc1", "==1.0.*"));
+    }
+}
@@ -0,0 +1,285 @@
+//! Reading the `WHEEL` file of installed distributions and finding their compiled modules.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use bincode::{Decode, Encode};
+use walkdir::WalkDir;
+
+use crate::venv::record::RecordEntry;
+
+/// Contents of a `WHEEL` file.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Encode, Decode)]
+pub struct WheelInfo {
+    pub wheel_version: Option<String>,
+    pub generator: Option<String>,
+    pub root_is_purelib: Option<bool>,
+    /// Tags as written, possibly compressed like `py2.py3-none-any`
+    pub tags: Vec<String>,
+}
+
+/// A single `python-abi-platform` wheel tag.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tag {
+    pub python: String,
+    pub abi: String,
+    pub platform: String,
+}
+
+impl Tag {
+    /// Runs on any platform and doesn't need a particular interpreter ABI.
+    pub fn is_pure(&self) -> bool {
+        self.abi == "none" && self.platform == "any"
+    }
+}
+
+impl WheelInfo {
+    /// The tags with the compressed sets expanded, `py2.py3-none-any` is `py2-none-any` and
+    /// `py3-none-any`. Malformed tags are left out.
+    pub fn expanded_tags(&self) -> Vec<Tag> {
+        let mut expanded = Vec::new();
+        for tag in &self.tags {
+            let mut parts = tag.rsplitn(3, '-');
+            let (Some(platforms), Some(abis), Some(pythons)) =
+                (parts.next(), parts.next(), parts.next())
+            else {
+                continue;
+            };
+            for python in pythons.split('.') {
+                for abi in abis.split('.') {
+                    for platform in platforms.split('.') {
+                        expanded.push(Tag {
+                            python: python.to_string(),
+                            abi: abi.to_string(),
+                            platform: platform.to_string(),
+                        });
+                    }
+                }
+            }
+        }
+        expanded
+    }
+
+    /// Platforms other than `any` the wheel was built for, e.g. `manylinux_2_17_x86_64`.
+    pub fn platforms(&self) -> Vec<String> {
+        unique(self.expanded_tags().into_iter().map(|t| t.platform), "any")
+    }
+
+    /// ABIs other than `none` the wheel was built for, e.g. `cp311` or `abi3`.
+    pub fn abis(&self) -> Vec<String> {
+        unique(self.expanded_tags().into_iter().map(|t| t.abi), "none")
+    }
+}
+
+/// Whether a distribution can be copied to another machine with the same Python.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Portability {
+    /// Only Python code and data
+    Pure,
+    /// Compiled for a platform or interpreter ABI
+    PlatformSpecific,
+    /// Neither a `WHEEL` file nor native files to tell
+    Unknown,
+}
+
+impl Portability {
+    pub fn name(self) -> &'static str {
+        match self {
+            Portability::Pure => "pure Python",
+            Portability::PlatformSpecific => "platform-specific",
+            Portability::Unknown => "unknown",
+        }
+    }
+}
+
+/// Native files always make a distribution platform-specific. Otherwise the wheel tags decide,
+/// `Root-Is-Purelib` only says where the files were installed.
+pub fn portability(wheel: Option<&WheelInfo>, native_files: &[String]) -> Portability {
+    if !native_files.is_empty() {
+        return Portability::PlatformSpecific;
+    }
+    match wheel {
+        Some(wheel) if wheel.expanded_tags().iter().all(Tag::is_pure) => Portability::Pure,
+        Some(_) => Portability::PlatformSpecific,
+        None => Portability::Unknown,
+    }
+}
+
+fn unique(values: impl Iterator<Item = String>, skip: &str) -> Vec<String> {
+    let mut unique: Vec<String> = Vec::new();
+    for value in values {
+        if value != skip && !unique.contains(&value) {
+            unique.push(value);
+        }
+    }
+    unique
+}
+
+pub fn parse_wheel(contents: &str) -> WheelInfo {
+    let mut wheel = WheelInfo::default();
+    for line in contents.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match key.trim() {
+            "Wheel-Version" => wheel.wheel_version = Some(value.to_string()),
+            "Generator" => wheel.generator = Some(value.to_string()),
+            "Root-Is-Purelib" => wheel.root_is_purelib = Some(value.eq_ignore_ascii_case("true")),
+            "Tag" => wheel.tags.push(value.to_string()),
+            _ => {}
+        }
+    }
+    wheel
+}
+
+/// The `WHEEL` file of `dist_info`, `None` for distributions that weren't installed from a
+/// wheel, like eggs or `setup.py develop`. A `WHEEL` that can't be read tells nothing either,
+/// the distribution's portability is unknown then.
+pub fn read_wheel(dist_info: &Path) -> Option<WheelInfo> {
+    let contents = fs::read_to_string(dist_info.join("WHEEL")).ok()?;
+    Some(parse_wheel(&contents))
+}
+
+/// A compiled extension module or a shared library bundled with one, like the versioned
+/// `.so.1` files auditwheel vendors into `<package>.libs`.
+pub fn is_native(path: &str) -> bool {
+    let file_name = path.rsplit('/').next().unwrap_or(path);
+    let mut parts = file_name.split('.').skip(1);
+    parts.any(|ext| matches!(ext, "so" | "pyd" | "dylib" | "dll"))
+}
+
+/// Paths of the native files of a distribution relative to site-packages, from its `RECORD`.
+/// Without a `RECORD` the package directory is walked instead.
+pub fn find_native_files(
+    record: Option<&[RecordEntry]>,
+    dist_info: &Path,
+    package: Option<&PathBuf>,
+) -> Vec<String> {
+    let mut native: Vec<String> = match record {
+        Some(entries) => entries
+            .iter()
+            .filter(|e| is_native(&e.path))
+            .map(|e| e.path.clone())
+            .collect(),
+        None => {
+            let Some(package) = package else {
+                return Vec::new();
+            };
+            let site_packages = dist_info.parent().unwrap_or(dist_info);
+            WalkDir::new(package)
+                .into_iter()
+                .filter_map(|e| e.ok())
+                .filter(|e| e.file_type().is_file())
+                .filter_map(|e| {
+                    let path = e.path().strip_prefix(site_packages).ok()?;
+                    let path = path.to_string_lossy().replace('\\', "/");
+                    is_native(&path).then_some(path)
+                })
+                .collect()
+        }
+    };
+    native.sort();
+    native
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_wheel() {
+        let wheel = parse_wheel(
+            "Wheel-Version: 1.0\nGenerator: bdist_wheel (0.41.2)\nRoot-Is-Purelib: false\n\
+             Tag: cp311-cp311-manylinux_2_17_x86_64.manylinux2014_x86_64\n",
+        );
+        assert_eq!(wheel.wheel_version.as_deref(), Some("1.0"));
+        assert_eq!(wheel.root_is_purelib, Some(false));
+        assert_eq!(
+            wheel.platforms(),
+            ["manylinux_2_17_x86_64", "manylinux2014_x86_64"]
+        );
+        assert_eq!(wheel.abis(), ["cp311"]);
+        assert!(!wheel.expanded_tags().iter().any(Tag::is_pure));
+
+        let pure = parse_wheel("Root-Is-Purelib: true\nTag: py2-none-any\nTag: py3-none-any\n");
+        assert_eq!(pure.expanded_tags().len(), 2);
+        assert!(pure.expanded_tags().iter().all(Tag::is_pure));
+        assert!(pure.platforms().is_empty());
+
+        let compressed = parse_wheel("Tag: py2.py3-none-any");
+        assert_eq!(compressed.expanded_tags(), pure.expanded_tags());
+
+        assert!(is_native("_cffi_backend.cpython-311-x86_64-linux-gnu.so"));
+        assert!(is_native("numpy.libs/libopenblas64_p-r0.3.23.so.1"));
+        assert!(is_native("markupsafe/_speedups.cp311-win_amd64.pyd"));
+        assert!(!is_native("sonic/solver.py"));
+        assert!(!is_native("pkg/so_what.txt"));
+
+        assert_eq!(portability(Some(&pure), &[]), Portability::Pure);
+        assert_eq!(
+            portability(Some(&wheel), &[]),
+            Portability::PlatformSpecific
+        );
+        assert_eq!(
+            portability(Some(&pure), &["alpha/_speedups.so".to_string()]),
+            Portability::PlatformSpecific
+        );
+        assert_eq!(portability(None, &[]), Portability::Unknown);
+    }
+
+    #[test]
+    fn test_multiple_tag_lines() {
+        // auditwheel writes one line per platform tag
+        let numpy = parse_wheel(
+            "Wheel-Version: 1.0\r\nGenerator: meson\r\nRoot-Is-Purelib: false\r\n\
+             Tag: cp312-cp312-manylinux_2_17_x86_64\r\n\
+             Tag: cp312-cp312-manylinux2014_x86_64\r\n",
+        );
+        assert_eq!(numpy.tags.len(), 2);
+        assert_eq!(
+            numpy.platforms(),
+            ["manylinux_2_17_x86_64", "manylinux2014_x86_64"]
+        );
+        assert_eq!(numpy.abis(), ["cp312"]);
+        let compressed = parse_wheel("Tag: cp312-cp312-manylinux_2_17_x86_64.manylinux2014_x86_64");
+        assert_eq!(numpy.expanded_tags(), compressed.expanded_tags());
+
+        let stable_abi = parse_wheel(
+            "Tag: cp38-abi3-manylinux_2_28_aarch64\nTag: cp38-abi3-musllinux_1_2_aarch64\n\
+             Tag: cp38-abi3-macosx_10_12_universal2\n",
+        );
+        assert_eq!(stable_abi.abis(), ["abi3"]);
+        assert_eq!(
+            stable_abi.platforms(),
+            [
+                "manylinux_2_28_aarch64",
+                "musllinux_1_2_aarch64",
+                "macosx_10_12_universal2"
+            ]
+        );
+        assert!(
+            stable_abi
+                .expanded_tags()
+                .iter()
+                .all(|t| t.python == "cp38")
+        );
+
+        // a pure tag next to a platform one still ties the wheel to the platform
+        let mixed = parse_wheel("Tag: py3-none-any\nTag: cp311-cp311-win_amd64\n");
+        assert_eq!(mixed.platforms(), ["win_amd64"]);
+        assert_eq!(mixed.abis(), ["cp311"]);
+        assert_eq!(
+            portability(Some(&mixed), &[]),
+            Portability::PlatformSpecific
+        );
+
+        // interpreter specific but without a compiled ABI
+        let py_only = parse_wheel("Tag: py3-none-manylinux1_x86_64\nTag: garbage\n");
+        assert_eq!(py_only.expanded_tags().len(), 1);
+        assert!(py_only.abis().is_empty());
+        assert_eq!(py_only.platforms(), ["manylinux1_x86_64"]);
+    }
+}